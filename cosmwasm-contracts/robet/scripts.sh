@@ -35,7 +35,7 @@ echo "Code ID: $CODE_ID"
 
 echo "=== Instantiating contract ==="
 INIT_TX=$(~/Desktop/garage/xion/build/xiond tx wasm instantiate $CODE_ID \
-  '{"admin_wallet":"'$ADMIN_WALLET'"}' \
+  '{"admin_wallet":"'$ADMIN_WALLET'","initial_whitelist":["'$DENOM'"]}' \
   --from $WALLET \
   --label "robet_v1.0.0" \
   --gas auto \
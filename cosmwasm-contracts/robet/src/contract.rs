@@ -1,59 +1,515 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
-    StdError, StdResult, Uint128, WasmMsg, Event,
+    entry_point, to_json_binary, to_json_vec, Addr, Api, Binary, CanonicalAddr, CosmosMsg,
+    Deps, DepsMut, Env, HexBinary, MessageInfo, Order, Reply, Response, StdError, StdResult,
+    Storage, SubMsg, Timestamp, Uint128, WasmMsg, Event,
 };
 use cw2::set_contract_version;
-use cw_storage_plus::Item;
+use cw20::{AllowanceResponse, BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw_storage_plus::{Item, Map};
 use cosmwasm_schema::{cw_serde, QueryResponses};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
 
 // Version info for migration
 const CONTRACT_NAME: &str = "crates.io:robet-cosmwasm";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Schema version attached to every emitted event as the `schema` attribute.
+/// Bump whenever the attributes of any event change so indexers can branch on it.
+pub const EVENT_SCHEMA_VERSION: u8 = 3;
+
+/// Creates an event of type `ty` carrying the `schema` attribute.
+fn new_event(ty: &str) -> Event {
+    Event::new(ty).add_attribute("schema", EVENT_SCHEMA_VERSION.to_string())
+}
+
 /// State storage for the contract configuration.
 #[cw_serde]
 pub struct Config {
-    /// The owner who can manage the whitelist.
+    /// The owner who controls the config and can manage the whitelist.
     pub owner: Addr,
     /// The wallet that receives deposited tokens.
     pub admin_wallet: Addr,
-    /// List of whitelisted token contract addresses.
-    pub whitelist: Vec<Addr>,
+    /// List of whitelisted CW20 contract addresses and native denoms, sorted lexically.
+    pub whitelist: Vec<String>,
+    /// Minimum CW20 decimals accepted when whitelisting a token.
+    pub min_decimals: u8,
+    /// Maximum CW20 decimals accepted when whitelisting a token.
+    pub max_decimals: u8,
+    /// A secondary role allowed to add and remove whitelisted tokens.
+    pub whitelist_manager: Addr,
+    /// Number of deposits processed so far; each deposit event carries the next value.
+    pub deposit_seq: u64,
+    /// Whether the owner may deposit tokens that are not whitelisted.
+    pub owner_bypass_enabled: bool,
+    /// Seconds a new admin wallet stays pending before it can be applied; 0 applies at once.
+    pub admin_change_delay: u64,
+    /// Admin wallet scheduled by `UpdateConfig`, waiting for `ApplyAdminWallet`.
+    pub pending_admin_wallet: Option<PendingAdminWallet>,
+    /// Tax the chain burns on native transfers, in basis points; 0 on untaxed chains.
+    pub native_tax_bps: u16,
+    /// Contract notified with a `PostDepositHookMsg` after every deposit, if set.
+    pub post_deposit_hook: Option<Addr>,
+    /// Bitfield of optional features, drawn from `KNOWN_FEATURE_FLAGS`.
+    #[serde(default)]
+    pub feature_flags: u64,
+}
+
+/// Most coins a deposit may attach before it is rejected up front with `TooManyFunds`.
+/// This only gives an earlier, clearer error: native deposits must attach exactly one coin
+/// and CW20 deposits none, which the later checks enforce anyway.
+pub const MAX_DEPOSIT_FUNDS: u32 = 4;
+
+/// Message sent to the configured post-deposit hook contract.
+#[cw_serde]
+pub enum PostDepositHookMsg {
+    /// A deposit was made; the hook can credit it in the same transaction.
+    Deposit(DepositHookPayload),
+}
+
+/// Details of a deposit passed to the post-deposit hook.
+#[cw_serde]
+pub struct DepositHookPayload {
+    /// The party the deposit is credited to: the beneficiary, or else the sender.
+    pub user: Addr,
+    /// The deposited CW20 contract address or native denom.
+    pub token: String,
+    /// The deposited amount, before any native transfer tax.
+    pub amount: Uint128,
+}
+
+/// An admin wallet change waiting for its timelock to elapse.
+#[cw_serde]
+pub struct PendingAdminWallet {
+    /// The wallet that will receive deposits once applied.
+    pub wallet: Addr,
+    /// Block time from which `ApplyAdminWallet` succeeds.
+    pub effective: Timestamp,
+}
+
+/// Bits of `Config::feature_flags` that are assigned to a feature. New optional behavior
+/// gets the next free bit and is gated on `Config::feature_enabled`, so it can be turned
+/// on without a config migration. No bit is assigned yet.
+pub const KNOWN_FEATURE_FLAGS: u64 = 0;
+
+impl Config {
+    /// Returns a config with an empty whitelist, the owner as whitelist manager and every
+    /// other setting at its default.
+    pub fn new(owner: Addr, admin_wallet: Addr) -> Self {
+        Self {
+            owner: owner.clone(),
+            admin_wallet,
+            whitelist: vec![],
+            min_decimals: 0,
+            max_decimals: u8::MAX,
+            whitelist_manager: owner,
+            deposit_seq: 0,
+            owner_bypass_enabled: false,
+            admin_change_delay: 0,
+            pending_admin_wallet: None,
+            native_tax_bps: 0,
+            post_deposit_hook: None,
+            feature_flags: 0,
+        }
+    }
+
+    /// Returns true if every bit of `flag` is set in `feature_flags`.
+    pub fn feature_enabled(&self, flag: u64) -> bool {
+        self.feature_flags & flag == flag
+    }
+
+    /// Returns true if `addr` may add or remove whitelisted tokens.
+    pub fn can_manage_whitelist(&self, addr: &Addr) -> bool {
+        *addr == self.owner || *addr == self.whitelist_manager
+    }
+
+    /// Returns true if `addr` may deposit tokens that are not whitelisted.
+    pub fn owner_bypass(&self, addr: &Addr) -> bool {
+        self.owner_bypass_enabled && *addr == self.owner
+    }
+
+    /// Returns what the admin wallet receives from a deposit of `amount` of `token_address`
+    /// once the chain's native transfer tax is taken. CW20 transfers are untaxed.
+    pub fn net_amount(&self, token_address: &str, amount: Uint128) -> Uint128 {
+        if !is_native_denom(token_address) {
+            return amount;
+        }
+        amount - amount.multiply_ratio(self.native_tax_bps, MAX_BPS)
+    }
 }
 
+/// Basis points in 100%.
+pub const MAX_BPS: u16 = 10_000;
+
 // Use a singleton storage item for config.
 const CONFIG: Item<Config> = Item::new("config");
 
+/// The config as stored by the original contract version, before any setting was added.
+#[cw_serde]
+struct LegacyConfig {
+    owner: Addr,
+    admin_wallet: Addr,
+    whitelist: Vec<String>,
+}
+
+// The same storage slot as `CONFIG`, read by `migrate` in the original layout.
+const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+
+/// Per-token deposit settings.
+#[cw_serde]
+pub struct TokenConfig {
+    /// Smallest amount accepted per deposit, in raw units or, with `min_in_whole_tokens`,
+    /// in whole tokens.
+    pub min_amount: Uint128,
+    /// Largest amount accepted per deposit, in raw units.
+    pub max_amount: Uint128,
+    /// Logical asset id shared by the native and CW20 forms of the same asset.
+    pub canonical_id: Option<String>,
+    /// Whether `min_amount` counts whole tokens, scaled by the CW20 decimals at deposit time.
+    #[serde(default)]
+    pub min_in_whole_tokens: bool,
+    /// CW20 decimals cached when the token was whitelisted or last refreshed.
+    pub decimals: Option<u8>,
+    /// CW20 symbol cached alongside `decimals`.
+    pub symbol: Option<String>,
+    /// Seconds a user must wait between two deposits of this token; 0 disables the cooldown.
+    #[serde(default)]
+    pub cooldown_secs: u64,
+    /// Whether a CW20 deposit is verified by the admin wallet's balance growth instead of
+    /// trusting the requested amount, for rebasing or elastic-supply tokens.
+    #[serde(default)]
+    pub balance_delta: bool,
+    /// Length in seconds of the epochs `epoch_cap` applies to; 0 disables the cap.
+    #[serde(default)]
+    pub epoch_len_secs: u64,
+    /// Most raw units that may be deposited per epoch, across all users.
+    #[serde(default)]
+    pub epoch_cap: Uint128,
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            min_amount: Uint128::zero(),
+            max_amount: Uint128::MAX,
+            canonical_id: None,
+            min_in_whole_tokens: false,
+            decimals: None,
+            symbol: None,
+            cooldown_secs: 0,
+            balance_delta: false,
+            epoch_len_secs: 0,
+            epoch_cap: Uint128::zero(),
+        }
+    }
+}
+
+// Per-token settings keyed by CW20 contract address or native denom.
+// Tokens without an entry use `TokenConfig::default()`.
+const TOKEN_CONFIGS: Map<&str, TokenConfig> = Map::new("token_configs");
+
+/// A hidden deposit waiting to be revealed.
+#[cw_serde]
+pub struct DepositCommitment {
+    /// `commitment_hash` of the hidden amount and nonce.
+    pub commitment: HexBinary,
+    /// Block time after which the commitment can no longer be revealed.
+    pub expires: Timestamp,
+}
+
+// Open deposit commitments, at most one per user.
+const COMMITMENTS: Map<&Addr, DepositCommitment> = Map::new("commitments");
+
+/// How long a deposit commitment can be revealed after it is made.
+pub const COMMITMENT_TTL_SECONDS: u64 = 10 * 60;
+
+/// A trusted IBC route: `base_denom` as received over `channel` on the transfer port.
+#[cw_serde]
+pub struct IbcPath {
+    pub channel: String,
+    pub base_denom: String,
+}
+
+// Trusted IBC routes keyed by the `ibc/<HASH>` denom they produce.
+const TRUSTED_IBC_DENOMS: Map<&str, IbcPath> = Map::new("trusted_ibc_denoms");
+
+// Block time of each user's last deposit per token, kept for tokens with a cooldown.
+const LAST_DEPOSITS: Map<(&Addr, &str), Timestamp> = Map::new("last_deposits");
+
+/// Deposits of a token counted against its epoch cap.
+#[cw_serde]
+pub struct EpochUsage {
+    /// When the epoch started; the first deposit after it ends starts the next one.
+    pub epoch_start_ts: Timestamp,
+    /// Raw units deposited in the epoch.
+    pub epoch_used: Uint128,
+}
+
+// Current epoch of each token with an epoch cap.
+const EPOCH_USAGE: Map<&str, EpochUsage> = Map::new("epoch_usage");
+
+/// A balance-delta deposit waiting for its transfer to complete. The deposit is only
+/// counted, and the post-deposit hook only notified, once the reply knows what arrived.
+#[cw_serde]
+pub struct PendingBalanceCheck {
+    pub token_address: String,
+    pub deposit_seq: u64,
+    /// The admin wallet's balance before the transfer.
+    pub balance_before: Uint128,
+    /// The least the admin wallet's balance must grow by.
+    pub min_received: Uint128,
+    /// The depositor, counted in the global stats.
+    pub sender: Addr,
+    /// The party the deposit is credited to.
+    pub beneficiary: Addr,
+    /// The amount the depositor asked to transfer.
+    pub amount: Uint128,
+}
+
+// Set while a balance-delta deposit's transfer runs and cleared by its reply.
+const PENDING_BALANCE_CHECK: Item<PendingBalanceCheck> = Item::new("pending_balance_check");
+
+/// Reply id of the transfer submessage of a balance-delta deposit.
+pub const BALANCE_DELTA_REPLY_ID: u64 = 1;
+
+// The nonce each user's next `DepositIntent` must carry; 0 if none was used yet.
+const RELAY_NONCES: Map<&Addr, u64> = Map::new("relay_nonces");
+
+/// `DepositIntentSignData::domain`, so the signature cannot be replayed as a signature over
+/// anything else.
+pub const DEPOSIT_INTENT_DOMAIN: &str = "robet:deposit_intent:v1";
+
+// Total deposited amount per token, in raw units.
+const VOLUME_BY_TOKEN: Map<&str, Uint128> = Map::new("volume_by_token");
+
+// Every address that has deposited at least once. This grows by one entry per new
+// depositor and is never pruned, so a first deposit pays for one extra storage write;
+// it is only ever read by key, never iterated.
+const DEPOSITORS: Map<&Addr, ()> = Map::new("depositors");
+
+// Number of entries in `DEPOSITORS`, kept separately so it can be read in constant time.
+const UNIQUE_DEPOSITORS: Item<u64> = Item::new("unique_depositors");
+
 /// Instantiate message. The instantiator's address will be saved as the owner.
 #[cw_serde]
 pub struct InstantiateMsg {
     /// The admin wallet address that will receive deposited tokens.
     pub admin_wallet: String,
+    /// Tokens to whitelist right away. Entries are validated like `AddWhitelistedToken`
+    /// and deduplicated; at most `MAX_INITIAL_WHITELIST` distinct tokens are accepted.
+    pub initial_whitelist: Option<Vec<String>>,
 }
 
+/// Maximum number of distinct tokens `InstantiateMsg::initial_whitelist` may seed.
+pub const MAX_INITIAL_WHITELIST: usize = 30;
+
 /// Execute messages.
 #[cw_serde]
 pub enum ExecuteMsg {
-    /// Adds a token contract to the whitelist. (Owner only)
+    /// Adds a CW20 contract address or native denom to the whitelist. (Owner or whitelist manager)
+    ///
+    /// `canonical_id` maps the token to a logical asset. Give the native and CW20 forms of the
+    /// same asset the same id, and deposits in either form report it in the `canonical_id`
+    /// event attribute. Re-adding a whitelisted token with a new id replaces its mapping;
+    /// passing `None` leaves an existing mapping unchanged.
     AddWhitelistedToken {
         token_address: String,
+        canonical_id: Option<String>,
     },
-    /// Removes a token contract from the whitelist. (Owner only)
+    /// Re-fetches a whitelisted CW20's cached decimals and symbol, in case the token
+    /// contract changed them. (Owner or whitelist manager)
+    RefreshTokenInfo {
+        token_address: String,
+    },
+    /// Removes a CW20 contract address or native denom from the whitelist. (Owner or whitelist manager)
     RemoveWhitelistedToken {
         token_address: String,
     },
+    /// Removes several tokens from the whitelist at once. Every token must be whitelisted,
+    /// or nothing is removed. (Owner or whitelist manager)
+    RemoveWhitelistedTokens {
+        token_addresses: Vec<String>,
+    },
     /// Deposits tokens from the user to the admin wallet.
     ///
     /// For CW20 tokens: Provide token_address (contract address) and amount (requires allowance).
     /// For native tokens: Provide token_address (denom string) and amount, and send with the transaction.
+    ///
+    /// If `beneficiary` is set, the deposit is credited to that address in the event while the
+    /// tokens still come from the sender.
+    ///
+    /// A rejected deposit fails the transaction, discarding its events; rejections are only
+    /// observable through `PreflightDeposit` or the `SimulateDeposit` query.
     DepositToken {
         token_address: String,
         amount: Uint128,
+        beneficiary: Option<String>,
+    },
+    /// Runs the deposit checks for the sender without moving funds, and emits a
+    /// `deposit_rejected` event with a stable `reason_code` if the deposit would fail.
+    /// The transaction itself succeeds, so the event reaches indexers. Funds must not be
+    /// attached; whether a native deposit attaches exactly `amount` is not checked.
+    PreflightDeposit {
+        token_address: String,
+        amount: Uint128,
     },
     /// Updates the config (for example, changing the admin wallet). (Owner only)
+    ///
+    /// With a non-zero `admin_change_delay` the new wallet only becomes pending and is
+    /// installed by `ApplyAdminWallet` once the delay has elapsed. Sending this again
+    /// replaces the pending wallet and restarts the delay.
     UpdateConfig {
         new_admin_wallet: String,
     },
+    /// Installs the pending admin wallet once its delay has elapsed. (Owner only)
+    ApplyAdminWallet {},
+    /// Sets how many seconds a new admin wallet stays pending before it can be applied.
+    /// The delay can only be raised, so a compromised owner key cannot shorten it. (Owner only)
+    SetAdminChangeDelay {
+        delay_seconds: u64,
+    },
+    /// Sets the inclusive range of CW20 decimals accepted when whitelisting tokens. (Owner only)
+    SetDecimalsBounds {
+        min_decimals: u8,
+        max_decimals: u8,
+    },
+    /// Hands whitelist management to a separate manager address. (Owner only)
+    SetWhitelistManager {
+        manager: String,
+    },
+    /// Updates any subset of the admin wallet and whitelist manager in one call, e.g. while
+    /// responding to a key compromise. A new admin wallet is subject to `admin_change_delay`
+    /// just like `UpdateConfig`. (Owner only)
+    RotateRoles {
+        admin_wallet: Option<String>,
+        whitelist_manager: Option<String>,
+    },
+    /// Lets the owner deposit tokens that are not whitelisted, for testing and treasury
+    /// rebalancing. Such deposits use default limits and are flagged in the event. (Owner only)
+    SetOwnerBypass {
+        enabled: bool,
+    },
+    /// Records the tax the chain takes on native transfers, in basis points, so deposit
+    /// events can report what the admin wallet actually receives. (Owner only)
+    SetNativeTaxBps {
+        bps: u16,
+    },
+    /// Replaces the feature flags. Bits outside `KNOWN_FEATURE_FLAGS` are refused, so a bit
+    /// cannot be set before the feature it will gate exists. (Owner only)
+    SetFeatureFlags {
+        flags: u64,
+    },
+    /// Sets or clears the contract notified after every deposit. The notification is a
+    /// regular message, so a failing hook rolls back the whole deposit. (Owner only)
+    SetPostDepositHook {
+        hook: Option<String>,
+    },
+    /// Sets whether deposits of a whitelisted CW20 token are verified by the admin wallet's
+    /// balance growth, at least the token's minimum and never zero, instead of the requested
+    /// amount. Meant for rebasing tokens, whose transfers may not move the exact amount.
+    /// Native bank transfers are exact, so native denoms cannot enable it.
+    /// (Owner or whitelist manager)
+    SetBalanceDeltaMode {
+        token_address: String,
+        enabled: bool,
+    },
+    /// Sets whether a whitelisted CW20 token's minimum is counted in whole tokens, e.g. 1 for
+    /// "at least 1.0 token", or in raw units. Native denoms have no on-chain decimals, so
+    /// their minimum is always raw. (Owner or whitelist manager)
+    SetMinInWholeTokens {
+        token_address: String,
+        whole_tokens: bool,
+    },
+    /// Trusts `base_denom` arriving over `channel` on the transfer port. Deposits of an
+    /// `ibc/<HASH>` denom are only accepted if its hash matches a trusted route, so a
+    /// look-alike denom from another channel is rejected even if whitelisted. (Owner only)
+    RegisterIbcPath {
+        channel: String,
+        base_denom: String,
+    },
+    /// Stops trusting a route registered with `RegisterIbcPath`. (Owner only)
+    RemoveIbcPath {
+        channel: String,
+        base_denom: String,
+    },
+    /// Sets the per-deposit minimum and maximum for a whitelisted token in one step.
+    /// (Owner or whitelist manager)
+    SetTokenLimits {
+        token_address: String,
+        min: Uint128,
+        max: Uint128,
+    },
+    /// Sets how many seconds each user must wait between two deposits of a whitelisted
+    /// token; 0 disables the cooldown. (Owner or whitelist manager)
+    SetTokenCooldown {
+        token_address: String,
+        cooldown_secs: u64,
+    },
+    /// Caps how much of a whitelisted token may be deposited per `epoch_len_secs`, across
+    /// all users; 0 seconds disables the cap. An epoch starts with the first deposit after
+    /// the previous one ended. (Owner or whitelist manager)
+    SetEpochCap {
+        token_address: String,
+        epoch_len_secs: u64,
+        epoch_cap: Uint128,
+    },
+    /// Deposits everything the sender has currently approved for this contract on a CW20
+    /// token, capped by the sender's balance. The event reports the amount actually moved.
+    DepositCw20Allowance {
+        token_address: String,
+    },
+    /// Commits to a future deposit without revealing its amount. `commitment` must equal
+    /// sha256(amount as 16 big-endian bytes || nonce || sender address). A new commitment
+    /// replaces the sender's previous one, and each expires after `COMMITMENT_TTL_SECONDS`.
+    CommitDeposit {
+        commitment: HexBinary,
+    },
+    /// Reveals the sender's committed deposit and performs it like `DepositToken`.
+    RevealDeposit {
+        token_address: String,
+        amount: Uint128,
+        nonce: HexBinary,
+    },
+    /// Performs a CW20 deposit the user signed off-chain, submitted by a relayer, so the
+    /// user sends no transaction of their own.
+    ///
+    /// The user signs `deposit_intent_sign_bytes` with ADR-036 arbitrary-data signing, e.g.
+    /// a wallet's `signArbitrary`, and hands over the 64-byte `signature` together with
+    /// their 33-byte compressed secp256k1 `pubkey`. The key must hash to the intent's user,
+    /// as Cosmos SDK account addresses do. The intent's nonce must be the user's next relay
+    /// nonce, so each intent is used at most once. The tokens are pulled from the user via
+    /// allowance and the deposit is credited to them exactly like their own `DepositToken`.
+    RelayedDeposit {
+        intent: DepositIntent,
+        pubkey: Binary,
+        signature: Binary,
+    },
+}
+
+/// The data a user signs for `RelayedDeposit`, binding their intent to one contract on
+/// one chain.
+#[cw_serde]
+pub struct DepositIntentSignData {
+    /// Always `DEPOSIT_INTENT_DOMAIN`.
+    pub domain: String,
+    pub chain_id: String,
+    pub contract: String,
+    pub intent: DepositIntent,
+}
+
+/// A deposit a user authorizes off-chain for `RelayedDeposit`.
+#[cw_serde]
+pub struct DepositIntent {
+    pub user: String,
+    /// CW20 contract address; native funds cannot be pulled from the user.
+    pub token_address: String,
+    pub amount: Uint128,
+    /// Must equal the user's next relay nonce.
+    pub nonce: u64,
+    /// Block time after which the intent can no longer be submitted.
+    pub deadline: Timestamp,
 }
 
 /// Query messages.
@@ -63,6 +519,184 @@ pub enum QueryMsg {
     /// Returns the current configuration.
     #[returns(Config)]
     GetConfig {},
+    /// Runs the deposit validation for `user` without moving funds or changing state.
+    ///
+    /// Attached funds cannot be checked here; a native deposit must still attach exactly `amount`.
+    #[returns(SimulateDepositResponse)]
+    SimulateDeposit {
+        user: String,
+        token_address: String,
+        amount: Uint128,
+    },
+    /// Returns a page of whitelisted tokens in lexical order, starting after `start_after`.
+    ///
+    /// The order only depends on the token strings, so inserts and removals never reorder
+    /// the tokens around a `start_after` cursor.
+    #[returns(WhitelistResponse)]
+    Whitelist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns a page of whitelisted tokens with their settings and deposit status, paged
+    /// like `Whitelist`.
+    #[returns(WhitelistDetailedResponse)]
+    WhitelistDetailed {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the settings of one whitelisted token. Fails if the token is not whitelisted.
+    #[returns(WhitelistEntryResponse)]
+    WhitelistEntry {
+        token_address: String,
+    },
+    /// Reports whether `token_address` can currently be deposited, with its limits.
+    #[returns(DepositStatusResponse)]
+    DepositStatus {
+        token_address: String,
+    },
+    /// Returns the largest amount `user` can deposit of `token_address` in one deposit,
+    /// under the token's per-deposit maximum and epoch cap, and when their cooldown ends.
+    #[returns(RemainingAllowanceResponse)]
+    RemainingAllowance {
+        user: String,
+        token_address: String,
+    },
+    /// Returns deposit totals across all users and tokens.
+    #[returns(GlobalStatsResponse)]
+    GlobalStats {},
+    /// Returns the nonce `user`'s next `DepositIntent` must carry.
+    #[returns(RelayStateResponse)]
+    RelayState {
+        user: String,
+    },
+    /// Returns the deployed contract's name and version with its feature bits, so clients
+    /// can detect what an instance supports.
+    #[returns(VersionResponse)]
+    Version {},
+}
+
+/// The deployed contract version and the features it supports.
+#[cw_serde]
+pub struct VersionResponse {
+    /// Contract name as stored by `cw2`.
+    pub contract: String,
+    /// Semantic version as stored by `cw2`.
+    pub version: String,
+    /// Feature bits this build knows about, i.e. `KNOWN_FEATURE_FLAGS`.
+    pub known_feature_flags: u64,
+    /// Feature bits currently enabled in the config.
+    pub feature_flags: u64,
+}
+
+/// How much a user can still deposit of a token.
+#[cw_serde]
+pub struct RemainingAllowanceResponse {
+    /// The smallest of the token's remaining limits, ignoring any cooldown. Zero if the
+    /// token cannot be deposited or the remainder is below the token's minimum.
+    pub remaining: Uint128,
+    /// When the user's cooldown for the token ends; `None` if they are not in one.
+    pub next_allowed_ts: Option<Timestamp>,
+}
+
+/// A user's relayed deposit settings.
+#[cw_serde]
+pub struct RelayStateResponse {
+    pub next_nonce: u64,
+}
+
+/// Deposit totals across all users and tokens.
+#[cw_serde]
+pub struct GlobalStatsResponse {
+    /// Number of deposits made so far; equal to the config's `deposit_seq`.
+    pub total_deposits: u64,
+    /// Number of distinct addresses that have deposited.
+    pub unique_depositors: u64,
+    /// Total deposited per token, in raw units, for every token ever deposited.
+    pub volume_by_token: Vec<TokenVolume>,
+}
+
+/// Total deposited amount of one token.
+#[cw_serde]
+pub struct TokenVolume {
+    pub token_address: String,
+    pub volume: Uint128,
+}
+
+/// A page of whitelisted tokens.
+#[cw_serde]
+pub struct WhitelistResponse {
+    pub tokens: Vec<String>,
+}
+
+/// A page of whitelisted tokens with their settings.
+#[cw_serde]
+pub struct WhitelistDetailedResponse {
+    pub tokens: Vec<WhitelistDetailedEntry>,
+}
+
+/// Settings and deposit status of one whitelisted token.
+#[cw_serde]
+pub struct WhitelistDetailedEntry {
+    pub token: String,
+    /// "native" or "cw20", as in deposit events.
+    pub kind: String,
+    /// Whether deposits are accepted; an IBC denom without a trusted route is not.
+    pub enabled: bool,
+    /// Smallest accepted deposit, in raw units.
+    pub min_amount: Uint128,
+    /// Largest accepted deposit.
+    pub max_amount: Uint128,
+    /// Deposit cap of the current epoch, or `None` when uncapped.
+    pub cap: Option<Uint128>,
+    /// Amount deposited in the current epoch; `None` while the token is uncapped.
+    pub cap_used: Option<Uint128>,
+    /// Share the chain takes on transfers, in basis points; deposits carry no fee of
+    /// their own, so this is the native tax for native denoms and 0 for CW20s.
+    pub tax_bps: u16,
+}
+
+/// Settings of a single whitelisted token.
+#[cw_serde]
+pub struct WhitelistEntryResponse {
+    pub token_address: String,
+    /// Deposit limits and canonical id; defaults if none were set.
+    pub token_config: TokenConfig,
+    /// Decimals of the CW20 contract, cached or fetched live; `None` for native denoms.
+    pub decimals: Option<u8>,
+}
+
+// Page sizes for the `Whitelist` query.
+const DEFAULT_WHITELIST_LIMIT: u32 = 10;
+const MAX_WHITELIST_LIMIT: u32 = 30;
+
+/// Outcome of a simulated deposit.
+#[cw_serde]
+pub struct DepositStatusResponse {
+    /// Whether deposits of the token are accepted right now.
+    pub allowed: bool,
+    /// Why deposits are rejected, if they are.
+    pub reason: Option<String>,
+    /// Smallest accepted deposit, in raw units.
+    pub min_amount: Uint128,
+    /// Largest accepted deposit.
+    pub max_amount: Uint128,
+    /// Amount that can still be deposited before the token's epoch cap is reached,
+    /// or `None` when the token is uncapped.
+    pub cap_remaining: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct SimulateDepositResponse {
+    /// Whether the deposit would pass validation.
+    pub ok: bool,
+    /// Why the deposit would fail, if it would.
+    pub reason: Option<String>,
+    /// Stable code of `reason`, as in `deposit_rejected` events.
+    pub reason_code: Option<String>,
+    /// Amount the admin wallet receives, after any native transfer tax.
+    pub net_amount: Uint128,
+    /// Amount withheld as a fee. Deposits are currently fee-free.
+    pub fee_amount: Uint128,
 }
 
 #[entry_point]
@@ -71,128 +705,1184 @@ pub fn instantiate(
     _env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let admin_wallet = deps.api.addr_validate(&msg.admin_wallet)?;
-    let config = Config {
-        owner: info.sender.clone(),
-        admin_wallet,
-        whitelist: vec![],
-    };
+    let mut config = Config::new(info.sender.clone(), admin_wallet);
+
+    let mut response = Response::default();
+    if let Some(mut initial_whitelist) = msg.initial_whitelist {
+        initial_whitelist.sort();
+        initial_whitelist.dedup();
+        if initial_whitelist.len() > MAX_INITIAL_WHITELIST {
+            return Err(ContractError::InitialWhitelistTooLarge {
+                count: initial_whitelist.len(),
+                max: MAX_INITIAL_WHITELIST,
+            });
+        }
+        for token in &initial_whitelist {
+            if let Some(token_info) = validate_whitelist_token(deps.as_ref(), &config, token)? {
+                cache_token_info(deps.storage, token, token_info)?;
+            }
+        }
+        response = response.add_event(new_event("seed_whitelist")
+            .add_attribute("count", initial_whitelist.len().to_string())
+            .add_attribute("tokens", initial_whitelist.join(",")));
+        config.whitelist = initial_whitelist;
+    }
+
     CONFIG.save(deps.storage, &config)?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    Ok(Response::default()
-        .add_event(Event::new("instantiate")
+    Ok(response
+        .add_event(new_event("instantiate")
             .add_attribute("owner", info.sender)
             .add_attribute("admin_wallet", msg.admin_wallet)))
 }
 
+/// Migrate message.
+#[cw_serde]
+pub struct MigrateMsg {}
+
+/// Upgrades an instance of this contract to the current version.
+///
+/// A config still in the original layout (owner, admin wallet and whitelist) is rewritten
+/// in the current one. Its tokens are sorted and deduplicated and get no limits, and every
+/// other setting starts as in `instantiate`. A config already in the current layout is kept.
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = cw2::get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::WrongContract {
+            contract: stored.contract,
+        });
+    }
+    let config_migrated = CONFIG.load(deps.storage).is_err();
+    if config_migrated {
+        let legacy = LEGACY_CONFIG.load(deps.storage)?;
+        let mut config = Config::new(legacy.owner, legacy.admin_wallet);
+        config.whitelist = legacy.whitelist;
+        config.whitelist.sort();
+        config.whitelist.dedup();
+        CONFIG.save(deps.storage, &config)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new().add_event(new_event("migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION)
+        .add_attribute("config_migrated", config_migrated.to_string())))
+}
+
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::AddWhitelistedToken { token_address } => {
-            execute_add_whitelisted_token(deps, info, token_address)
+        ExecuteMsg::AddWhitelistedToken {
+            token_address,
+            canonical_id,
+        } => execute_add_whitelisted_token(deps, info, token_address, canonical_id),
+        ExecuteMsg::RefreshTokenInfo { token_address } => {
+            execute_refresh_token_info(deps, info, token_address)
         }
         ExecuteMsg::RemoveWhitelistedToken { token_address } => {
             execute_remove_whitelisted_token(deps, info, token_address)
         }
+        ExecuteMsg::RemoveWhitelistedTokens { token_addresses } => {
+            execute_remove_whitelisted_tokens(deps, info, token_addresses)
+        }
         ExecuteMsg::DepositToken {
             token_address,
             amount,
-        } => execute_deposit_token(deps, env, info, token_address, amount),
+            beneficiary,
+        } => execute_deposit_token(deps, env, info, token_address, amount, beneficiary),
+        ExecuteMsg::PreflightDeposit {
+            token_address,
+            amount,
+        } => execute_preflight_deposit(deps, env, info, token_address, amount),
         ExecuteMsg::UpdateConfig { new_admin_wallet } => {
-            execute_update_config(deps, info, new_admin_wallet)
+            execute_update_config(deps, env, info, new_admin_wallet)
+        }
+        ExecuteMsg::ApplyAdminWallet {} => execute_apply_admin_wallet(deps, env, info),
+        ExecuteMsg::SetAdminChangeDelay { delay_seconds } => {
+            execute_set_admin_change_delay(deps, info, delay_seconds)
+        }
+        ExecuteMsg::SetDecimalsBounds {
+            min_decimals,
+            max_decimals,
+        } => execute_set_decimals_bounds(deps, info, min_decimals, max_decimals),
+        ExecuteMsg::SetWhitelistManager { manager } => {
+            execute_set_whitelist_manager(deps, info, manager)
+        }
+        ExecuteMsg::RotateRoles {
+            admin_wallet,
+            whitelist_manager,
+        } => execute_rotate_roles(deps, env, info, admin_wallet, whitelist_manager),
+        ExecuteMsg::SetOwnerBypass { enabled } => execute_set_owner_bypass(deps, info, enabled),
+        ExecuteMsg::SetNativeTaxBps { bps } => execute_set_native_tax_bps(deps, info, bps),
+        ExecuteMsg::SetPostDepositHook { hook } => execute_set_post_deposit_hook(deps, info, hook),
+        ExecuteMsg::SetFeatureFlags { flags } => execute_set_feature_flags(deps, info, flags),
+        ExecuteMsg::SetTokenLimits {
+            token_address,
+            min,
+            max,
+        } => execute_set_token_limits(deps, info, token_address, min, max),
+        ExecuteMsg::SetTokenCooldown {
+            token_address,
+            cooldown_secs,
+        } => execute_set_token_cooldown(deps, info, token_address, cooldown_secs),
+        ExecuteMsg::SetEpochCap {
+            token_address,
+            epoch_len_secs,
+            epoch_cap,
+        } => execute_set_epoch_cap(deps, info, token_address, epoch_len_secs, epoch_cap),
+        ExecuteMsg::SetBalanceDeltaMode {
+            token_address,
+            enabled,
+        } => execute_set_balance_delta_mode(deps, info, token_address, enabled),
+        ExecuteMsg::SetMinInWholeTokens {
+            token_address,
+            whole_tokens,
+        } => execute_set_min_in_whole_tokens(deps, info, token_address, whole_tokens),
+        ExecuteMsg::RegisterIbcPath {
+            channel,
+            base_denom,
+        } => execute_register_ibc_path(deps, info, channel, base_denom),
+        ExecuteMsg::RemoveIbcPath {
+            channel,
+            base_denom,
+        } => execute_remove_ibc_path(deps, info, channel, base_denom),
+        ExecuteMsg::DepositCw20Allowance { token_address } => {
+            execute_deposit_cw20_allowance(deps, env, info, token_address)
+        }
+        ExecuteMsg::CommitDeposit { commitment } => {
+            execute_commit_deposit(deps, env, info, commitment)
         }
+        ExecuteMsg::RevealDeposit {
+            token_address,
+            amount,
+            nonce,
+        } => execute_reveal_deposit(deps, env, info, token_address, amount, nonce),
+        ExecuteMsg::RelayedDeposit {
+            intent,
+            pubkey,
+            signature,
+        } => execute_relayed_deposit(deps, env, info, intent, pubkey, signature),
+    }
+}
+
+/// Checks that `token` may be whitelisted under `config`.
+///
+/// For CW20 tokens, the decimals are fetched via a `TokenInfo` query and must fall
+/// within the configured bounds; the fetched info is returned for caching. Native denoms
+/// carry no on-chain decimals and are not checked.
+fn validate_whitelist_token(
+    deps: Deps,
+    config: &Config,
+    token: &str,
+) -> Result<Option<TokenInfoResponse>, ContractError> {
+    if is_native_denom(token) {
+        return Ok(None);
+    }
+    let token_addr = deps.api.addr_validate(token)?;
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&token_addr, &Cw20QueryMsg::TokenInfo {})?;
+    if token_info.decimals < config.min_decimals || token_info.decimals > config.max_decimals {
+        return Err(ContractError::DecimalsOutOfRange {
+            decimals: token_info.decimals,
+            min: config.min_decimals,
+            max: config.max_decimals,
+        });
     }
+    Ok(Some(token_info))
 }
 
-/// Allows the owner to add a token address to the whitelist.
+/// Stores a CW20 token's decimals and symbol in its token config.
+///
+/// The cache saves a cross-contract query on every deposit, at the cost of going stale if
+/// the token contract ever changes its info; `RefreshTokenInfo` re-fetches it.
+fn cache_token_info(
+    storage: &mut dyn Storage,
+    token: &str,
+    token_info: TokenInfoResponse,
+) -> StdResult<()> {
+    let mut token_config = TOKEN_CONFIGS.may_load(storage, token)?.unwrap_or_default();
+    token_config.decimals = Some(token_info.decimals);
+    token_config.symbol = Some(token_info.symbol);
+    TOKEN_CONFIGS.save(storage, token, &token_config)
+}
+
+/// Allows the owner or whitelist manager to add a token address or native denom to the whitelist.
+///
+/// The token is checked with `validate_whitelist_token`, and a CW20's decimals and symbol
+/// are cached. An optional `canonical_id` is stored in the token's config.
 pub fn execute_add_whitelisted_token(
     deps: DepsMut,
     info: MessageInfo,
     token_address: String,
-) -> StdResult<Response> {
-    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
-        // Only the owner can update the whitelist.
-        if config.owner != info.sender {
-            return Err(StdError::generic_err("Unauthorized"));
-        }
-        let token_addr = deps.api.addr_validate(&token_address)?;
-        if !config.whitelist.contains(&token_addr) {    
-            config.whitelist.push(token_addr.clone());
+    canonical_id: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    // Only the owner or whitelist manager can update the whitelist.
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(token_info) = validate_whitelist_token(deps.as_ref(), &config, &token_address)? {
+        cache_token_info(deps.storage, &token_address, token_info)?;
+    }
+    // Insert at the sorted position so `Whitelist` pagination stays stable.
+    if let Err(index) = config.whitelist.binary_search(&token_address) {
+        config.whitelist.insert(index, token_address.clone());
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    let mut event = new_event("add_whitelisted_token")
+        .add_attribute("token_address", token_address.clone());
+    if let Some(canonical_id) = canonical_id {
+        if canonical_id.is_empty() {
+            return Err(ContractError::EmptyCanonicalId {});
         }
-        Ok(config)
-    })?;
-    Ok(Response::new()
-        .add_event(Event::new("add_whitelisted_token")
-            .add_attribute("token_address", token_address)))
+        let mut token_config = TOKEN_CONFIGS
+            .may_load(deps.storage, &token_address)?
+            .unwrap_or_default();
+        token_config.canonical_id = Some(canonical_id.clone());
+        TOKEN_CONFIGS.save(deps.storage, &token_address, &token_config)?;
+        event = event.add_attribute("canonical_id", canonical_id);
+    }
+    Ok(Response::new().add_event(event))
+}
+
+/// Allows the owner or whitelist manager to re-fetch a whitelisted CW20's cached decimals
+/// and symbol. The decimals must still fall within the configured bounds.
+pub fn execute_refresh_token_info(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_address: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+    let token_info = validate_whitelist_token(deps.as_ref(), &config, &token_address)?
+        .ok_or(ContractError::NotCw20 {})?;
+    let event = new_event("refresh_token_info")
+        .add_attribute("token_address", token_address.clone())
+        .add_attribute("decimals", token_info.decimals.to_string())
+        .add_attribute("symbol", token_info.symbol.clone());
+    cache_token_info(deps.storage, &token_address, token_info)?;
+    Ok(Response::new().add_event(event))
 }
 
-/// Allows the owner to remove a token address from the whitelist.
+/// Allows the owner or whitelist manager to remove a token address or native denom from the whitelist.
 pub fn execute_remove_whitelisted_token(
     deps: DepsMut,
     info: MessageInfo,
     token_address: String,
-) -> StdResult<Response> {
-    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
-        if config.owner != info.sender {
-            return Err(StdError::generic_err("Unauthorized"));
+) -> Result<Response, ContractError> {
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        if !config.can_manage_whitelist(&info.sender) {
+            return Err(ContractError::Unauthorized {});
         }
-        let token_addr = deps.api.addr_validate(&token_address)?;
-        config.whitelist.retain(|addr| *addr != token_addr);
+        config.whitelist.retain(|token| *token != token_address);
         Ok(config)
     })?;
+    TOKEN_CONFIGS.remove(deps.storage, &token_address);
     Ok(Response::new()
-        .add_event(Event::new("remove_whitelisted_token")
+        .add_event(new_event("remove_whitelisted_token")
             .add_attribute("token_address", token_address)))
 }
 
-/// Allows the owner to update the admin wallet.
+/// Allows the owner or whitelist manager to remove several tokens in one call. Fails
+/// without removing anything if any token is not whitelisted; duplicates are ignored.
+pub fn execute_remove_whitelisted_tokens(
+    deps: DepsMut,
+    info: MessageInfo,
+    mut token_addresses: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    token_addresses.sort();
+    token_addresses.dedup();
+    if let Some(token) = token_addresses
+        .iter()
+        .find(|token| config.whitelist.binary_search(token).is_err())
+    {
+        return Err(ContractError::NotWhitelisted {
+            token: token.clone(),
+        });
+    }
+    config
+        .whitelist
+        .retain(|token| token_addresses.binary_search(token).is_err());
+    CONFIG.save(deps.storage, &config)?;
+    for token in &token_addresses {
+        TOKEN_CONFIGS.remove(deps.storage, token);
+    }
+    Ok(Response::new()
+        .add_event(new_event("remove_whitelisted_tokens")
+            .add_attribute("count", token_addresses.len().to_string())
+            .add_attribute("token_addresses", token_addresses.join(","))))
+}
+
+/// Allows the owner to update the admin wallet, subject to `admin_change_delay`.
 pub fn execute_update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_admin_wallet: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     if config.owner != info.sender {
-        return Err(StdError::generic_err("Unauthorized"));
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let effective = env.block.time.plus_seconds(config.admin_change_delay);
+    config.pending_admin_wallet = Some(PendingAdminWallet {
+        wallet: deps.api.addr_validate(&new_admin_wallet)?,
+        effective,
+    });
+    let mut response = Response::new()
+        .add_event(new_event("admin_wallet_pending")
+            .add_attribute("new_admin_wallet", new_admin_wallet)
+            .add_attribute("effective", effective.seconds().to_string()));
+    if config.admin_change_delay == 0 {
+        response = response.add_event(apply_admin_wallet(&mut config, &env)?);
     }
-    
-    let old_admin = config.admin_wallet.to_string();
-    config.admin_wallet = deps.api.addr_validate(&new_admin_wallet)?;
     CONFIG.save(deps.storage, &config)?;
-    
-    Ok(Response::new()
-        .add_event(Event::new("update_config")
-            .add_attribute("old_admin_wallet", old_admin)
-            .add_attribute("new_admin_wallet", new_admin_wallet)))
+    Ok(response)
 }
 
-/// Deposits tokens from the user into the admin wallet's account.
-///
-/// This function handles both CW20 tokens and native tokens:
-/// - For CW20 tokens: Provide token_address (contract address) and amount (requires allowance)
-/// - For native tokens: Provide token_address (denom string) and amount, with matching funds sent
-pub fn execute_deposit_token(
+/// Installs the pending admin wallet if its delay has elapsed, returning the event to emit.
+fn apply_admin_wallet(config: &mut Config, env: &Env) -> Result<Event, ContractError> {
+    let pending = config
+        .pending_admin_wallet
+        .take()
+        .ok_or(ContractError::NoPendingAdminWallet {})?;
+    if env.block.time < pending.effective {
+        return Err(ContractError::AdminChangeTimelocked {
+            effective: pending.effective,
+        });
+    }
+    let old_admin = std::mem::replace(&mut config.admin_wallet, pending.wallet);
+    // The applied change keeps the `update_config` event type indexers already follow.
+    Ok(new_event("update_config")
+        .add_attribute("old_admin_wallet", old_admin)
+        .add_attribute("new_admin_wallet", config.admin_wallet.to_string()))
+}
+
+/// Allows the owner to install the pending admin wallet once its delay has elapsed.
+pub fn execute_apply_admin_wallet(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    token_address: String,
-    amount: Uint128,
-) -> StdResult<Response> {
-    // Load the stored config.
-    let config = CONFIG.load(deps.storage)?;
-    
-    // Check if the token_address is a denom (starts with a specific pattern like "u")
-    // This is a simple heuristic - adjust based on your chain's denom patterns
-    if token_address.starts_with("u") || token_address.contains("ibc/") {
-        // Handle native tokens
-        
-        // Find the specified denom in the sent funds
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let event = apply_admin_wallet(&mut config, &env)?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new().add_event(event))
+}
+
+/// Allows the owner to raise the admin change delay. A wallet that is already pending keeps
+/// its original effective time.
+pub fn execute_set_admin_change_delay(
+    deps: DepsMut,
+    info: MessageInfo,
+    delay_seconds: u64,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if delay_seconds < config.admin_change_delay {
+        return Err(ContractError::AdminChangeDelayDecrease {});
+    }
+    config.admin_change_delay = delay_seconds;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_admin_change_delay")
+            .add_attribute("delay_seconds", delay_seconds.to_string())))
+}
+
+/// Allows the owner to set the range of CW20 decimals accepted by the whitelist.
+/// Tokens already on the whitelist are not re-checked.
+pub fn execute_set_decimals_bounds(
+    deps: DepsMut,
+    info: MessageInfo,
+    min_decimals: u8,
+    max_decimals: u8,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if min_decimals > max_decimals {
+        return Err(ContractError::InvalidDecimalsBounds {
+            min: min_decimals,
+            max: max_decimals,
+        });
+    }
+
+    config.min_decimals = min_decimals;
+    config.max_decimals = max_decimals;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_decimals_bounds")
+            .add_attribute("min_decimals", min_decimals.to_string())
+            .add_attribute("max_decimals", max_decimals.to_string())))
+}
+
+/// Allows the owner to hand whitelist management to a separate manager.
+pub fn execute_set_whitelist_manager(
+    deps: DepsMut,
+    info: MessageInfo,
+    manager: String,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let old_manager = config.whitelist_manager.to_string();
+    config.whitelist_manager = deps.api.addr_validate(&manager)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_whitelist_manager")
+            .add_attribute("old_manager", old_manager)
+            .add_attribute("new_manager", manager)))
+}
+
+/// Allows the owner to update the admin wallet and whitelist manager atomically.
+///
+/// The manager changes at once. A new admin wallet becomes pending, and is applied in the
+/// same call only when `admin_change_delay` is 0. One `rotate_roles` event lists every change.
+pub fn execute_rotate_roles(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    admin_wallet: Option<String>,
+    whitelist_manager: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut event = new_event("rotate_roles");
+    if let Some(manager) = whitelist_manager {
+        config.whitelist_manager = deps.api.addr_validate(&manager)?;
+        event = event.add_attribute("whitelist_manager", manager);
+    }
+    if let Some(admin_wallet) = admin_wallet {
+        let effective = env.block.time.plus_seconds(config.admin_change_delay);
+        config.pending_admin_wallet = Some(PendingAdminWallet {
+            wallet: deps.api.addr_validate(&admin_wallet)?,
+            effective,
+        });
+        if config.admin_change_delay == 0 {
+            apply_admin_wallet(&mut config, &env)?;
+        }
+        event = event
+            .add_attribute("admin_wallet", admin_wallet)
+            .add_attribute("admin_wallet_effective", effective.seconds().to_string());
+    }
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new().add_event(event))
+}
+
+/// Allows the owner to enable or disable the owner's whitelist bypass for deposits.
+pub fn execute_set_owner_bypass(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    config.owner_bypass_enabled = enabled;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_owner_bypass")
+            .add_attribute("enabled", enabled.to_string())))
+}
+
+/// Allows the owner to record the chain's native transfer tax.
+pub fn execute_set_native_tax_bps(
+    deps: DepsMut,
+    info: MessageInfo,
+    bps: u16,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if bps > MAX_BPS {
+        return Err(ContractError::InvalidBps { bps });
+    }
+    config.native_tax_bps = bps;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_native_tax_bps")
+            .add_attribute("bps", bps.to_string())))
+}
+
+/// Returns the `ibc/<HASH>` denom of `base_denom` received over `channel` on the
+/// transfer port, where the hash is the uppercase hex sha256 of the denom trace.
+pub fn ibc_denom(channel: &str, base_denom: &str) -> String {
+    let hash = Sha256::digest(format!("transfer/{channel}/{base_denom}"));
+    format!("ibc/{}", HexBinary::from(hash.as_slice()).to_hex().to_uppercase())
+}
+
+/// Allows the owner to trust an IBC route, so deposits of its denom are accepted.
+pub fn execute_register_ibc_path(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    base_denom: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !channel.starts_with("channel-") || base_denom.is_empty() {
+        return Err(ContractError::InvalidIbcPath {});
+    }
+    let denom = ibc_denom(&channel, &base_denom);
+    let event = new_event("register_ibc_path")
+        .add_attribute("channel", channel.clone())
+        .add_attribute("base_denom", base_denom.clone())
+        .add_attribute("denom", denom.clone());
+    TRUSTED_IBC_DENOMS.save(deps.storage, &denom, &IbcPath { channel, base_denom })?;
+    Ok(Response::new().add_event(event))
+}
+
+/// Allows the owner to stop trusting an IBC route.
+pub fn execute_remove_ibc_path(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    base_denom: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let denom = ibc_denom(&channel, &base_denom);
+    TRUSTED_IBC_DENOMS.remove(deps.storage, &denom);
+    Ok(Response::new()
+        .add_event(new_event("remove_ibc_path")
+            .add_attribute("channel", channel)
+            .add_attribute("base_denom", base_denom)
+            .add_attribute("denom", denom)))
+}
+
+/// Allows the owner to replace the feature flags.
+pub fn execute_set_feature_flags(
+    deps: DepsMut,
+    info: MessageInfo,
+    flags: u64,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if flags & !KNOWN_FEATURE_FLAGS != 0 {
+        return Err(ContractError::UnknownFeatureFlags { flags });
+    }
+    let old_flags = config.feature_flags;
+    config.feature_flags = flags;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_feature_flags")
+            .add_attribute("old_flags", old_flags.to_string())
+            .add_attribute("flags", flags.to_string())))
+}
+
+/// Allows the owner to set or clear the post-deposit hook contract.
+pub fn execute_set_post_deposit_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    hook: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    config.post_deposit_hook = hook.map(|hook| deps.api.addr_validate(&hook)).transpose()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    let hook = config
+        .post_deposit_hook
+        .map(|hook| hook.to_string())
+        .unwrap_or_default();
+    Ok(Response::new()
+        .add_event(new_event("set_post_deposit_hook")
+            .add_attribute("hook", hook)))
+}
+
+/// Allows the owner or whitelist manager to set a token's deposit limits atomically.
+pub fn execute_set_token_limits(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_address: String,
+    min: Uint128,
+    max: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if min > max {
+        return Err(ContractError::InvalidTokenLimits { min, max });
+    }
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+
+    let mut token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    token_config.min_amount = min;
+    token_config.max_amount = max;
+    TOKEN_CONFIGS.save(deps.storage, &token_address, &token_config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_token_limits")
+            .add_attribute("token_address", token_address)
+            .add_attribute("min", min.to_string())
+            .add_attribute("max", max.to_string())))
+}
+
+/// Allows the owner or whitelist manager to set a token's per-user deposit cooldown.
+pub fn execute_set_token_cooldown(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_address: String,
+    cooldown_secs: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+
+    let mut token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    token_config.cooldown_secs = cooldown_secs;
+    TOKEN_CONFIGS.save(deps.storage, &token_address, &token_config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_token_cooldown")
+            .add_attribute("token_address", token_address)
+            .add_attribute("cooldown_secs", cooldown_secs.to_string())))
+}
+
+/// Runs the deposit checks for the sender and emits `deposit_rejected` if they fail,
+/// succeeding either way so the event is kept.
+pub fn execute_preflight_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_address: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if !info.funds.is_empty() {
+        return Err(ContractError::FundsNotAllowed {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let result = check_deposit_allowed(
+        deps.as_ref(),
+        &config,
+        &info.sender,
+        &token_address,
+        amount,
+        env.block.time,
+    );
+    Ok(match result {
+        Ok(_) => Response::new(),
+        Err(err) => Response::new().add_event(new_event("deposit_rejected")
+            .add_attribute("user", info.sender)
+            .add_attribute("token_address", token_address)
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("reason_code", err.reason_code())
+            .add_attribute("reason", err.to_string())),
+    })
+}
+
+/// Allows the owner or whitelist manager to set a token's epoch deposit cap. Deposits
+/// already counted in the current epoch stay counted against the new cap.
+pub fn execute_set_epoch_cap(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_address: String,
+    epoch_len_secs: u64,
+    epoch_cap: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+
+    let mut token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    token_config.epoch_len_secs = epoch_len_secs;
+    token_config.epoch_cap = epoch_cap;
+    TOKEN_CONFIGS.save(deps.storage, &token_address, &token_config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_epoch_cap")
+            .add_attribute("token_address", token_address)
+            .add_attribute("epoch_len_secs", epoch_len_secs.to_string())
+            .add_attribute("epoch_cap", epoch_cap.to_string())))
+}
+
+/// Allows the owner or whitelist manager to count a CW20 token's minimum in whole tokens.
+pub fn execute_set_min_in_whole_tokens(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_address: String,
+    whole_tokens: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+    if whole_tokens && is_native_denom(&token_address) {
+        return Err(ContractError::NotCw20 {});
+    }
+
+    let mut token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    token_config.min_in_whole_tokens = whole_tokens;
+    TOKEN_CONFIGS.save(deps.storage, &token_address, &token_config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_min_in_whole_tokens")
+            .add_attribute("token_address", token_address)
+            .add_attribute("whole_tokens", whole_tokens.to_string())))
+}
+
+/// Allows the owner or whitelist manager to verify a CW20 token's deposits by balance growth.
+pub fn execute_set_balance_delta_mode(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_address: String,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.can_manage_whitelist(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+    if enabled && is_native_denom(&token_address) {
+        return Err(ContractError::NotCw20 {});
+    }
+
+    let mut token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    token_config.balance_delta = enabled;
+    TOKEN_CONFIGS.save(deps.storage, &token_address, &token_config)?;
+
+    Ok(Response::new()
+        .add_event(new_event("set_balance_delta_mode")
+            .add_attribute("token_address", token_address)
+            .add_attribute("enabled", enabled.to_string())))
+}
+
+/// Fetches a CW20's balance of `address` with a `Balance` query.
+fn query_cw20_balance(deps: Deps, token_address: &str, address: &Addr) -> StdResult<Uint128> {
+    let balance: BalanceResponse = deps.querier.query_wasm_smart(
+        token_address,
+        &Cw20QueryMsg::Balance {
+            address: address.to_string(),
+        },
+    )?;
+    Ok(balance.balance)
+}
+
+/// Completes a balance-delta deposit once its transfer has run: the admin wallet's balance
+/// must have grown by at least the pending minimum. The observed growth is reported, counted
+/// against the epoch cap and in the global stats, and passed to the post-deposit hook.
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id != BALANCE_DELTA_REPLY_ID {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "unknown reply id {}",
+            msg.id
+        ))));
+    }
+    let pending = PENDING_BALANCE_CHECK.load(deps.storage)?;
+    PENDING_BALANCE_CHECK.remove(deps.storage);
+
+    let config = CONFIG.load(deps.storage)?;
+    let balance_after =
+        query_cw20_balance(deps.as_ref(), &pending.token_address, &config.admin_wallet)?;
+    let received = balance_after.saturating_sub(pending.balance_before);
+    if received < pending.min_received {
+        return Err(ContractError::InsufficientBalanceDelta {
+            received,
+            min: pending.min_received,
+        });
+    }
+
+    let token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &pending.token_address)?
+        .unwrap_or_default();
+    if let Some(usage) = check_epoch_cap(
+        deps.storage,
+        &token_config,
+        &pending.token_address,
+        received,
+        env.block.time,
+    )? {
+        EPOCH_USAGE.save(deps.storage, &pending.token_address, &usage)?;
+    }
+    record_deposit_stats(deps.storage, &pending.sender, &pending.token_address, received)?;
+
+    let mut response = Response::new();
+    if let Some(hook) = &config.post_deposit_hook {
+        response = response.add_message(post_deposit_hook_msg(
+            hook,
+            &pending.beneficiary,
+            &pending.token_address,
+            received,
+        )?);
+    }
+    Ok(response
+        .add_event(new_event("balance_delta")
+            .add_attribute("user", pending.sender.to_string())
+            .add_attribute("beneficiary", pending.beneficiary.to_string())
+            .add_attribute("token_address", pending.token_address)
+            .add_attribute("deposit_seq", pending.deposit_seq.to_string())
+            .add_attribute("amount", pending.amount.to_string())
+            .add_attribute("received", received.to_string())))
+}
+
+/// Builds the `PostDepositHookMsg` notifying `hook` that `user` was credited `amount`.
+fn post_deposit_hook_msg(
+    hook: &Addr,
+    user: &Addr,
+    token_address: &str,
+    amount: Uint128,
+) -> StdResult<WasmMsg> {
+    let hook_msg = PostDepositHookMsg::Deposit(DepositHookPayload {
+        user: user.clone(),
+        token: token_address.to_string(),
+        amount,
+    });
+    Ok(WasmMsg::Execute {
+        contract_addr: hook.to_string(),
+        msg: to_json_binary(&hook_msg)?,
+        funds: vec![],
+    })
+}
+
+/// Fetches a CW20's decimals with a `TokenInfo` query.
+fn query_token_decimals(deps: Deps, token_address: &str) -> StdResult<u8> {
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(token_address, &Cw20QueryMsg::TokenInfo {})?;
+    Ok(token_info.decimals)
+}
+
+/// Returns a token's minimum deposit in raw units, using the CW20 decimals when the
+/// minimum is set in whole tokens. A threshold beyond `Uint128` saturates.
+fn raw_min_amount(
+    deps: Deps,
+    token_address: &str,
+    token_config: &TokenConfig,
+) -> StdResult<Uint128> {
+    if !token_config.min_in_whole_tokens {
+        return Ok(token_config.min_amount);
+    }
+    let decimals = match token_config.decimals {
+        Some(decimals) => decimals,
+        None => query_token_decimals(deps, token_address)?,
+    };
+    let scale = Uint128::new(10).saturating_pow(u32::from(decimals));
+    Ok(token_config.min_amount.saturating_mul(scale))
+}
+
+/// Returns true if `token_address` looks like a native denom rather than a CW20 address.
+///
+/// Native denoms are detected by a specific pattern (a "u" prefix or an "ibc/" path).
+/// This is a simple heuristic - adjust based on your chain's denom patterns.
+fn is_native_denom(token_address: &str) -> bool {
+    token_address.starts_with('u') || token_address.contains("ibc/")
+}
+
+/// Checks the parts of a deposit that do not depend on attached funds: the token must be
+/// whitelisted, the amount non-zero and within the token's limits, and a CW20 address valid.
+/// With the owner bypass enabled, the owner may deposit tokens that are not whitelisted.
+/// An `ibc/` denom must also come from a trusted route, even for the owner.
+///
+/// Shared by `execute_deposit_token` and the `SimulateDeposit` query so both agree.
+fn validate_deposit(
+    deps: Deps,
+    config: &Config,
+    sender: &Addr,
+    token_address: &str,
+    amount: Uint128,
+) -> Result<TokenConfig, ContractError> {
+    // Both CW20 addresses and native denoms must be whitelisted.
+    if !config.whitelist.iter().any(|token| token == token_address)
+        && !config.owner_bypass(sender)
+    {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address.to_string(),
+        });
+    }
+    check_trusted_ibc_denom(deps, token_address)?;
+    if amount.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+
+    // Enforce the token's per-deposit limits.
+    let token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, token_address)?
+        .unwrap_or_default();
+    let min_amount = raw_min_amount(deps, token_address, &token_config)?;
+    if amount < min_amount || amount > token_config.max_amount {
+        return Err(ContractError::OutsideTokenLimits {
+            amount,
+            min: min_amount,
+            max: token_config.max_amount,
+        });
+    }
+
+    if !is_native_denom(token_address) {
+        deps.api.addr_validate(token_address)?;
+    }
+    Ok(token_config)
+}
+
+/// Returns when the token's cooldown since `user`'s previous deposit ends, or `None` if
+/// the token has no cooldown or the user never deposited it.
+fn cooldown_ends_at(
+    storage: &dyn Storage,
+    token_config: &TokenConfig,
+    user: &Addr,
+    token_address: &str,
+) -> StdResult<Option<Timestamp>> {
+    if token_config.cooldown_secs == 0 {
+        return Ok(None);
+    }
+    Ok(LAST_DEPOSITS
+        .may_load(storage, (user, token_address))?
+        .map(|last| last.plus_seconds(token_config.cooldown_secs)))
+}
+
+/// Rejects a deposit by `user` made before the token's cooldown since their previous
+/// deposit has elapsed.
+fn check_cooldown_elapsed(
+    storage: &dyn Storage,
+    token_config: &TokenConfig,
+    user: &Addr,
+    token_address: &str,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    if let Some(ready) = cooldown_ends_at(storage, token_config, user, token_address)? {
+        if now < ready {
+            return Err(ContractError::CooldownActive {
+                remaining: ready.seconds() - now.seconds(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Like `check_cooldown_elapsed`, but also records `now` as the user's latest deposit.
+fn check_deposit_cooldown(
+    storage: &mut dyn Storage,
+    token_config: &TokenConfig,
+    user: &Addr,
+    token_address: &str,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    check_cooldown_elapsed(storage, token_config, user, token_address, now)?;
+    if token_config.cooldown_secs > 0 {
+        LAST_DEPOSITS.save(storage, (user, token_address), &now)?;
+    }
+    Ok(())
+}
+
+/// Every deposit check that does not depend on attached funds: `validate_deposit`, the
+/// cooldown and the epoch cap. Changes no state.
+///
+/// Shared by `PreflightDeposit` and the `SimulateDeposit` query so both agree.
+fn check_deposit_allowed(
+    deps: Deps,
+    config: &Config,
+    sender: &Addr,
+    token_address: &str,
+    amount: Uint128,
+    now: Timestamp,
+) -> Result<TokenConfig, ContractError> {
+    let token_config = validate_deposit(deps, config, sender, token_address, amount)?;
+    check_cooldown_elapsed(deps.storage, &token_config, sender, token_address, now)?;
+    check_epoch_cap(deps.storage, &token_config, token_address, amount, now)?;
+    Ok(token_config)
+}
+
+/// Returns the token's epoch at `now`: the stored one while it lasts, otherwise a fresh
+/// epoch starting at `now`. `None` if the token has no epoch cap.
+fn current_epoch(
+    storage: &dyn Storage,
+    token_config: &TokenConfig,
+    token_address: &str,
+    now: Timestamp,
+) -> StdResult<Option<EpochUsage>> {
+    if token_config.epoch_len_secs == 0 {
+        return Ok(None);
+    }
+    Ok(Some(match EPOCH_USAGE.may_load(storage, token_address)? {
+        Some(usage) if now < usage.epoch_start_ts.plus_seconds(token_config.epoch_len_secs) => {
+            usage
+        }
+        _ => EpochUsage {
+            epoch_start_ts: now,
+            epoch_used: Uint128::zero(),
+        },
+    }))
+}
+
+/// Checks that a deposit of `amount` fits the token's epoch cap at `now` and returns the
+/// epoch with the deposit counted, or `None` if the token has no epoch cap.
+fn check_epoch_cap(
+    storage: &dyn Storage,
+    token_config: &TokenConfig,
+    token_address: &str,
+    amount: Uint128,
+    now: Timestamp,
+) -> Result<Option<EpochUsage>, ContractError> {
+    let Some(mut usage) = current_epoch(storage, token_config, token_address, now)? else {
+        return Ok(None);
+    };
+    usage.epoch_used = usage
+        .epoch_used
+        .checked_add(amount)
+        .ok()
+        .filter(|used| *used <= token_config.epoch_cap)
+        .ok_or(ContractError::EpochCapExceeded {
+            remaining: token_config.epoch_cap.saturating_sub(usage.epoch_used),
+        })?;
+    Ok(Some(usage))
+}
+
+/// Rejects an `ibc/` denom whose hash does not match a route registered with
+/// `RegisterIbcPath`. Other tokens pass.
+fn check_trusted_ibc_denom(deps: Deps, token_address: &str) -> Result<(), ContractError> {
+    if token_address.starts_with("ibc/") && !TRUSTED_IBC_DENOMS.has(deps.storage, token_address)
+    {
+        return Err(ContractError::UntrustedIbcDenom {
+            denom: token_address.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Deposits tokens from the user into the admin wallet's account.
+///
+/// This function handles both CW20 tokens and native tokens:
+/// - For CW20 tokens: Provide token_address (contract address) and amount (requires allowance)
+/// - For native tokens: Provide token_address (denom string) and amount, with matching funds sent
+///
+/// The optional beneficiary only changes who the deposit is credited to in the event.
+///
+/// For CW20 tokens in balance-delta mode the `deposit_token` event reports the requested
+/// amount. The epoch cap, the global stats and the post-deposit hook wait for `reply`, which
+/// uses the admin wallet's actual balance growth.
+pub fn execute_deposit_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_address: String,
+    amount: Uint128,
+    beneficiary: Option<String>,
+) -> Result<Response, ContractError> {
+    // Load the stored config.
+    let mut config = CONFIG.load(deps.storage)?;
+
+    // Reject oversized fund lists before the token is looked up.
+    if info.funds.len() > MAX_DEPOSIT_FUNDS as usize {
+        return Err(ContractError::TooManyFunds {
+            count: info.funds.len(),
+            max: MAX_DEPOSIT_FUNDS,
+        });
+    }
+
+    // The credited party defaults to the sender.
+    let beneficiary = match beneficiary {
+        Some(beneficiary) => deps.api.addr_validate(&beneficiary)?,
+        None => info.sender.clone(),
+    };
+
+    let token_config =
+        validate_deposit(deps.as_ref(), &config, &info.sender, &token_address, amount)?;
+    // Validation passed, so a token missing from the whitelist means the owner bypass applied.
+    let owner_bypass = !config.whitelist.contains(&token_address);
+    check_deposit_cooldown(
+        deps.storage,
+        &token_config,
+        &info.sender,
+        &token_address,
+        env.block.time,
+    )?;
+    // Balance-delta deposits are counted in `reply`, once the received amount is known.
+    let balance_delta = token_config.balance_delta && !is_native_denom(&token_address);
+    let usage =
+        check_epoch_cap(deps.storage, &token_config, &token_address, amount, env.block.time)?;
+    if let (Some(usage), false) = (usage, balance_delta) {
+        EPOCH_USAGE.save(deps.storage, &token_address, &usage)?;
+    }
+
+    // Check if the token_address is a denom (starts with a specific pattern like "u")
+    let (transfer_msg, token_type) = if is_native_denom(&token_address) {
+        // Handle native tokens
+        
+        // Find the specified denom in the sent funds
         let sent_amount = info
             .funds
             .iter()
@@ -200,127 +1890,2429 @@ pub fn execute_deposit_token(
             .map(|coin| coin.amount)
             .unwrap_or(Uint128::zero());
         
+        // Only the deposited denom may be attached; other coins would be stranded in the contract.
+        if info.funds.len() != 1 {
+            return Err(ContractError::UnexpectedFunds {
+                denom: token_address,
+            });
+        }
+
         // Verify the sent amount matches the specified amount
         if sent_amount != amount {
-            return Err(StdError::generic_err(format!(
-                "Sent amount ({}) doesn't match specified amount ({}) for denom {}",
-                sent_amount, amount, token_address
-            )));
+            return Err(ContractError::AmountMismatch {
+                denom: token_address,
+                sent: sent_amount,
+                expected: amount,
+            });
+        }
+        
+        // Create a bank send message for just this denom
+        let bank_msg = CosmosMsg::Bank(cosmwasm_std::BankMsg::Send {
+            to_address: config.admin_wallet.to_string(),
+            amount: vec![cosmwasm_std::Coin {
+                denom: token_address.clone(),
+                amount,
+            }],
+        });
+        (bank_msg, "native")
+    } else {
+        // Handle CW20 tokens
+        let token_addr = deps.api.addr_validate(&token_address)?;
+
+        // A CW20 deposit is pulled via allowance, so no native funds may be attached.
+        // In particular a coin sharing the token's name would otherwise be counted twice.
+        if !info.funds.is_empty() {
+            return Err(ContractError::FundsWithCw20Deposit {});
+        }
+
+        // Construct the CW20 TransferFrom message.
+        let transfer_from_msg = cw20_base::msg::ExecuteMsg::TransferFrom {
+            owner: info.sender.to_string(),
+            recipient: config.admin_wallet.to_string(),
+            amount,
+        };
+        let exec_transfer = WasmMsg::Execute {
+            contract_addr: token_addr.to_string(),
+            msg: to_json_binary(&transfer_from_msg)?,
+            funds: vec![],
+        };
+        (CosmosMsg::Wasm(exec_transfer), "cw20")
+    };
+
+    // Give every deposit a sequence number so consumers can order deposits within a block.
+    config.deposit_seq = config
+        .deposit_seq
+        .checked_add(1)
+        .ok_or(ContractError::DepositSeqOverflow {})?;
+    CONFIG.save(deps.storage, &config)?;
+
+    // For balance-delta tokens the transfer replies, so its actual effect can be checked.
+    let transfer_msg = if balance_delta {
+        let min_amount = raw_min_amount(deps.as_ref(), &token_address, &token_config)?;
+        let pending = PendingBalanceCheck {
+            token_address: token_address.clone(),
+            deposit_seq: config.deposit_seq,
+            balance_before: query_cw20_balance(
+                deps.as_ref(),
+                &token_address,
+                &config.admin_wallet,
+            )?,
+            min_received: min_amount.max(Uint128::one()),
+            sender: info.sender.clone(),
+            beneficiary: beneficiary.clone(),
+            amount,
+        };
+        PENDING_BALANCE_CHECK.save(deps.storage, &pending)?;
+        SubMsg::reply_on_success(transfer_msg, BALANCE_DELTA_REPLY_ID)
+    } else {
+        record_deposit_stats(deps.storage, &info.sender, &token_address, amount)?;
+        SubMsg::new(transfer_msg)
+    };
+
+    // Tokens without a mapping are their own logical asset.
+    let canonical_id = token_config
+        .canonical_id
+        .unwrap_or_else(|| token_address.clone());
+
+    // The hook runs after the transfer, and its failure reverts the deposit.
+    let mut response = Response::new().add_submessage(transfer_msg);
+    if let (Some(hook), false) = (&config.post_deposit_hook, balance_delta) {
+        let hook_msg = post_deposit_hook_msg(hook, &beneficiary, &token_address, amount)?;
+        response = response.add_message(hook_msg);
+    }
+
+    Ok(response
+        .add_event(new_event("deposit_token")
+            .add_attribute("user", info.sender.to_string())
+            .add_attribute("beneficiary", beneficiary.to_string())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("net_amount", config.net_amount(&token_address, amount).to_string())
+            .add_attribute("token_address", token_address)
+            .add_attribute("token_type", token_type)
+            .add_attribute("canonical_id", canonical_id)
+            .add_attribute("deposit_seq", config.deposit_seq.to_string())
+            .add_attribute("owner_bypass", owner_bypass.to_string())
+            .add_attribute("balance_delta", balance_delta.to_string())
+            .add_attribute("timestamp", env.block.time.seconds().to_string())))
+}
+
+/// Adds a deposit to the token's volume and counts `sender` if it is a new depositor.
+fn record_deposit_stats(
+    storage: &mut dyn Storage,
+    sender: &Addr,
+    token_address: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    let volume = VOLUME_BY_TOKEN
+        .may_load(storage, token_address)?
+        .unwrap_or_default()
+        .checked_add(amount)?;
+    VOLUME_BY_TOKEN.save(storage, token_address, &volume)?;
+    if !DEPOSITORS.has(storage, sender) {
+        DEPOSITORS.save(storage, sender, &())?;
+        let unique_depositors = UNIQUE_DEPOSITORS.may_load(storage)?.unwrap_or_default();
+        UNIQUE_DEPOSITORS.save(storage, &(unique_depositors + 1))?;
+    }
+    Ok(())
+}
+
+/// Returns sha256(amount as 16 big-endian bytes || nonce || user address).
+pub fn commitment_hash(amount: Uint128, nonce: &[u8], user: &Addr) -> HexBinary {
+    let mut hasher = Sha256::new();
+    hasher.update(amount.to_be_bytes());
+    hasher.update(nonce);
+    hasher.update(user.as_bytes());
+    HexBinary::from(hasher.finalize().as_slice())
+}
+
+/// Returns the bytes a user signs to authorize `intent` on this contract and chain: the
+/// JSON of a `DepositIntentSignData`, with fields in declaration order and no whitespace.
+/// `Uint128` amounts and `Timestamp` nanoseconds are JSON strings, the nonce a number.
+pub fn deposit_intent_sign_bytes(
+    chain_id: &str,
+    contract: &Addr,
+    intent: &DepositIntent,
+) -> StdResult<Vec<u8>> {
+    to_json_vec(&DepositIntentSignData {
+        domain: DEPOSIT_INTENT_DOMAIN.to_string(),
+        chain_id: chain_id.to_string(),
+        contract: contract.to_string(),
+        intent: intent.clone(),
+    })
+}
+
+/// Returns the ADR-036 sign doc of `signer` signing `data`: the amino JSON `StdSignDoc`
+/// holding a single `sign/MsgSignData`, with an empty chain id, zero account number and
+/// sequence, no fee and an empty memo, keys sorted and no whitespace. Wallets sign the
+/// sha256 of these bytes in `signArbitrary`.
+pub fn adr036_sign_doc(signer: &str, data: &[u8]) -> String {
+    format!(
+        "{{\"account_number\":\"0\",\"chain_id\":\"\",\"fee\":{{\"amount\":[],\"gas\":\"0\"}},\
+         \"memo\":\"\",\"msgs\":[{{\"type\":\"sign/MsgSignData\",\"value\":{{\"data\":\"{}\",\
+         \"signer\":\"{}\"}}}}],\"sequence\":\"0\"}}",
+        Binary::from(data).to_base64(),
+        signer,
+    )
+}
+
+/// Returns the address of the account holding the secp256k1 public key `pubkey`: the
+/// ripemd160 of its sha256, in the chain's bech32 form.
+pub fn secp256k1_pubkey_address(api: &dyn Api, pubkey: &[u8]) -> StdResult<Addr> {
+    let hash = Ripemd160::digest(Sha256::digest(pubkey));
+    api.addr_humanize(&CanonicalAddr::from(hash.as_slice()))
+}
+
+/// Verifies a user's signed deposit intent and performs it on their behalf.
+pub fn execute_relayed_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    intent: DepositIntent,
+    pubkey: Binary,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    if env.block.time > intent.deadline {
+        return Err(ContractError::IntentExpired {});
+    }
+    // A relayer can attach its own funds but never move the user's native coins.
+    if is_native_denom(&intent.token_address) {
+        return Err(ContractError::RelayedNativeDeposit {});
+    }
+    let user = deps.api.addr_validate(&intent.user)?;
+    if secp256k1_pubkey_address(deps.api, &pubkey)? != user {
+        return Err(ContractError::IntentSignerMismatch {});
+    }
+    let sign_bytes =
+        deposit_intent_sign_bytes(&env.block.chain_id, &env.contract.address, &intent)?;
+    let sign_doc = adr036_sign_doc(user.as_str(), &sign_bytes);
+    let hash = Sha256::digest(sign_doc.as_bytes());
+    if !deps.api.secp256k1_verify(&hash, &signature, &pubkey).unwrap_or(false) {
+        return Err(ContractError::InvalidIntentSignature {});
+    }
+    let next_nonce = RELAY_NONCES.may_load(deps.storage, &user)?.unwrap_or_default();
+    if intent.nonce != next_nonce {
+        return Err(ContractError::InvalidNonce {
+            expected: next_nonce,
+            got: intent.nonce,
+        });
+    }
+    RELAY_NONCES.save(deps.storage, &user, &(next_nonce + 1))?;
+
+    // The deposit runs as if the user sent it, so every check and the attribution use them.
+    let user_info = MessageInfo {
+        sender: user.clone(),
+        funds: info.funds,
+    };
+    let response = execute_deposit_token(
+        deps,
+        env,
+        user_info,
+        intent.token_address,
+        intent.amount,
+        None,
+    )?;
+    Ok(response.add_event(new_event("relayed_deposit")
+        .add_attribute("user", user)
+        .add_attribute("relayer", info.sender)
+        .add_attribute("nonce", intent.nonce.to_string())))
+}
+
+/// Stores the sender's deposit commitment, replacing any previous one.
+pub fn execute_commit_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    commitment: HexBinary,
+) -> Result<Response, ContractError> {
+    if commitment.len() != 32 {
+        return Err(ContractError::InvalidCommitment {});
+    }
+    let expires = env.block.time.plus_seconds(COMMITMENT_TTL_SECONDS);
+    COMMITMENTS.save(
+        deps.storage,
+        &info.sender,
+        &DepositCommitment {
+            commitment: commitment.clone(),
+            expires,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_event(new_event("commit_deposit")
+            .add_attribute("user", info.sender)
+            .add_attribute("commitment", commitment.to_hex())
+            .add_attribute("expires", expires.seconds().to_string())))
+}
+
+/// Checks the revealed `amount` and `nonce` against the sender's commitment, consumes it,
+/// and performs the deposit.
+pub fn execute_reveal_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_address: String,
+    amount: Uint128,
+    nonce: HexBinary,
+) -> Result<Response, ContractError> {
+    let commitment = COMMITMENTS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or(ContractError::NoCommitment {})?;
+    if env.block.time > commitment.expires {
+        return Err(ContractError::CommitmentExpired {});
+    }
+    if commitment_hash(amount, &nonce, &info.sender) != commitment.commitment {
+        return Err(ContractError::CommitmentMismatch {});
+    }
+    COMMITMENTS.remove(deps.storage, &info.sender);
+    execute_deposit_token(deps, env, info, token_address, amount, None)
+}
+
+/// Deposits the sender's full unexpired allowance to this contract, capped by their balance.
+///
+/// The amount is resolved first and then goes through `execute_deposit_token`, so it is
+/// subject to the same whitelist and limit checks as an explicit deposit.
+pub fn execute_deposit_cw20_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_address: String,
+) -> Result<Response, ContractError> {
+    if is_native_denom(&token_address) {
+        return Err(ContractError::NotCw20 {});
+    }
+    // Check the whitelist before querying so arbitrary contracts are never called.
+    let config = CONFIG.load(deps.storage)?;
+    if !config.whitelist.contains(&token_address) {
+        return Err(ContractError::NotWhitelisted {
+            token: token_address,
+        });
+    }
+
+    let token_addr = deps.api.addr_validate(&token_address)?;
+    let allowance: AllowanceResponse = deps.querier.query_wasm_smart(
+        &token_addr,
+        &Cw20QueryMsg::Allowance {
+            owner: info.sender.to_string(),
+            spender: env.contract.address.to_string(),
+        },
+    )?;
+    let approved = if allowance.expires.is_expired(&env.block) {
+        Uint128::zero()
+    } else {
+        allowance.allowance
+    };
+    let balance: BalanceResponse = deps.querier.query_wasm_smart(
+        &token_addr,
+        &Cw20QueryMsg::Balance {
+            address: info.sender.to_string(),
+        },
+    )?;
+
+    let amount = approved.min(balance.balance);
+    execute_deposit_token(deps, env, info, token_address, amount, None)
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetConfig {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::SimulateDeposit {
+            user,
+            token_address,
+            amount,
+        } => to_json_binary(&query_simulate_deposit(deps, env, user, token_address, amount)?),
+        QueryMsg::Whitelist { start_after, limit } => {
+            to_json_binary(&query_whitelist(deps, start_after, limit)?)
+        }
+        QueryMsg::WhitelistDetailed { start_after, limit } => {
+            to_json_binary(&query_whitelist_detailed(deps, env, start_after, limit)?)
+        }
+        QueryMsg::WhitelistEntry { token_address } => {
+            to_json_binary(&query_whitelist_entry(deps, token_address)?)
+        }
+        QueryMsg::DepositStatus { token_address } => {
+            to_json_binary(&query_deposit_status(deps, env, token_address)?)
+        }
+        QueryMsg::RemainingAllowance {
+            user,
+            token_address,
+        } => to_json_binary(&query_remaining_allowance(deps, env, user, token_address)?),
+        QueryMsg::GlobalStats {} => to_json_binary(&query_global_stats(deps)?),
+        QueryMsg::RelayState { user } => to_json_binary(&query_relay_state(deps, user)?),
+        QueryMsg::Version {} => to_json_binary(&query_version(deps)?),
+    }
+}
+
+/// Returns the `cw2` contract version together with the feature bits.
+pub fn query_version(deps: Deps) -> StdResult<VersionResponse> {
+    let contract_version = cw2::get_contract_version(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    Ok(VersionResponse {
+        contract: contract_version.contract,
+        version: contract_version.version,
+        known_feature_flags: KNOWN_FEATURE_FLAGS,
+        feature_flags: config.feature_flags,
+    })
+}
+
+/// Returns a user's relay key and next relay nonce.
+pub fn query_relay_state(deps: Deps, user: String) -> StdResult<RelayStateResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    Ok(RelayStateResponse {
+        next_nonce: RELAY_NONCES.may_load(deps.storage, &user)?.unwrap_or_default(),
+    })
+}
+
+/// Returns deposit totals across all users and tokens.
+pub fn query_global_stats(deps: Deps) -> StdResult<GlobalStatsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let volume_by_token = VOLUME_BY_TOKEN
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (token_address, volume) = item?;
+            Ok(TokenVolume { token_address, volume })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(GlobalStatsResponse {
+        total_deposits: config.deposit_seq,
+        unique_depositors: UNIQUE_DEPOSITORS.may_load(deps.storage)?.unwrap_or_default(),
+        volume_by_token,
+    })
+}
+
+/// Returns the stored settings of a whitelisted token, with CW20 decimals from the cache
+/// or, if nothing is cached, fetched live.
+pub fn query_whitelist_entry(
+    deps: Deps,
+    token_address: String,
+) -> StdResult<WhitelistEntryResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.whitelist.binary_search(&token_address).is_err() {
+        return Err(StdError::generic_err(
+            ContractError::NotWhitelisted { token: token_address }.to_string(),
+        ));
+    }
+    let token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    let decimals = match token_config.decimals {
+        Some(decimals) => Some(decimals),
+        None if is_native_denom(&token_address) => None,
+        None => Some(query_token_decimals(deps, &token_address)?),
+    };
+    Ok(WhitelistEntryResponse {
+        token_address,
+        token_config,
+        decimals,
+    })
+}
+
+/// Returns up to `limit` whitelisted tokens that sort after `start_after`.
+pub fn query_whitelist(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<WhitelistResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_WHITELIST_LIMIT).min(MAX_WHITELIST_LIMIT) as usize;
+    let start = match start_after {
+        Some(start_after) => config.whitelist.partition_point(|token| *token <= start_after),
+        None => 0,
+    };
+    let tokens = config.whitelist.into_iter().skip(start).take(limit).collect();
+    Ok(WhitelistResponse { tokens })
+}
+
+/// Returns a page of whitelisted tokens with their settings, for rendering the whole
+/// whitelist at once.
+pub fn query_whitelist_detailed(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<WhitelistDetailedResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let tokens = query_whitelist(deps, start_after, limit)?
+        .tokens
+        .into_iter()
+        .map(|token| {
+            let token_config = TOKEN_CONFIGS
+                .may_load(deps.storage, &token)?
+                .unwrap_or_default();
+            let native = is_native_denom(&token);
+            let epoch = current_epoch(deps.storage, &token_config, &token, env.block.time)?;
+            Ok(WhitelistDetailedEntry {
+                kind: if native { "native" } else { "cw20" }.to_string(),
+                enabled: check_trusted_ibc_denom(deps, &token).is_ok(),
+                min_amount: raw_min_amount(deps, &token, &token_config)?,
+                max_amount: token_config.max_amount,
+                cap: epoch.is_some().then_some(token_config.epoch_cap),
+                cap_used: epoch.map(|epoch| epoch.epoch_used),
+                tax_bps: if native { config.native_tax_bps } else { 0 },
+                token,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(WhitelistDetailedResponse { tokens })
+}
+
+/// Consolidates every gate on deposits of a token, independent of the depositor and amount.
+///
+/// The owner bypass is not reflected, since it depends on who deposits.
+pub fn query_deposit_status(
+    deps: Deps,
+    env: Env,
+    token_address: String,
+) -> StdResult<DepositStatusResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    let min_amount = raw_min_amount(deps, &token_address, &token_config)?;
+    let cap_remaining = current_epoch(deps.storage, &token_config, &token_address, env.block.time)?
+        .map(|epoch| token_config.epoch_cap.saturating_sub(epoch.epoch_used));
+    let reason = if config.whitelist.binary_search(&token_address).is_err() {
+        Some(ContractError::NotWhitelisted { token: token_address }.to_string())
+    } else {
+        check_trusted_ibc_denom(deps, &token_address).err().map(|err| err.to_string())
+    };
+    Ok(DepositStatusResponse {
+        allowed: reason.is_none(),
+        reason,
+        min_amount,
+        max_amount: token_config.max_amount,
+        cap_remaining,
+    })
+}
+
+/// Returns the largest single deposit `user` can make of a token, for a "max" button.
+///
+/// Combines the same gates as `validate_deposit` with the epoch cap. A cooldown does not
+/// lower `remaining` but is reported as `next_allowed_ts`.
+pub fn query_remaining_allowance(
+    deps: Deps,
+    env: Env,
+    user: String,
+    token_address: String,
+) -> StdResult<RemainingAllowanceResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let user = deps.api.addr_validate(&user)?;
+    let token_config = TOKEN_CONFIGS
+        .may_load(deps.storage, &token_address)?
+        .unwrap_or_default();
+    let now = env.block.time;
+
+    let accepted = (config.whitelist.binary_search(&token_address).is_ok()
+        || config.owner_bypass(&user))
+        && check_trusted_ibc_denom(deps, &token_address).is_ok();
+    let mut remaining = if accepted { token_config.max_amount } else { Uint128::zero() };
+    if let Some(epoch) = current_epoch(deps.storage, &token_config, &token_address, now)? {
+        remaining = remaining.min(token_config.epoch_cap.saturating_sub(epoch.epoch_used));
+    }
+    if remaining < raw_min_amount(deps, &token_address, &token_config)? {
+        remaining = Uint128::zero();
+    }
+
+    let next_allowed_ts = cooldown_ends_at(deps.storage, &token_config, &user, &token_address)?
+        .filter(|ready| now < *ready);
+    Ok(RemainingAllowanceResponse {
+        remaining,
+        next_allowed_ts,
+    })
+}
+
+/// Reports whether a deposit would pass validation, with the reason if not.
+pub fn query_simulate_deposit(
+    deps: Deps,
+    env: Env,
+    user: String,
+    token_address: String,
+    amount: Uint128,
+) -> StdResult<SimulateDepositResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let result = deps
+        .api
+        .addr_validate(&user)
+        .map_err(ContractError::from)
+        .and_then(|user| {
+            check_deposit_allowed(deps, &config, &user, &token_address, amount, env.block.time)
+        });
+    Ok(match result {
+        Ok(_) => SimulateDepositResponse {
+            ok: true,
+            reason: None,
+            reason_code: None,
+            net_amount: config.net_amount(&token_address, amount),
+            fee_amount: Uint128::zero(),
+        },
+        Err(err) => SimulateDepositResponse {
+            ok: false,
+            reason: Some(err.to_string()),
+            reason_code: Some(err.reason_code().to_string()),
+            net_amount: Uint128::zero(),
+            fee_amount: Uint128::zero(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env, MockApi};
+    use cosmwasm_std::{coins, Addr, BankMsg, Coin, ContractResult, SystemResult, WasmQuery};
+
+    /// Builds a config owned by `owner` with no whitelist manager split and default bounds.
+    fn mock_config(api: &MockApi, whitelist: Vec<String>) -> Config {
+        Config {
+            owner: api.addr_make("owner"),
+            admin_wallet: api.addr_make("admin_wallet"),
+            whitelist,
+            min_decimals: 0,
+            max_decimals: u8::MAX,
+            whitelist_manager: api.addr_make("owner"),
+            deposit_seq: 0,
+            owner_bypass_enabled: false,
+            admin_change_delay: 0,
+            pending_admin_wallet: None,
+            native_tax_bps: 0,
+            post_deposit_hook: None,
+            feature_flags: 0,
         }
+    }
+
+    #[test]
+    fn test_native_token_detection() {
+        println!("testing native token detection with uxion");
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        
+        // Set up contract config
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
         
-        if sent_amount.is_zero() {
-            return Err(StdError::generic_err(format!(
-                "No tokens with denom {} were sent with transaction", 
-                token_address
-            )));
+        // Test "uxion" as token_address
+        let amount = Uint128::new(1000);
+        let info = message_info(&deps.api.addr_make("sender"), &coins(1000, "uxion"));
+        
+        let result = execute_deposit_token(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "uxion".to_string(),
+            amount,
+            None,
+        ).unwrap();
+        
+        // Verify it was treated as a native token by checking for a bank message
+        assert_eq!(result.messages.len(), 1);
+        match &result.messages[0].msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount: send_amount }) => {
+                assert_eq!(to_address, config.admin_wallet.as_str());
+                assert_eq!(send_amount.len(), 1);
+                assert_eq!(send_amount[0].denom, "uxion");
+                assert_eq!(send_amount[0].amount, amount);
+            },
+            _ => panic!("Expected Bank message, got something else"),
         }
         
-        // Create a bank send message for just this denom
-        let bank_msg = CosmosMsg::Bank(cosmwasm_std::BankMsg::Send {
-            to_address: config.admin_wallet.to_string(),
-            amount: vec![cosmwasm_std::Coin {
-                denom: token_address.clone(),
-                amount,
-            }],
+        // Check event attributes that indicate it was treated as native
+        let deposit_event = result.events.iter().find(|e| e.ty == "deposit_token").unwrap();
+        let token_type = deposit_event.attributes.iter()
+            .find(|attr| attr.key == "token_type")
+            .unwrap();
+        assert_eq!(token_type.value, "native");
+        let schema = deposit_event.attributes.iter()
+            .find(|attr| attr.key == "schema")
+            .unwrap();
+        assert_eq!(schema.value, EVENT_SCHEMA_VERSION.to_string());
+    }
+
+    #[test]
+    fn test_add_whitelisted_token_checks_decimals() {
+        let mut deps = mock_dependencies();
+        let owner = deps.api.addr_make("owner");
+        let token = deps.api.addr_make("token");
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&TokenInfoResponse {
+                    name: "Token".to_string(),
+                    symbol: "TKN".to_string(),
+                    decimals: 18,
+                    total_supply: Uint128::zero(),
+                })
+                .unwrap(),
+            )),
+            _ => panic!("unexpected query"),
+        });
+
+        let mut config = mock_config(&deps.api, vec![]);
+        config.min_decimals = 6;
+        config.max_decimals = 9;
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        // 18 decimals is above the configured maximum.
+        let err = execute_add_whitelisted_token(
+            deps.as_mut(),
+            message_info(&owner, &[]),
+            token.to_string(),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DecimalsOutOfRange { .. }));
+
+        execute_set_decimals_bounds(deps.as_mut(), message_info(&owner, &[]), 6, 18).unwrap();
+        execute_add_whitelisted_token(
+            deps.as_mut(),
+            message_info(&owner, &[]),
+            token.to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap().whitelist, vec![token.to_string()]);
+    }
+
+    #[test]
+    fn test_token_info_is_cached_until_refreshed() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        let set_decimals = |querier: &mut cosmwasm_std::testing::MockQuerier, decimals: u8| {
+            querier.update_wasm(move |query| match query {
+                WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&TokenInfoResponse {
+                        name: "Token".to_string(),
+                        symbol: "TKN".to_string(),
+                        decimals,
+                        total_supply: Uint128::zero(),
+                    })
+                    .unwrap(),
+                )),
+                _ => panic!("unexpected query"),
+            });
+        };
+        set_decimals(&mut deps.querier, 6);
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        execute_add_whitelisted_token(deps.as_mut(), owner.clone(), token.to_string(), None)
+            .unwrap();
+        let token_config = TOKEN_CONFIGS.load(&deps.storage, token.as_str()).unwrap();
+        assert_eq!(token_config.decimals, Some(6));
+        assert_eq!(token_config.symbol.as_deref(), Some("TKN"));
+
+        // The cache is used even though the token now reports different decimals.
+        set_decimals(&mut deps.querier, 8);
+        let entry = query_whitelist_entry(deps.as_ref(), token.to_string()).unwrap();
+        assert_eq!(entry.decimals, Some(6));
+
+        execute_refresh_token_info(deps.as_mut(), owner.clone(), token.to_string()).unwrap();
+        let entry = query_whitelist_entry(deps.as_ref(), token.to_string()).unwrap();
+        assert_eq!(entry.decimals, Some(8));
+
+        let err = execute_refresh_token_info(deps.as_mut(), owner, "uxion".to_string())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::NotWhitelisted { .. }));
+    }
+
+    #[test]
+    fn test_cw20_deposit_rejects_matching_native_funds() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        let config = mock_config(&deps.api, vec![token.to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let info = message_info(&sender, &coins(1000, token.as_str()));
+        let err = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            token.to_string(),
+            Uint128::new(1000),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::FundsWithCw20Deposit {});
+    }
+
+    #[test]
+    fn test_deposit_rejects_too_many_coins() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let funds: Vec<Coin> = (0..=MAX_DEPOSIT_FUNDS)
+            .map(|i| Coin::new(1u128, format!("udust{i}")))
+            .collect();
+        let deposit = |deps: DepsMut, funds: &[Coin]| {
+            execute_deposit_token(
+                deps,
+                mock_env(),
+                message_info(&sender, funds),
+                "uxion".to_string(),
+                Uint128::new(1),
+                None,
+            )
+        };
+        let err = deposit(deps.as_mut(), &funds).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TooManyFunds {
+                count: funds.len(),
+                max: MAX_DEPOSIT_FUNDS,
+            }
+        );
+        // Within the limit the usual single-denom check applies instead.
+        let err = deposit(deps.as_mut(), &funds[1..]).unwrap_err();
+        assert!(matches!(err, ContractError::UnexpectedFunds { .. }));
+        deposit(deps.as_mut(), &[Coin::new(1u128, "uxion")]).unwrap();
+    }
+
+    #[test]
+    fn test_epoch_cap_resets_when_a_new_epoch_begins() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let err = execute_set_epoch_cap(
+            deps.as_mut(),
+            message_info(&alice, &[]),
+            "uxion".to_string(),
+            60,
+            Uint128::new(100),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute_set_epoch_cap(deps.as_mut(), owner, "uxion".to_string(), 60, Uint128::new(100))
+            .unwrap();
+
+        let start = mock_env().block.time;
+        let env_at = |elapsed: u64| {
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(elapsed);
+            env
+        };
+        let deposit = |deps: DepsMut, user: &Addr, amount: u128, elapsed: u64| {
+            execute_deposit_token(
+                deps,
+                env_at(elapsed),
+                message_info(user, &coins(amount, "uxion")),
+                "uxion".to_string(),
+                Uint128::new(amount),
+                None,
+            )
+        };
+        // The cap is shared by all users.
+        deposit(deps.as_mut(), &alice, 70, 0).unwrap();
+        let err = deposit(deps.as_mut(), &bob, 40, 59).unwrap_err();
+        assert_eq!(err, ContractError::EpochCapExceeded { remaining: Uint128::new(30) });
+
+        let simulate = query_simulate_deposit(
+            deps.as_ref(),
+            env_at(59),
+            bob.to_string(),
+            "uxion".to_string(),
+            Uint128::new(40),
+        )
+        .unwrap();
+        assert!(!simulate.ok);
+        let status = query_deposit_status(deps.as_ref(), env_at(59), "uxion".to_string()).unwrap();
+        assert_eq!(status.cap_remaining, Some(Uint128::new(30)));
+        let entry = &query_whitelist_detailed(deps.as_ref(), env_at(59), None, None)
+            .unwrap()
+            .tokens[0];
+        assert_eq!((entry.cap, entry.cap_used), (Some(Uint128::new(100)), Some(Uint128::new(70))));
+
+        // The first deposit after the epoch ended starts the next one.
+        let status = query_deposit_status(deps.as_ref(), env_at(60), "uxion".to_string()).unwrap();
+        assert_eq!(status.cap_remaining, Some(Uint128::new(100)));
+        deposit(deps.as_mut(), &bob, 40, 75).unwrap();
+        let usage = EPOCH_USAGE.load(deps.as_ref().storage, "uxion").unwrap();
+        assert_eq!(usage.epoch_start_ts, start.plus_seconds(75));
+        assert_eq!(usage.epoch_used, Uint128::new(40));
+        let err = deposit(deps.as_mut(), &alice, 61, 134).unwrap_err();
+        assert_eq!(err, ContractError::EpochCapExceeded { remaining: Uint128::new(60) });
+    }
+
+    #[test]
+    fn test_remaining_allowance_combines_limits_and_cooldown() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let token = "uxion".to_string();
+        execute_set_token_limits(
+            deps.as_mut(),
+            owner.clone(),
+            token.clone(),
+            Uint128::new(5),
+            Uint128::new(100),
+        )
+        .unwrap();
+        execute_set_epoch_cap(deps.as_mut(), owner.clone(), token.clone(), 60, Uint128::new(150))
+            .unwrap();
+        execute_set_token_cooldown(deps.as_mut(), owner, token.clone(), 30).unwrap();
+
+        let alice = deps.api.addr_make("alice");
+        let start = mock_env().block.time;
+        let env_at = |elapsed: u64| {
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(elapsed);
+            env
+        };
+        let allowance = |deps: Deps, token: &str, elapsed: u64| {
+            let msg = QueryMsg::RemainingAllowance {
+                user: alice.to_string(),
+                token_address: token.to_string(),
+            };
+            let res: RemainingAllowanceResponse =
+                cosmwasm_std::from_json(query(deps, env_at(elapsed), msg).unwrap()).unwrap();
+            (res.remaining.u128(), res.next_allowed_ts)
+        };
+        let deposit = |deps: DepsMut, amount: u128, elapsed: u64| {
+            execute_deposit_token(
+                deps,
+                env_at(elapsed),
+                message_info(&alice, &coins(amount, "uxion")),
+                "uxion".to_string(),
+                Uint128::new(amount),
+                None,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(allowance(deps.as_ref(), "uxion", 0), (100, None));
+        assert_eq!(allowance(deps.as_ref(), "uatom", 0), (0, None));
+
+        // The epoch cap binds below the per-deposit maximum, and the cooldown is reported.
+        deposit(deps.as_mut(), 100, 0);
+        assert_eq!(allowance(deps.as_ref(), "uxion", 10), (50, Some(start.plus_seconds(30))));
+
+        // Less than the minimum left in the epoch means nothing can be deposited.
+        deposit(deps.as_mut(), 46, 30);
+        assert_eq!(allowance(deps.as_ref(), "uxion", 59), (0, Some(start.plus_seconds(60))));
+        assert_eq!(allowance(deps.as_ref(), "uxion", 60), (100, None));
+    }
+
+    #[test]
+    fn test_token_cooldown_is_per_user_and_token() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uatom".to_string(), "uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let err = execute_set_token_cooldown(
+            deps.as_mut(),
+            message_info(&alice, &[]),
+            "uxion".to_string(),
+            60,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute_set_token_cooldown(deps.as_mut(), owner, "uxion".to_string(), 60).unwrap();
+
+        let start = mock_env().block.time;
+        let deposit = |deps: DepsMut, user: &Addr, denom: &str, elapsed: u64| {
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(elapsed);
+            execute_deposit_token(
+                deps,
+                env,
+                message_info(user, &coins(1, denom)),
+                denom.to_string(),
+                Uint128::new(1),
+                None,
+            )
+        };
+        deposit(deps.as_mut(), &alice, "uxion", 0).unwrap();
+        let err = deposit(deps.as_mut(), &alice, "uxion", 45).unwrap_err();
+        assert_eq!(err, ContractError::CooldownActive { remaining: 15 });
+        // Other users and tokens without a cooldown are unaffected.
+        deposit(deps.as_mut(), &bob, "uxion", 45).unwrap();
+        deposit(deps.as_mut(), &alice, "uatom", 45).unwrap();
+        deposit(deps.as_mut(), &alice, "uatom", 45).unwrap();
+        deposit(deps.as_mut(), &alice, "uxion", 60).unwrap();
+    }
+
+    #[test]
+    fn test_whitelist_manager_can_manage_whitelist_only() {
+        let mut deps = mock_dependencies();
+        let owner = deps.api.addr_make("owner");
+        let manager = deps.api.addr_make("manager");
+        let token = deps.api.addr_make("token");
+        let config = mock_config(&deps.api, vec![token.to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        // The manager role cannot be assigned by anyone but the owner.
+        let err = execute_set_whitelist_manager(
+            deps.as_mut(),
+            message_info(&manager, &[]),
+            manager.to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute_set_whitelist_manager(deps.as_mut(), message_info(&owner, &[]), manager.to_string())
+            .unwrap();
+
+        // The manager can edit the whitelist but not owner-level settings.
+        let err = execute_update_config(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&manager, &[]),
+            manager.to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute_remove_whitelisted_token(
+            deps.as_mut(),
+            message_info(&manager, &[]),
+            token.to_string(),
+        )
+        .unwrap();
+        assert!(CONFIG.load(deps.as_ref().storage).unwrap().whitelist.is_empty());
+    }
+
+    #[test]
+    fn test_set_token_limits_and_deposit_within_limits() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+
+        let err = execute_set_token_limits(
+            deps.as_mut(),
+            owner.clone(),
+            "uxion".to_string(),
+            Uint128::new(500),
+            Uint128::new(100),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidTokenLimits { .. }));
+
+        execute_set_token_limits(
+            deps.as_mut(),
+            owner,
+            "uxion".to_string(),
+            Uint128::new(100),
+            Uint128::new(500),
+        )
+        .unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let err = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(1000, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(1000),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::OutsideTokenLimits { .. }));
+
+        let res = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(250, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(250),
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_empty_whitelist_rejects_all_deposits() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let sender = deps.api.addr_make("sender");
+        let token = deps.api.addr_make("token");
+
+        let err = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(10, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(10),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NotWhitelisted { .. }));
+
+        let err = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &[]),
+            token.to_string(),
+            Uint128::new(10),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NotWhitelisted { .. }));
+
+        let err = execute_deposit_cw20_allowance(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &[]),
+            token.to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NotWhitelisted { .. }));
+        assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap().deposit_seq, 0);
+    }
+
+    #[test]
+    fn test_deposit_seq_increments_per_deposit() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        for expected in 1..=2u64 {
+            let res = execute_deposit_token(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &coins(10, "uxion")),
+                "uxion".to_string(),
+                Uint128::new(10),
+                None,
+            )
+            .unwrap();
+            let event = res.events.iter().find(|e| e.ty == "deposit_token").unwrap();
+            let seq = event.attributes.iter().find(|a| a.key == "deposit_seq").unwrap();
+            assert_eq!(seq.value, expected.to_string());
+        }
+        assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap().deposit_seq, 2);
+    }
+
+    #[test]
+    fn test_global_stats_count_deposits_volume_and_depositors() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uatom".to_string(), "uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let deposits = [(&alice, "uxion", 10), (&alice, "uatom", 5), (&bob, "uxion", 7)];
+        for (sender, denom, amount) in deposits {
+            execute_deposit_token(
+                deps.as_mut(),
+                mock_env(),
+                message_info(sender, &coins(amount, denom)),
+                denom.to_string(),
+                Uint128::new(amount),
+                None,
+            )
+            .unwrap();
+        }
+
+        let stats = query_global_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.total_deposits, 3);
+        assert_eq!(stats.unique_depositors, 2);
+        assert_eq!(
+            stats.volume_by_token,
+            vec![
+                TokenVolume { token_address: "uatom".to_string(), volume: Uint128::new(5) },
+                TokenVolume { token_address: "uxion".to_string(), volume: Uint128::new(17) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ibc_deposits_require_a_trusted_route() {
+        let mut deps = mock_dependencies();
+        // ATOM as received by Osmosis over channel-0.
+        let trusted = ibc_denom("channel-0", "uatom");
+        assert_eq!(
+            trusted,
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        );
+        let lookalike = ibc_denom("channel-1", "uatom");
+        let config = mock_config(&deps.api, vec![trusted.clone(), lookalike.clone()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let sender = deps.api.addr_make("sender");
+        let deposit = |deps: DepsMut, denom: &str| {
+            execute_deposit_token(
+                deps,
+                mock_env(),
+                message_info(&sender, &coins(10, denom)),
+                denom.to_string(),
+                Uint128::new(10),
+                None,
+            )
+        };
+
+        let err = deposit(deps.as_mut(), &trusted).unwrap_err();
+        assert_eq!(err, ContractError::UntrustedIbcDenom { denom: trusted.clone() });
+
+        let err = execute_register_ibc_path(
+            deps.as_mut(),
+            message_info(&sender, &[]),
+            "channel-0".to_string(),
+            "uatom".to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute_register_ibc_path(
+            deps.as_mut(),
+            owner.clone(),
+            "channel-0".to_string(),
+            "uatom".to_string(),
+        )
+        .unwrap();
+
+        deposit(deps.as_mut(), &trusted).unwrap();
+        let err = deposit(deps.as_mut(), &lookalike).unwrap_err();
+        assert_eq!(err, ContractError::UntrustedIbcDenom { denom: lookalike });
+
+        execute_remove_ibc_path(deps.as_mut(), owner, "channel-0".to_string(), "uatom".to_string())
+            .unwrap();
+        assert!(deposit(deps.as_mut(), &trusted).is_err());
+    }
+
+    #[test]
+    fn test_post_deposit_hook_is_notified_after_transfer() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let sender = deps.api.addr_make("sender");
+        let beneficiary = deps.api.addr_make("beneficiary");
+        let hook = deps.api.addr_make("hook");
+
+        let err = execute_set_post_deposit_hook(
+            deps.as_mut(),
+            message_info(&sender, &[]),
+            Some(hook.to_string()),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute_set_post_deposit_hook(deps.as_mut(), owner.clone(), Some(hook.to_string()))
+            .unwrap();
+
+        let deposit = |deps: DepsMut| {
+            execute_deposit_token(
+                deps,
+                mock_env(),
+                message_info(&sender, &coins(10, "uxion")),
+                "uxion".to_string(),
+                Uint128::new(10),
+                Some(beneficiary.to_string()),
+            )
+            .unwrap()
+        };
+        let res = deposit(deps.as_mut());
+        assert_eq!(res.messages.len(), 2);
+        let expected = PostDepositHookMsg::Deposit(DepositHookPayload {
+            user: beneficiary.clone(),
+            token: "uxion".to_string(),
+            amount: Uint128::new(10),
+        });
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: hook.to_string(),
+                msg: to_json_binary(&expected).unwrap(),
+                funds: vec![],
+            })
+        );
+
+        execute_set_post_deposit_hook(deps.as_mut(), owner, None).unwrap();
+        assert_eq!(deposit(deps.as_mut()).messages.len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_upgrades_original_config() {
+        let mut deps = mock_dependencies();
+        let owner = deps.api.addr_make("owner");
+        let admin_wallet = deps.api.addr_make("admin_wallet");
+        let token_b = deps.api.addr_make("token_b").to_string();
+        let token_a = deps.api.addr_make("token_a").to_string();
+        // The original contract stored whitelisted token contracts in insertion order.
+        let legacy = LegacyConfig {
+            owner: owner.clone(),
+            admin_wallet: admin_wallet.clone(),
+            whitelist: vec![token_b.clone(), token_a.clone(), token_b.clone()],
+        };
+        LEGACY_CONFIG.save(deps.as_mut().storage, &legacy).unwrap();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.0.1").unwrap();
+        assert!(CONFIG.load(&deps.storage).is_err());
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert!(res.events[0]
+            .attributes
+            .contains(&cosmwasm_std::Attribute::new("config_migrated", "true")));
+        let mut expected = Config::new(owner, admin_wallet);
+        let mut whitelist = vec![token_a, token_b];
+        whitelist.sort();
+        expected.whitelist = whitelist;
+        assert_eq!(CONFIG.load(&deps.storage).unwrap(), expected);
+        let version = cw2::get_contract_version(&deps.storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+
+        // Migrating again keeps the current config.
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert!(res.events[0]
+            .attributes
+            .contains(&cosmwasm_std::Attribute::new("config_migrated", "false")));
+        assert_eq!(CONFIG.load(&deps.storage).unwrap(), expected);
+
+        set_contract_version(deps.as_mut().storage, "crates.io:other", "1.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::WrongContract {
+                contract: "crates.io:other".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_feature_flags_only_accept_known_bits() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+
+        let err = execute_set_feature_flags(deps.as_mut(), owner.clone(), 1 << 63).unwrap_err();
+        assert_eq!(err, ContractError::UnknownFeatureFlags { flags: 1 << 63 });
+        let sender = deps.api.addr_make("sender");
+        let err = execute_set_feature_flags(deps.as_mut(), message_info(&sender, &[]), 0)
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute_set_feature_flags(deps.as_mut(), owner, KNOWN_FEATURE_FLAGS).unwrap();
+        assert!(CONFIG.load(&deps.storage).unwrap().feature_enabled(KNOWN_FEATURE_FLAGS));
+
+        // A config stored before the field existed loads with no features enabled.
+        let legacy = cosmwasm_std::to_json_string(&config)
+            .unwrap()
+            .replace(",\"feature_flags\":0", "");
+        assert!(!legacy.contains("feature_flags"));
+        let legacy: Config = cosmwasm_std::from_json(legacy).unwrap();
+        assert_eq!(legacy.feature_flags, 0);
+    }
+
+    #[test]
+    fn test_deposit_seq_overflow_is_an_error() {
+        let mut deps = mock_dependencies();
+        let mut config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        config.deposit_seq = u64::MAX;
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let err = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(10, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(10),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::DepositSeqOverflow {});
+    }
+
+    #[test]
+    fn test_owner_bypass_allows_owner_deposits_of_unlisted_tokens() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = config.owner.clone();
+        let deposit = |deps: DepsMut, sender: &Addr| {
+            execute_deposit_token(
+                deps,
+                mock_env(),
+                message_info(sender, &coins(10, "uatom")),
+                "uatom".to_string(),
+                Uint128::new(10),
+                None,
+            )
+        };
+
+        let err = deposit(deps.as_mut(), &owner).unwrap_err();
+        assert!(matches!(err, ContractError::NotWhitelisted { .. }));
+
+        let sender = deps.api.addr_make("sender");
+        let err = execute_set_owner_bypass(deps.as_mut(), message_info(&sender, &[]), true)
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute_set_owner_bypass(deps.as_mut(), message_info(&owner, &[]), true).unwrap();
+
+        let res = deposit(deps.as_mut(), &owner).unwrap();
+        let event = res.events.iter().find(|e| e.ty == "deposit_token").unwrap();
+        let bypass = event.attributes.iter().find(|a| a.key == "owner_bypass").unwrap();
+        assert_eq!(bypass.value, "true");
+
+        let err = deposit(deps.as_mut(), &sender).unwrap_err();
+        assert!(matches!(err, ContractError::NotWhitelisted { .. }));
+    }
+
+    #[test]
+    fn test_admin_wallet_change_waits_for_delay() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let new_admin = deps.api.addr_make("new_admin");
+
+        // Without a delay the change applies at once.
+        execute_update_config(deps.as_mut(), mock_env(), owner.clone(), new_admin.to_string())
+            .unwrap();
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().admin_wallet, new_admin);
+
+        execute_set_admin_change_delay(deps.as_mut(), owner.clone(), 100).unwrap();
+        let err = execute_set_admin_change_delay(deps.as_mut(), owner.clone(), 99).unwrap_err();
+        assert_eq!(err, ContractError::AdminChangeDelayDecrease {});
+
+        let other_admin = deps.api.addr_make("other_admin");
+        execute_update_config(deps.as_mut(), mock_env(), owner.clone(), other_admin.to_string())
+            .unwrap();
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().admin_wallet, new_admin);
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(99);
+        let err = execute_apply_admin_wallet(deps.as_mut(), env.clone(), owner.clone())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::AdminChangeTimelocked { .. }));
+
+        env.block.time = env.block.time.plus_seconds(1);
+        execute_apply_admin_wallet(deps.as_mut(), env.clone(), owner.clone()).unwrap();
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(config.admin_wallet, other_admin);
+        assert_eq!(config.pending_admin_wallet, None);
+        let err = execute_apply_admin_wallet(deps.as_mut(), env, owner).unwrap_err();
+        assert_eq!(err, ContractError::NoPendingAdminWallet {});
+    }
+
+    #[test]
+    fn test_native_tax_is_reported_as_net_amount() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token").to_string();
+        let config = mock_config(&deps.api, vec!["uluna".to_string(), token.clone()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let err = execute_set_native_tax_bps(deps.as_mut(), owner.clone(), MAX_BPS + 1)
+            .unwrap_err();
+        assert_eq!(err, ContractError::InvalidBps { bps: MAX_BPS + 1 });
+        execute_set_native_tax_bps(deps.as_mut(), owner, 50).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let net_amount = |res: Response| {
+            res.events[0]
+                .attributes
+                .iter()
+                .find(|attr| attr.key == "net_amount")
+                .unwrap()
+                .value
+                .clone()
+        };
+        let res = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(1_000, "uluna")),
+            "uluna".to_string(),
+            Uint128::new(1_000),
+            None,
+        )
+        .unwrap();
+        assert_eq!(net_amount(res), "995");
+
+        // CW20 transfers are not taxed.
+        let res = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &[]),
+            token,
+            Uint128::new(1_000),
+            None,
+        )
+        .unwrap();
+        assert_eq!(net_amount(res), "1000");
+    }
+
+    #[test]
+    fn test_rotate_roles_updates_only_given_roles() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let new_manager = deps.api.addr_make("new_manager");
+
+        let err = execute_rotate_roles(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&new_manager, &[]),
+            None,
+            Some(new_manager.to_string()),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let res = execute_rotate_roles(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            None,
+            Some(new_manager.to_string()),
+        )
+        .unwrap();
+        assert_eq!(res.events.len(), 1);
+        let stored = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(stored.whitelist_manager, new_manager);
+        assert_eq!(stored.admin_wallet, config.admin_wallet);
+
+        // With a delay the manager rotates at once but the admin wallet stays pending.
+        execute_set_admin_change_delay(deps.as_mut(), owner.clone(), 60).unwrap();
+        let new_admin = deps.api.addr_make("new_admin");
+        execute_rotate_roles(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            Some(new_admin.to_string()),
+            Some(config.owner.to_string()),
+        )
+        .unwrap();
+        let stored = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(stored.whitelist_manager, config.owner);
+        assert_eq!(stored.admin_wallet, config.admin_wallet);
+        assert_eq!(stored.pending_admin_wallet.unwrap().wallet, new_admin);
+    }
+
+    #[test]
+    fn test_commit_reveal_deposit() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let sender = deps.api.addr_make("sender");
+        let nonce = HexBinary::from(&[7u8; 32]);
+        let commit = |deps: DepsMut, env: Env| {
+            let commitment = commitment_hash(Uint128::new(10), &nonce, &sender);
+            execute_commit_deposit(deps, env, message_info(&sender, &[]), commitment).unwrap();
+        };
+        let reveal = |deps: DepsMut, env: Env, amount: u128, nonce: &HexBinary| {
+            execute_reveal_deposit(
+                deps,
+                env,
+                message_info(&sender, &coins(amount, "uxion")),
+                "uxion".to_string(),
+                Uint128::new(amount),
+                nonce.clone(),
+            )
+        };
+
+        commit(deps.as_mut(), mock_env());
+        let err = reveal(deps.as_mut(), mock_env(), 11, &nonce).unwrap_err();
+        assert_eq!(err, ContractError::CommitmentMismatch {});
+        let err = reveal(deps.as_mut(), mock_env(), 10, &HexBinary::from(&[8u8; 32]))
+            .unwrap_err();
+        assert_eq!(err, ContractError::CommitmentMismatch {});
+
+        let res = reveal(deps.as_mut(), mock_env(), 10, &nonce).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        // The commitment is single-use.
+        let err = reveal(deps.as_mut(), mock_env(), 10, &nonce).unwrap_err();
+        assert_eq!(err, ContractError::NoCommitment {});
+
+        commit(deps.as_mut(), mock_env());
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(COMMITMENT_TTL_SECONDS + 1);
+        let err = reveal(deps.as_mut(), later, 10, &nonce).unwrap_err();
+        assert_eq!(err, ContractError::CommitmentExpired {});
+    }
+
+    #[test]
+    fn test_deposit_credits_beneficiary() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let sponsored = deps.api.addr_make("player");
+        let res = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(10, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(10),
+            Some(sponsored.to_string()),
+        )
+        .unwrap();
+        let event = res.events.iter().find(|e| e.ty == "deposit_token").unwrap();
+        let attr = |key: &str| {
+            event.attributes.iter().find(|a| a.key == key).unwrap().value.clone()
+        };
+        assert_eq!(attr("user"), sender.to_string());
+        assert_eq!(attr("beneficiary"), sponsored.to_string());
+
+        let err = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(10, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(10),
+            Some("not an address".to_string()),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::GenericErr { .. })));
+    }
+
+    #[test]
+    fn test_canonical_id_unifies_native_and_cw20_deposits() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("wrapped_xion");
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&TokenInfoResponse {
+                    name: "Wrapped XION".to_string(),
+                    symbol: "WXION".to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::zero(),
+                })
+                .unwrap(),
+            )),
+            _ => panic!("unexpected query"),
         });
-        
-        // Create response with the bank send message and event
-        Ok(Response::new()
-            .add_message(bank_msg)
-            .add_event(Event::new("deposit_token")
-                .add_attribute("user", info.sender.to_string())
-                .add_attribute("amount", amount.to_string())
-                .add_attribute("token_address", token_address)
-                .add_attribute("token_type", "native")
-                .add_attribute("timestamp", env.block.time.seconds().to_string())))
-    } else {
-        // Handle CW20 tokens
-        let token_addr = deps.api.addr_validate(&token_address)?;
-        
-        // Check if the token is whitelisted.
-        if !config.whitelist.contains(&token_addr) {
-            return Err(StdError::generic_err("Token not whitelisted"));
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+
+        let err = execute_add_whitelisted_token(
+            deps.as_mut(),
+            owner.clone(),
+            "uxion".to_string(),
+            Some(String::new()),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::EmptyCanonicalId {});
+
+        for token_address in ["uxion".to_string(), token.to_string()] {
+            execute_add_whitelisted_token(
+                deps.as_mut(),
+                owner.clone(),
+                token_address,
+                Some("xion".to_string()),
+            )
+            .unwrap();
         }
 
-        // Construct the CW20 TransferFrom message.
-        let transfer_from_msg = cw20_base::msg::ExecuteMsg::TransferFrom {
-            owner: info.sender.to_string(),
-            recipient: config.admin_wallet.to_string(),
-            amount,
+        let sender = deps.api.addr_make("sender");
+        let canonical_id = |res: &Response| {
+            let event = res.events.iter().find(|e| e.ty == "deposit_token").unwrap();
+            event.attributes.iter().find(|a| a.key == "canonical_id").unwrap().value.clone()
         };
-        let exec_transfer = WasmMsg::Execute {
-            contract_addr: token_addr.to_string(),
-            msg: to_json_binary(&transfer_from_msg)?,
-            funds: vec![],
+        let native = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(10, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(10),
+            None,
+        )
+        .unwrap();
+        let cw20 = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &[]),
+            token.to_string(),
+            Uint128::new(10),
+            None,
+        )
+        .unwrap();
+        assert_eq!(canonical_id(&native), "xion");
+        assert_eq!(canonical_id(&cw20), "xion");
+
+        // Without a mapping the token is its own logical asset.
+        TOKEN_CONFIGS.remove(deps.as_mut().storage, "uxion");
+        let native = execute_deposit_token(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &coins(10, "uxion")),
+            "uxion".to_string(),
+            Uint128::new(10),
+            None,
+        )
+        .unwrap();
+        assert_eq!(canonical_id(&native), "uxion");
+    }
+
+    #[test]
+    fn test_deposit_status_query() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        execute_set_token_limits(
+            deps.as_mut(),
+            message_info(&config.owner, &[]),
+            "uxion".to_string(),
+            Uint128::new(100),
+            Uint128::new(500),
+        )
+        .unwrap();
+        let status = |deps: Deps, token: &str| -> DepositStatusResponse {
+            let msg = QueryMsg::DepositStatus {
+                token_address: token.to_string(),
+            };
+            cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
         };
-        
-        Ok(Response::new()
-            .add_message(CosmosMsg::Wasm(exec_transfer))
-            .add_event(Event::new("deposit_token")
-                .add_attribute("user", info.sender.to_string())
-                .add_attribute("amount", amount.to_string())
-                .add_attribute("token_address", token_addr.to_string())
-                .add_attribute("token_type", "cw20")
-                .add_attribute("timestamp", env.block.time.seconds().to_string())))
+
+        assert_eq!(
+            status(deps.as_ref(), "uxion"),
+            DepositStatusResponse {
+                allowed: true,
+                reason: None,
+                min_amount: Uint128::new(100),
+                max_amount: Uint128::new(500),
+                cap_remaining: None,
+            }
+        );
+        let res = status(deps.as_ref(), "uatom");
+        assert!(!res.allowed);
+        assert!(res.reason.unwrap().contains("Token not whitelisted"));
     }
-}
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetConfig {} => to_json_binary(&CONFIG.load(deps.storage)?),
+    #[test]
+    fn test_preflight_deposit_emits_rejection_reason() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let sender = message_info(&deps.api.addr_make("sender"), &[]);
+        let preflight = |deps: DepsMut, info: MessageInfo, token: &str, amount: u128| {
+            execute_preflight_deposit(deps, mock_env(), info, token.to_string(), amount.into())
+        };
+
+        let res = preflight(deps.as_mut(), sender.clone(), "uxion", 100).unwrap();
+        assert!(res.events.is_empty());
+
+        let attr = |res: &Response, key: &str| {
+            let event = res.events.iter().find(|e| e.ty == "deposit_rejected").unwrap();
+            event.attributes.iter().find(|a| a.key == key).unwrap().value.clone()
+        };
+        let res = preflight(deps.as_mut(), sender.clone(), "uatom", 100).unwrap();
+        assert_eq!(attr(&res, "user"), sender.sender.to_string());
+        assert_eq!(attr(&res, "token_address"), "uatom");
+        assert_eq!(attr(&res, "amount"), "100");
+        assert_eq!(attr(&res, "reason_code"), "not_whitelisted");
+        assert_eq!(
+            attr(&res, "reason"),
+            ContractError::NotWhitelisted { token: "uatom".to_string() }.to_string()
+        );
+
+        let res = preflight(deps.as_mut(), sender.clone(), "uxion", 0).unwrap();
+        assert_eq!(attr(&res, "reason_code"), "zero_amount");
+
+        // Attached funds would be stranded, so they are refused outright.
+        let paying = message_info(&sender.sender, &coins(100, "uxion"));
+        let err = preflight(deps.as_mut(), paying, "uxion", 100).unwrap_err();
+        assert_eq!(err, ContractError::FundsNotAllowed {});
+        assert_eq!(err.reason_code(), "funds_not_allowed");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary, Addr, BankMsg, SubMsg};
+    #[test]
+    fn test_simulate_deposit_reports_without_mutating_state() {
+        let mut deps = mock_dependencies();
+        let config = mock_config(&deps.api, vec!["uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        execute_set_token_limits(
+            deps.as_mut(),
+            message_info(&config.owner, &[]),
+            "uxion".to_string(),
+            Uint128::new(100),
+            Uint128::new(500),
+        )
+        .unwrap();
+
+        let user = deps.api.addr_make("sender").to_string();
+        let simulate = |deps: Deps, token: &str, amount: u128| -> SimulateDepositResponse {
+            let msg = QueryMsg::SimulateDeposit {
+                user: user.clone(),
+                token_address: token.to_string(),
+                amount: Uint128::new(amount),
+            };
+            cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+
+        let res = simulate(deps.as_ref(), "uxion", 250);
+        assert_eq!(
+            res,
+            SimulateDepositResponse {
+                ok: true,
+                reason: None,
+                reason_code: None,
+                net_amount: Uint128::new(250),
+                fee_amount: Uint128::zero(),
+            }
+        );
+
+        let res = simulate(deps.as_ref(), "uxion", 1000);
+        assert!(!res.ok);
+        assert!(res.reason.unwrap().contains("outside token limits"));
+        assert_eq!(res.reason_code.as_deref(), Some("outside_token_limits"));
+        let res = simulate(deps.as_ref(), "uatom", 250);
+        assert!(res.reason.unwrap().contains("Token not whitelisted"));
+
+        assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap(), config);
+    }
 
     #[test]
-    fn test_native_token_detection() {
-        println!("testing native token detection with uxion");
+    fn test_whitelist_pagination_is_stable_across_inserts() {
         let mut deps = mock_dependencies();
-        let env = mock_env();
-        
-        // Set up contract config
-        let config = Config {
-            owner: Addr::unchecked("owner"),
-            admin_wallet: Addr::unchecked("admin_wallet"),
-            whitelist: vec![],
+        let config = mock_config(&deps.api, vec![]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        for denom in ["uosmo", "uatom", "uxion"] {
+            execute_add_whitelisted_token(deps.as_mut(), owner.clone(), denom.to_string(), None)
+                .unwrap();
+        }
+
+        let page = |deps: Deps, start_after: Option<&str>| {
+            query_whitelist(deps, start_after.map(str::to_string), Some(2)).unwrap().tokens
         };
+        assert_eq!(page(deps.as_ref(), None), ["uatom", "uosmo"]);
+
+        // A token inserted before the cursor must not shift the next page.
+        execute_add_whitelisted_token(deps.as_mut(), owner, "ubtc".to_string(), None).unwrap();
+        assert_eq!(page(deps.as_ref(), Some("uosmo")), ["uxion"]);
+        assert_eq!(page(deps.as_ref(), None), ["uatom", "ubtc"]);
+        assert_eq!(page(deps.as_ref(), Some("ubtc")), ["uosmo", "uxion"]);
+    }
+
+    #[test]
+    fn test_whitelist_detailed_reports_settings() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        let untrusted = ibc_denom("channel-9", "uatom");
+        let mut config =
+            mock_config(&deps.api, vec![token.to_string(), "uxion".to_string(), untrusted.clone()]);
+        config.whitelist.sort();
+        config.native_tax_bps = 50;
         CONFIG.save(deps.as_mut().storage, &config).unwrap();
-        
-        // Test "uxion" as token_address
-        let amount = Uint128::new(1000);
-        let info = mock_info("sender", &coins(1000, "uxion"));
-        
-        let result = execute_deposit_token(
+        let owner = message_info(&config.owner, &[]);
+        execute_set_token_limits(
             deps.as_mut(),
-            env.clone(),
-            info,
+            owner,
             "uxion".to_string(),
-            amount,
-        ).unwrap();
-        
-        // Verify it was treated as a native token by checking for a bank message
-        assert_eq!(result.messages.len(), 1);
-        match &result.messages[0].msg {
-            CosmosMsg::Bank(BankMsg::Send { to_address, amount: send_amount }) => {
-                assert_eq!(to_address, "admin_wallet");
-                assert_eq!(send_amount.len(), 1);
-                assert_eq!(send_amount[0].denom, "uxion");
-                assert_eq!(send_amount[0].amount, amount);
-            },
-            _ => panic!("Expected Bank message, got something else"),
+            Uint128::new(5),
+            Uint128::new(100),
+        )
+        .unwrap();
+
+        let entries =
+            query_whitelist_detailed(deps.as_ref(), mock_env(), None, None).unwrap().tokens;
+        assert_eq!(
+            entries.iter().map(|entry| entry.token.as_str()).collect::<Vec<_>>(),
+            config.whitelist
+        );
+        let entry = |token: &str| entries.iter().find(|entry| entry.token == token).unwrap();
+        assert_eq!(
+            *entry("uxion"),
+            WhitelistDetailedEntry {
+                token: "uxion".to_string(),
+                kind: "native".to_string(),
+                enabled: true,
+                min_amount: Uint128::new(5),
+                max_amount: Uint128::new(100),
+                cap: None,
+                cap_used: None,
+                tax_bps: 50,
+            }
+        );
+        assert_eq!(entry(token.as_str()).kind, "cw20");
+        assert_eq!(entry(token.as_str()).tax_bps, 0);
+        assert!(!entry(&untrusted).enabled);
+
+        let start_after = Some(config.whitelist[0].clone());
+        let page = query_whitelist_detailed(deps.as_ref(), mock_env(), start_after, Some(1))
+            .unwrap()
+            .tokens;
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].token, config.whitelist[1]);
+    }
+
+    #[test]
+    fn test_remove_whitelisted_tokens_in_one_call() {
+        let mut deps = mock_dependencies();
+        let whitelist = ["uatom", "uosmo", "uxion"].map(str::to_string).to_vec();
+        let config = mock_config(&deps.api, whitelist);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let remove = |deps: DepsMut, tokens: &[&str]| {
+            execute_remove_whitelisted_tokens(
+                deps,
+                owner.clone(),
+                tokens.iter().map(|token| token.to_string()).collect(),
+            )
+        };
+
+        let err = remove(deps.as_mut(), &["uxion", "ubtc"]).unwrap_err();
+        assert_eq!(err, ContractError::NotWhitelisted { token: "ubtc".to_string() });
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().whitelist.len(), 3);
+
+        let res = remove(deps.as_mut(), &["uxion", "uatom", "uxion"]).unwrap();
+        let event = res.events.iter().find(|e| e.ty == "remove_whitelisted_tokens").unwrap();
+        let removed = event.attributes.iter().find(|a| a.key == "token_addresses").unwrap();
+        assert_eq!(removed.value, "uatom,uxion");
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().whitelist, ["uosmo"]);
+    }
+
+    #[test]
+    fn test_instantiate_seeds_whitelist() {
+        let mut deps = mock_dependencies();
+        let owner = deps.api.addr_make("owner");
+        let admin_wallet = deps.api.addr_make("admin_wallet").to_string();
+        let msg = |initial_whitelist: Vec<String>| InstantiateMsg {
+            admin_wallet: admin_wallet.clone(),
+            initial_whitelist: Some(initial_whitelist),
+        };
+
+        let too_many = (0..=MAX_INITIAL_WHITELIST).map(|i| format!("udenom{i}")).collect();
+        let err = instantiate(deps.as_mut(), mock_env(), message_info(&owner, &[]), msg(too_many))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::InitialWhitelistTooLarge { .. }));
+
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&owner, &[]),
+            msg(vec!["uxion".to_string(), "not an address".to_string()]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::GenericErr { .. })));
+
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&owner, &[]),
+            msg(vec!["uxion".to_string(), "uatom".to_string(), "uxion".to_string()]),
+        )
+        .unwrap();
+        assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap().whitelist, ["uatom", "uxion"]);
+        let event = res.events.iter().find(|e| e.ty == "seed_whitelist").unwrap();
+        let attr = |key: &str| {
+            event.attributes.iter().find(|a| a.key == key).unwrap().value.clone()
+        };
+        assert_eq!(attr("count"), "2");
+        assert_eq!(attr("tokens"), "uatom,uxion");
+    }
+
+    #[test]
+    fn test_version_query_reports_cw2_version_and_features() {
+        let mut deps = mock_dependencies();
+        let owner = deps.api.addr_make("owner");
+        let msg = InstantiateMsg {
+            admin_wallet: deps.api.addr_make("admin_wallet").to_string(),
+            initial_whitelist: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), message_info(&owner, &[]), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Version {}).unwrap();
+        let version: VersionResponse = cosmwasm_std::from_json(res).unwrap();
+        assert_eq!(
+            version,
+            VersionResponse {
+                contract: CONTRACT_NAME.to_string(),
+                version: CONTRACT_VERSION.to_string(),
+                known_feature_flags: KNOWN_FEATURE_FLAGS,
+                feature_flags: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_relayed_deposit_checks_signature_and_nonce() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        let config = mock_config(&deps.api, vec![token.to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        // An ADR-036 signature by the key below over the sign doc asserted further down,
+        // as a wallet's `signArbitrary` produces it for `mock_env()`, with low S.
+        let pubkey = Binary::from(
+            HexBinary::from_hex(
+                "03db68498974283704703e8f526ee68dd0d5478bd5539c7182e25a116f9b60676a",
+            )
+            .unwrap(),
+        );
+        let user = Addr::unchecked("cosmwasm1tzzn38nr74lvyxrlddrwvq0ea6vgw2n3cjvztk");
+        assert_eq!(secp256k1_pubkey_address(&deps.api, &pubkey).unwrap(), user);
+        let relayer = message_info(&deps.api.addr_make("relayer"), &[]);
+        let env = mock_env();
+        let intent = DepositIntent {
+            user: user.to_string(),
+            token_address: token.to_string(),
+            amount: Uint128::new(1000),
+            nonce: 0,
+            deadline: env.block.time.plus_seconds(60),
+        };
+        let sign_bytes =
+            deposit_intent_sign_bytes(&env.block.chain_id, &env.contract.address, &intent)
+                .unwrap();
+        assert_eq!(
+            adr036_sign_doc(user.as_str(), &sign_bytes),
+            "{\"account_number\":\"0\",\"chain_id\":\"\",\"fee\":{\"amount\":[],\"gas\":\"0\"\
+             },\"memo\":\"\",\"msgs\":[{\"type\":\"sign/MsgSignData\",\"value\":{\"data\":\"e\
+             yJkb21haW4iOiJyb2JldDpkZXBvc2l0X2ludGVudDp2MSIsImNoYWluX2lkIjoiY29zbW9zLXRlc3RuZ\
+             XQtMTQwMDIiLCJjb250cmFjdCI6ImNvc213YXNtMWpwZXYyY3NycHBnNzkydDIycm44ejh1ZXc4aDNza\
+             mNwZ2xjZDBxdjlnOGdqOGt5OTIydHNjcDhhdnMiLCJpbnRlbnQiOnsidXNlciI6ImNvc213YXNtMXR6e\
+             m4zOG5yNzRsdnl4cmxkZHJ3dnEwZWE2dmd3Mm4zY2p2enRrIiwidG9rZW5fYWRkcmVzcyI6ImNvc213Y\
+             XNtMTgzcmZhOHR2dHA2YXg3anI3ZGZhZjd5d3Y4NzBzeWt4ZG1ocmc0Nnh0ZnF0cGtqcDIwc3FocWc3c\
+             mUiLCJhbW91bnQiOiIxMDAwIiwibm9uY2UiOjAsImRlYWRsaW5lIjoiMTU3MTc5NzQ3OTg3OTMwNTUzM\
+             yJ9fQ==\",\"signer\":\"cosmwasm1tzzn38nr74lvyxrlddrwvq0ea6vgw2n3cjvztk\"}}],\"se\
+             quence\":\"0\"}"
+        );
+        let signature = Binary::from(
+            HexBinary::from_hex(
+                "20d234fe6f7dd5de228abc6294c54b669b429b3c86b3e9f4490a071ec3ac226c\
+                 7b30dc2c4f9b1bcce1c44fcb8dbabaf8a1b6a71d9a7afc1ee6897422db2cede9",
+            )
+            .unwrap(),
+        );
+        let relay = |deps: DepsMut, intent: &DepositIntent, pubkey: &Binary| {
+            execute_relayed_deposit(
+                deps,
+                mock_env(),
+                relayer.clone(),
+                intent.clone(),
+                pubkey.clone(),
+                signature.clone(),
+            )
+        };
+
+        let native = DepositIntent {
+            token_address: "uxion".to_string(),
+            ..intent.clone()
+        };
+        let err = relay(deps.as_mut(), &native, &pubkey).unwrap_err();
+        assert_eq!(err, ContractError::RelayedNativeDeposit {});
+        // The key must belong to the intent's user.
+        let other = DepositIntent {
+            user: deps.api.addr_make("user").to_string(),
+            ..intent.clone()
+        };
+        let err = relay(deps.as_mut(), &other, &pubkey).unwrap_err();
+        assert_eq!(err, ContractError::IntentSignerMismatch {});
+
+        // The relayer cannot change what the user signed.
+        let tampered = DepositIntent {
+            amount: Uint128::new(2000),
+            ..intent.clone()
+        };
+        let err = relay(deps.as_mut(), &tampered, &pubkey).unwrap_err();
+        assert_eq!(err, ContractError::InvalidIntentSignature {});
+
+        let res = relay(deps.as_mut(), &intent, &pubkey).unwrap();
+        let attr = |ty: &str, key: &str| {
+            let event = res.events.iter().find(|e| e.ty == ty).unwrap();
+            event.attributes.iter().find(|a| a.key == key).unwrap().value.clone()
+        };
+        assert_eq!(attr("deposit_token", "user"), user.as_str());
+        assert_eq!(attr("relayed_deposit", "relayer"), relayer.sender.as_str());
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+                let transfer: cw20_base::msg::ExecuteMsg = cosmwasm_std::from_json(msg).unwrap();
+                assert!(matches!(
+                    transfer,
+                    cw20_base::msg::ExecuteMsg::TransferFrom { owner, .. } if owner == user.as_str()
+                ));
+            }
+            msg => panic!("unexpected message {msg:?}"),
         }
-        
-        // Check event attributes that indicate it was treated as native
-        let deposit_event = result.events.iter().find(|e| e.ty == "deposit_token").unwrap();
-        let token_type = deposit_event.attributes.iter()
-            .find(|attr| attr.key == "token_type")
+        let state = query_relay_state(deps.as_ref(), user.to_string()).unwrap();
+        assert_eq!(state.next_nonce, 1);
+
+        let err = relay(deps.as_mut(), &intent, &pubkey).unwrap_err();
+        assert_eq!(err, ContractError::InvalidNonce { expected: 1, got: 0 });
+        let mut late = mock_env();
+        late.block.time = intent.deadline.plus_seconds(1);
+        let err = execute_relayed_deposit(deps.as_mut(), late, relayer, intent, pubkey, signature)
+            .unwrap_err();
+        assert_eq!(err, ContractError::IntentExpired {});
+    }
+
+    #[test]
+    fn test_balance_delta_deposit_checks_admin_balance_growth() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("rebasing");
+        let admin_balance = std::rc::Rc::new(std::cell::Cell::new(100u128));
+        let balance = admin_balance.clone();
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { msg, .. } => match cosmwasm_std::from_json(msg).unwrap() {
+                Cw20QueryMsg::Balance { .. } => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&BalanceResponse {
+                        balance: Uint128::new(balance.get()),
+                    })
+                    .unwrap(),
+                )),
+                _ => panic!("unexpected query"),
+            },
+            _ => panic!("unexpected query"),
+        });
+        let config = mock_config(&deps.api, vec![token.to_string(), "uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        let err = execute_set_balance_delta_mode(deps.as_mut(), owner.clone(), "uxion".into(), true)
+            .unwrap_err();
+        assert_eq!(err, ContractError::NotCw20 {});
+        execute_set_balance_delta_mode(deps.as_mut(), owner.clone(), token.to_string(), true)
             .unwrap();
-        assert_eq!(token_type.value, "native");
+        execute_set_token_limits(
+            deps.as_mut(),
+            owner.clone(),
+            token.to_string(),
+            Uint128::new(10),
+            Uint128::MAX,
+        )
+        .unwrap();
+        execute_set_epoch_cap(
+            deps.as_mut(),
+            owner.clone(),
+            token.to_string(),
+            3_600,
+            Uint128::new(1_000),
+        )
+        .unwrap();
+        let hook = deps.api.addr_make("hook");
+        execute_set_post_deposit_hook(deps.as_mut(), owner, Some(hook.to_string())).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let deposit = |deps: DepsMut| {
+            execute_deposit_token(
+                deps,
+                mock_env(),
+                message_info(&sender, &[]),
+                token.to_string(),
+                Uint128::new(50),
+                None,
+            )
+            .unwrap()
+        };
+        #[allow(deprecated)]
+        let transfer_done = Reply {
+            id: BALANCE_DELTA_REPLY_ID,
+            payload: Binary::default(),
+            gas_used: 0,
+            result: cosmwasm_std::SubMsgResult::Ok(cosmwasm_std::SubMsgResponse {
+                events: vec![],
+                data: None,
+                msg_responses: vec![],
+            }),
+        };
+
+        let res = deposit(deps.as_mut());
+        assert_eq!(res.messages[0].reply_on, cosmwasm_std::ReplyOn::Success);
+        // Nothing is counted and the hook waits until the transfer's effect is known.
+        assert_eq!(res.messages.len(), 1);
+        assert!(!VOLUME_BY_TOKEN.has(&deps.storage, token.as_str()));
+        assert!(!EPOCH_USAGE.has(&deps.storage, token.as_str()));
+        // The token rebased during the transfer, so less than requested arrived.
+        admin_balance.set(145);
+        let res = reply(deps.as_mut(), mock_env(), transfer_done.clone()).unwrap();
+        let event = res.events.iter().find(|e| e.ty == "balance_delta").unwrap();
+        let received = event.attributes.iter().find(|a| a.key == "received").unwrap();
+        assert_eq!(received.value, "45");
+        assert!(!PENDING_BALANCE_CHECK.exists(&deps.storage));
+        assert_eq!(
+            VOLUME_BY_TOKEN.load(&deps.storage, token.as_str()).unwrap(),
+            Uint128::new(45)
+        );
+        let usage = EPOCH_USAGE.load(&deps.storage, token.as_str()).unwrap();
+        assert_eq!(usage.epoch_used, Uint128::new(45));
+        let expected = PostDepositHookMsg::Deposit(DepositHookPayload {
+            user: sender.clone(),
+            token: token.to_string(),
+            amount: Uint128::new(45),
+        });
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: hook.to_string(),
+                msg: to_json_binary(&expected).unwrap(),
+                funds: vec![],
+            })
+        );
+
+        deposit(deps.as_mut());
+        admin_balance.set(150);
+        let err = reply(deps.as_mut(), mock_env(), transfer_done).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientBalanceDelta {
+                received: Uint128::new(5),
+                min: Uint128::new(10),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deposit_cw20_allowance_sweeps_up_to_balance() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => {
+                let response = match cosmwasm_std::from_json(msg).unwrap() {
+                    Cw20QueryMsg::Allowance { .. } => to_json_binary(&AllowanceResponse {
+                        allowance: Uint128::new(500),
+                        expires: cw20::Expiration::Never {},
+                    }),
+                    Cw20QueryMsg::Balance { .. } => to_json_binary(&BalanceResponse {
+                        balance: Uint128::new(300),
+                    }),
+                    _ => panic!("unexpected query"),
+                };
+                SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+        let config = mock_config(&deps.api, vec![token.to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let sender = deps.api.addr_make("sender");
+        let res = execute_deposit_cw20_allowance(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &[]),
+            token.to_string(),
+        )
+        .unwrap();
+        let event = res.events.iter().find(|e| e.ty == "deposit_token").unwrap();
+        let amount = event.attributes.iter().find(|a| a.key == "amount").unwrap();
+        assert_eq!(amount.value, "300");
+
+        let err = execute_deposit_cw20_allowance(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&sender, &[]),
+            "uxion".to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NotCw20 {});
+    }
+
+    #[test]
+    fn test_whole_token_minimum_scales_with_decimals() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&TokenInfoResponse {
+                    name: "Token".to_string(),
+                    symbol: "TKN".to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::zero(),
+                })
+                .unwrap(),
+            )),
+            _ => panic!("unexpected query"),
+        });
+        let config = mock_config(&deps.api, vec![token.to_string(), "uxion".to_string()]);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let owner = message_info(&config.owner, &[]);
+        execute_set_token_limits(
+            deps.as_mut(),
+            owner.clone(),
+            token.to_string(),
+            Uint128::new(2),
+            Uint128::MAX,
+        )
+        .unwrap();
+        let err = execute_set_min_in_whole_tokens(
+            deps.as_mut(),
+            owner.clone(),
+            "uxion".to_string(),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NotCw20 {});
+        execute_set_min_in_whole_tokens(deps.as_mut(), owner, token.to_string(), true).unwrap();
+
+        let status = query_deposit_status(deps.as_ref(), mock_env(), token.to_string()).unwrap();
+        assert_eq!(status.min_amount, Uint128::new(2_000_000));
+        let sender = deps.api.addr_make("sender");
+        let deposit = |deps: DepsMut, amount: u128| {
+            execute_deposit_token(
+                deps,
+                mock_env(),
+                message_info(&sender, &[]),
+                token.to_string(),
+                Uint128::new(amount),
+                None,
+            )
+        };
+        let err = deposit(deps.as_mut(), 1_999_999).unwrap_err();
+        assert!(matches!(err, ContractError::OutsideTokenLimits { .. }));
+        deposit(deps.as_mut(), 2_000_000).unwrap();
+    }
+
+    #[test]
+    fn test_whitelist_entry_query() {
+        let mut deps = mock_dependencies();
+        let token = deps.api.addr_make("token");
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&TokenInfoResponse {
+                    name: "Token".to_string(),
+                    symbol: "TKN".to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::zero(),
+                })
+                .unwrap(),
+            )),
+            _ => panic!("unexpected query"),
+        });
+        let mut whitelist = vec!["uxion".to_string(), token.to_string()];
+        whitelist.sort();
+        let config = mock_config(&deps.api, whitelist);
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        execute_set_token_limits(
+            deps.as_mut(),
+            message_info(&config.owner, &[]),
+            "uxion".to_string(),
+            Uint128::new(100),
+            Uint128::new(500),
+        )
+        .unwrap();
+
+        let entry = query_whitelist_entry(deps.as_ref(), "uxion".to_string()).unwrap();
+        assert_eq!(entry.token_config.min_amount, Uint128::new(100));
+        assert_eq!(entry.token_config.max_amount, Uint128::new(500));
+        assert_eq!(entry.decimals, None);
+
+        let entry = query_whitelist_entry(deps.as_ref(), token.to_string()).unwrap();
+        assert_eq!(entry.token_config, TokenConfig::default());
+        assert_eq!(entry.decimals, Some(6));
+
+        let err = query_whitelist_entry(deps.as_ref(), "uatom".to_string()).unwrap_err();
+        assert!(err.to_string().contains("Token not whitelisted"));
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use cosmwasm_std::{BankMsg, Coin, WasmMsg};
+    use proptest::prelude::*;
+
+    const WHITELISTED_DENOM: &str = "uxion";
+
+    fn denom() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just(WHITELISTED_DENOM.to_string()),
+            Just("uatom".to_string()),
+            Just("ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2".to_string()),
+            "u[a-z]{1,8}",
+        ]
+    }
+
+    fn funds() -> impl Strategy<Value = Vec<Coin>> {
+        prop::collection::vec((denom(), 0u128..10_000), 0..3).prop_map(|coins| {
+            coins
+                .into_iter()
+                .map(|(denom, amount)| Coin::new(amount, denom))
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn native_deposit_forwards_exactly_the_attached_funds(
+            token in denom(),
+            amount in 0u128..10_000,
+            funds in funds(),
+        ) {
+            let mut deps = mock_dependencies();
+            let config = Config {
+                owner: deps.api.addr_make("owner"),
+                admin_wallet: deps.api.addr_make("admin_wallet"),
+                whitelist: vec![WHITELISTED_DENOM.to_string()],
+                min_decimals: 0,
+                max_decimals: u8::MAX,
+                whitelist_manager: deps.api.addr_make("owner"),
+                deposit_seq: 0,
+                owner_bypass_enabled: false,
+                admin_change_delay: 0,
+                pending_admin_wallet: None,
+                native_tax_bps: 0,
+                post_deposit_hook: None,
+                feature_flags: 0,
+            };
+            CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+            let sender = deps.api.addr_make("sender");
+            let result = execute_deposit_token(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &funds),
+                token.clone(),
+                Uint128::new(amount),
+                None,
+            );
+
+            let Ok(res) = result else { return Ok(()) };
+            prop_assert_eq!(token.as_str(), WHITELISTED_DENOM);
+            prop_assert_eq!(&funds, &vec![Coin::new(amount, WHITELISTED_DENOM)]);
+            match &res.messages[0].msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount: sent }) => {
+                    prop_assert_eq!(to_address, config.admin_wallet.as_str());
+                    prop_assert_eq!(sent, &funds);
+                }
+                _ => prop_assert!(false, "expected a bank send"),
+            }
+        }
+
+        #[test]
+        fn cw20_deposit_never_accepts_native_funds(
+            whitelisted in any::<bool>(),
+            amount in 0u128..10_000,
+            funds in funds(),
+        ) {
+            let mut deps = mock_dependencies();
+            let token = deps.api.addr_make("token").to_string();
+            let config = Config {
+                owner: deps.api.addr_make("owner"),
+                admin_wallet: deps.api.addr_make("admin_wallet"),
+                whitelist: if whitelisted { vec![token.clone()] } else { vec![] },
+                min_decimals: 0,
+                max_decimals: u8::MAX,
+                whitelist_manager: deps.api.addr_make("owner"),
+                deposit_seq: 0,
+                owner_bypass_enabled: false,
+                admin_change_delay: 0,
+                pending_admin_wallet: None,
+                native_tax_bps: 0,
+                post_deposit_hook: None,
+                feature_flags: 0,
+            };
+            CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+            let sender = deps.api.addr_make("sender");
+            let result = execute_deposit_token(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &funds),
+                token.clone(),
+                Uint128::new(amount),
+                None,
+            );
+
+            prop_assert_eq!(result.is_ok(), whitelisted && amount > 0 && funds.is_empty());
+            if let Ok(res) = result {
+                match &res.messages[0].msg {
+                    CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, funds, .. }) => {
+                        prop_assert_eq!(contract_addr, &token);
+                        prop_assert!(funds.is_empty());
+                    }
+                    _ => prop_assert!(false, "expected a CW20 transfer"),
+                }
+            }
+        }
     }
 }
@@ -1,13 +1,141 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Timestamp, Uint128};
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
     #[error("Unauthorized")]
     Unauthorized {},
-    // Add any other custom errors you like here.
-    // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+
+    #[error("Token not whitelisted: {token}")]
+    NotWhitelisted { token: String },
+
+    #[error("Deposit amount must be greater than zero")]
+    ZeroAmount {},
+
+    #[error("Sent amount ({sent}) doesn't match specified amount ({expected}) for denom {denom}")]
+    AmountMismatch {
+        denom: String,
+        sent: Uint128,
+        expected: Uint128,
+    },
+
+    #[error("Only {denom} may be sent with a native deposit")]
+    UnexpectedFunds { denom: String },
+
+    #[error("Native funds must not be sent with a CW20 deposit")]
+    FundsWithCw20Deposit {},
+
+    #[error("Admin wallet balance grew by {received}, at least {min} is required")]
+    InsufficientBalanceDelta { received: Uint128, min: Uint128 },
+
+    #[error("Public key does not belong to the deposit intent's user")]
+    IntentSignerMismatch {},
+
+    #[error("Deposit intent is not signed by its user")]
+    InvalidIntentSignature {},
+
+    #[error("A relayer cannot move the user's native coins; only CW20 deposits can be relayed")]
+    RelayedNativeDeposit {},
+
+    #[error("Deposit intent deadline has passed")]
+    IntentExpired {},
+
+    #[error("Expected relay nonce {expected}, got {got}")]
+    InvalidNonce { expected: u64, got: u64 },
+
+    #[error("Deposit cooldown active, {remaining} seconds remaining")]
+    CooldownActive { remaining: u64 },
+
+    #[error("Deposit exceeds the token's epoch cap, {remaining} can still be deposited")]
+    EpochCapExceeded { remaining: Uint128 },
+
+    #[error("Deposit attaches {count} coins, at most {max} are allowed")]
+    TooManyFunds { count: usize, max: u32 },
+
+    #[error("This operation is only supported for CW20 tokens")]
+    NotCw20 {},
+
+    #[error("Amount ({amount}) outside token limits [{min}, {max}]")]
+    OutsideTokenLimits {
+        amount: Uint128,
+        min: Uint128,
+        max: Uint128,
+    },
+
+    #[error("Minimum ({min}) exceeds maximum ({max})")]
+    InvalidTokenLimits { min: Uint128, max: Uint128 },
+
+    #[error("min_decimals ({min}) exceeds max_decimals ({max})")]
+    InvalidDecimalsBounds { min: u8, max: u8 },
+
+    #[error("Token decimals ({decimals}) outside allowed range [{min}, {max}]")]
+    DecimalsOutOfRange { decimals: u8, min: u8, max: u8 },
+
+    #[error("Canonical id must not be empty")]
+    EmptyCanonicalId {},
+
+    #[error("Initial whitelist has {count} tokens, at most {max} are allowed")]
+    InitialWhitelistTooLarge { count: usize, max: usize },
+
+    #[error("Deposit sequence overflow")]
+    DepositSeqOverflow {},
+
+    #[error("Commitment must be a 32-byte sha256 hash")]
+    InvalidCommitment {},
+
+    #[error("No deposit commitment")]
+    NoCommitment {},
+
+    #[error("Deposit commitment expired")]
+    CommitmentExpired {},
+
+    #[error("Revealed amount and nonce do not match the commitment")]
+    CommitmentMismatch {},
+
+    #[error("No pending admin wallet")]
+    NoPendingAdminWallet {},
+
+    #[error("The pending admin wallet cannot be applied before {effective}")]
+    AdminChangeTimelocked { effective: Timestamp },
+
+    #[error("The admin change delay can only be raised")]
+    AdminChangeDelayDecrease {},
+
+    #[error("Basis points ({bps}) exceed 10000")]
+    InvalidBps { bps: u16 },
+
+    #[error("IBC path needs a channel-<n> channel and a base denom")]
+    InvalidIbcPath {},
+
+    #[error("IBC denom {denom} did not arrive over a trusted channel")]
+    UntrustedIbcDenom { denom: String },
+
+    #[error("Feature flags {flags} contain bits not assigned to any feature")]
+    UnknownFeatureFlags { flags: u64 },
+
+    #[error("This message does not accept funds")]
+    FundsNotAllowed {},
+
+    #[error("Cannot migrate from contract {contract}")]
+    WrongContract { contract: String },
+}
+
+impl ContractError {
+    /// Stable, machine-readable code of a deposit rejection, reported in `deposit_rejected`
+    /// events. Errors that never reject a deposit on their own map to "other".
+    pub fn reason_code(&self) -> &'static str {
+        match self {
+            ContractError::NotWhitelisted { .. } => "not_whitelisted",
+            ContractError::UntrustedIbcDenom { .. } => "untrusted_ibc_denom",
+            ContractError::ZeroAmount {} => "zero_amount",
+            ContractError::OutsideTokenLimits { .. } => "outside_token_limits",
+            ContractError::CooldownActive { .. } => "cooldown_active",
+            ContractError::EpochCapExceeded { .. } => "epoch_cap_exceeded",
+            ContractError::FundsNotAllowed {} => "funds_not_allowed",
+            _ => "other",
+        }
+    }
 }
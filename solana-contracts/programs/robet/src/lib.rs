@@ -1,80 +1,1393 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{
+    get_stack_height, AccountMeta, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT,
+};
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_spl::associated_token::{self, AssociatedToken};
+use anchor_spl::memo::{self, BuildMemo, Memo};
+use anchor_spl::token_2022::TransferChecked;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        interest_bearing_mint::InterestBearingConfig, non_transferable::NonTransferable,
+        permanent_delegate::PermanentDelegate, transfer_hook, BaseStateWithExtensions,
+        StateWithExtensions,
+    },
+};
 use anchor_spl::token_interface::{
-    TokenAccount, Mint, TokenInterface, transfer_checked
+    TokenAccount, Mint, TokenInterface, transfer_checked, mint_to, MintTo, burn, Burn
 };
 
 declare_id!("Bm6LM1dhfnVDCSah6h8tMayYA5yRKT29KUMuMRScQ5ee");
 
+/// Program version stamped into every emitted event, encoded from the crate version
+/// as `major * 10_000 + minor * 100 + patch`. Bump on any event layout change.
+pub const PROGRAM_VERSION: u16 = 112;
+
+/// Event schema version stamped into every emitted event right after `version`.
+/// Bump whenever the fields of any event change so indexers can branch on it.
+///
+/// Schemas are not prefix-compatible. `DepositEvent` in particular no longer starts with
+/// the original `config_owner, user, amount, token_mint, timestamp` layout: `version` and
+/// `schema` come first, fields were inserted before `timestamp` (now an `i64`), and the
+/// variable-length `memo` sits mid-struct. Decoders must select the layout by `schema`.
+pub const EVENT_SCHEMA_VERSION: u8 = 10;
+
+/// Seed prefix of receipt mints, derived as `[RECEIPT_SEED, config, underlying_mint]`.
+/// Each receipt mint is its own mint authority, so only this program can mint receipts.
+pub const RECEIPT_SEED: &[u8] = b"receipt";
+
+/// Seed prefix of deposit commitments, derived as `[COMMITMENT_SEED, config, user]`.
+pub const COMMITMENT_SEED: &[u8] = b"commitment";
+
+/// How long a deposit commitment can be revealed after it is made.
+pub const COMMITMENT_TTL_SECS: i64 = 10 * 60;
+
+/// Seed prefix of deposit receipts, derived as `[DEPOSIT_RECEIPT_SEED, config, deposit_seq]`
+/// with the deposit's sequence number in little-endian bytes.
+pub const DEPOSIT_RECEIPT_SEED: &[u8] = b"deposit_receipt";
+
+/// Instruction discriminator of the deposit callback: the first 8 bytes of
+/// `sha256("global:robet_deposit_callback")`, which is what Anchor generates for an
+/// instruction named `robet_deposit_callback`. The discriminator is followed by a
+/// Borsh-serialized `DepositCallback`.
+pub const DEPOSIT_CALLBACK_DISCRIMINATOR: [u8; 8] = [19, 249, 26, 213, 247, 134, 151, 59];
+
+/// Feature flag set by `init_global_stats`: deposits must then pass the config's
+/// `GlobalStats` account and add to it.
+pub const FEATURE_GLOBAL_STATS: u64 = 1 << 0;
+
+/// Feature flag that makes deposits made through CPI fail with `CpiDepositsDisabled`.
+pub const FEATURE_REJECT_CPI_DEPOSITS: u64 = 1 << 1;
+
+/// Feature flag set by `init_audit_log`: privileged instructions must then pass the
+/// config's `AuditLog` account and append to it. Once set, it cannot be cleared.
+pub const FEATURE_AUDIT_LOG: u64 = 1 << 2;
+
+/// Bits of `Config::feature_flags` that are assigned to a feature. New optional behavior
+/// gets the next free bit and is gated on `Config::feature_enabled`, so it can be turned
+/// on without growing the config.
+pub const KNOWN_FEATURE_FLAGS: u64 =
+    FEATURE_GLOBAL_STATS | FEATURE_REJECT_CPI_DEPOSITS | FEATURE_AUDIT_LOG;
+
+/// Most mints each list of `update_whitelist_batch` may hold, so the instruction and the
+/// mint accounts it needs fit in one transaction.
+pub const MAX_WHITELIST_BATCH: usize = 8;
+
+/// Seed of the config's relay authority, derived as `[RELAY_AUTHORITY_SEED, config]`.
+/// Users approve it as delegate to allow relayed deposits from a token account.
+pub const RELAY_AUTHORITY_SEED: &[u8] = b"relay_authority";
+
+/// Seed prefix of per-user relay nonces, derived as `[RELAY_NONCE_SEED, config, user]`.
+pub const RELAY_NONCE_SEED: &[u8] = b"relay_nonce";
+
+/// Prefix of every signed `DepositIntent` message, so the signature cannot be replayed
+/// as a signature over anything else.
+pub const DEPOSIT_INTENT_DOMAIN: &[u8] = b"robet:deposit_intent:v1";
+
+/// Seed prefix of per-user deposit cooldowns, derived as
+/// `[DEPOSIT_COOLDOWN_SEED, config, user, token_mint]`.
+pub const DEPOSIT_COOLDOWN_SEED: &[u8] = b"deposit_cooldown";
+
+/// Seed prefix of per-user statistics, derived as `[USER_STATS_SEED, config, user]`.
+pub const USER_STATS_SEED: &[u8] = b"user_stats";
+
+/// Seed prefix of the per-config statistics account, derived as `[GLOBAL_STATS_SEED, config]`.
+pub const GLOBAL_STATS_SEED: &[u8] = b"global_stats";
+
+/// Seed of the per-config audit log, derived as `[AUDIT_LOG_SEED, config]`.
+pub const AUDIT_LOG_SEED: &[u8] = b"audit_log";
+
+/// Most audit entries `get_audit_log` returns per call, so they fit in the return data.
+pub const AUDIT_LOG_PAGE: usize = 12;
+
+/// Longest memo, in bytes, accepted by `deposit_token`.
+pub const MAX_MEMO_LEN: usize = 64;
+
 #[program]
 pub mod robet {
-    use anchor_spl::token_2022::TransferChecked;
-
     use super::*;
 
-    /// Initializes the config account with the admin wallet and an empty whitelist.
-    pub fn initialize_config(ctx: Context<InitializeConfig>, admin_wallet: Pubkey) -> Result<()> {
+    /// Initializes the config account with the admin wallet and a whitelist seeded from
+    /// `initial_whitelist`, which may be empty. Duplicates are dropped and at most
+    /// `Config::MAX_WHITELIST` distinct mints fit. The decimals bounds start fully open,
+    /// so seeded mints are not checked against them.
+    /// `admin_wallet` may be off-curve, e.g. a multisig vault PDA.
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        admin_wallet: Pubkey,
+        initial_whitelist: Vec<Pubkey>,
+    ) -> Result<()> {
         let config = &mut ctx.accounts.config;
         config.owner = ctx.accounts.owner.key();
         config.admin_wallet = admin_wallet;
-        config.whitelist = Vec::new();
+        config.whitelist_manager = ctx.accounts.owner.key();
+        config.min_decimals = 0;
+        config.max_decimals = u8::MAX;
+        config.deposit_seq = 0;
+        config.owner_bypass_enabled = false;
+        config.admin_change_delay = 0;
+        config.pending_admin_wallet = None;
+        config.reject_permanent_delegate = false;
+        config.whitelist_frozen = false;
+        config.immutable = false;
+        config.callback_program = None;
+        config.feature_flags = 0;
+        config.replace_whitelist(&initial_whitelist)?;
+
+        emit!(WhitelistReplacedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            mints: config.whitelist.iter().map(|entry| entry.mint).collect(),
+        });
         Ok(())
     }
 
-    /// Adds a token mint to the whitelist. Only callable by the owner.
-    pub fn add_whitelisted_token(ctx: Context<ManageWhitelist>, token_mint: Pubkey) -> Result<()> {
+    /// Adds a token mint to the whitelist. Callable by the owner or the whitelist manager.
+    /// The mint's decimals must fall within the configured bounds. Fails once the whitelist
+    /// is frozen.
+    pub fn add_whitelisted_token(ctx: Context<AddWhitelistedToken>) -> Result<()> {
+        let token_mint = ctx.accounts.token_mint.key();
         let config = &mut ctx.accounts.config;
-        if !config.whitelist.contains(&token_mint) {
-            config.whitelist.push(token_mint);
-        }
-        Ok(())
+        check_whitelistable_mint(config, &ctx.accounts.token_mint)?;
+        config.add_to_whitelist(token_mint)?;
+        ctx.accounts.audit(AuditAction::AddWhitelistedToken, &token_mint)
     }
 
-    /// Removes a token mint from the whitelist. Only callable by the owner.
+    /// Removes a token mint from the whitelist. Callable by the owner or the whitelist manager.
+    ///
+    /// Removal preserves order: entries after the removed one shift down by one position.
+    /// Swap-remove is not an option because lookups rely on the whitelist staying sorted.
+    /// The emitted event carries the removed index so position-based clients can follow.
+    /// Removing a mint that is not whitelisted is a no-op and emits nothing.
+    /// Fails once the whitelist is frozen.
     pub fn remove_whitelisted_token(ctx: Context<ManageWhitelist>, token_mint: Pubkey) -> Result<()> {
         let config = &mut ctx.accounts.config;
-        config.whitelist.retain(|&x| x != token_mint);
+        if let Some(index) = config.remove_from_whitelist(&token_mint)? {
+            emit!(WhitelistTokenRemovedEvent {
+                version: PROGRAM_VERSION,
+                schema: EVENT_SCHEMA_VERSION,
+                config: config.key(),
+                token_mint,
+                index: index as u8,
+            });
+        }
+        ctx.accounts.audit(AuditAction::RemoveWhitelistedToken, &token_mint)
+    }
+
+    /// Removes every mint in `token_mints` from the whitelist in one step, keeping the
+    /// remaining entries sorted. Mints that are not whitelisted are ignored. Emits one
+    /// event listing the removed mints. Callable by the owner or the whitelist manager.
+    /// Fails once the whitelist is frozen.
+    pub fn remove_whitelisted_tokens_batch(
+        ctx: Context<ManageWhitelist>,
+        token_mints: Vec<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let removed = config.remove_many_from_whitelist(&token_mints)?;
+        emit!(WhitelistTokensRemovedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            count: removed.len() as u8,
+            token_mints: removed,
+        });
+        ctx.accounts.audit(AuditAction::RemoveWhitelistedTokensBatch, &token_mints)
+    }
+
+    /// Adds the mints in `add` and removes those in `remove` in one atomic step, e.g. for
+    /// launch days. Duplicates are ignored, as are additions that are already whitelisted
+    /// and removals that are not. A mint in both lists is rejected. Each list holds at most
+    /// `MAX_WHITELIST_BATCH` mints, and the resulting whitelist must fit `MAX_WHITELIST`.
+    ///
+    /// Every mint in `add` must be passed as a remaining account and is checked like in
+    /// `add_whitelisted_token`. Emits one event with the mints actually added and removed.
+    /// Callable by the owner or the whitelist manager. Fails once the whitelist is frozen.
+    pub fn update_whitelist_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ManageWhitelist<'info>>,
+        add: Vec<Pubkey>,
+        remove: Vec<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        for mint in &add {
+            let mint_info = ctx
+                .remaining_accounts
+                .iter()
+                .find(|account| account.key == mint)
+                .ok_or(CustomError::MintAccountMissing)?;
+            check_whitelistable_mint(config, &InterfaceAccount::<Mint>::try_from(mint_info)?)?;
+        }
+        let (added, removed) = config.update_whitelist_batch(&add, &remove)?;
+        emit!(WhitelistBatchUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            added,
+            removed,
+        });
+        ctx.accounts.audit(AuditAction::UpdateWhitelistBatch, &(add, remove))
+    }
+
+    /// Permanently freezes the whitelist, so no token can be added or removed afterwards.
+    /// Deposits and limit changes are unaffected. There is deliberately no way to unfreeze.
+    /// Only callable by the owner.
+    pub fn freeze_whitelist(ctx: Context<UpdateConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.whitelist_frozen = true;
+        emit!(WhitelistFrozenEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            mints: config.whitelist.iter().map(|entry| entry.mint).collect(),
+        });
+        ctx.accounts.audit(AuditAction::FreezeWhitelist, &())
+    }
+
+    /// Irreversibly gives up control of the config: afterwards every instruction gated on
+    /// the owner or whitelist manager fails with `ConfigImmutable`, while deposits keep
+    /// working. `confirm` must be true, guarding against accidental calls.
+    /// Only callable by the owner.
+    pub fn renounce_ownership(ctx: Context<UpdateConfig>, confirm: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.renounce_ownership(confirm)?;
+        emit!(OwnershipRenouncedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            owner: config.owner,
+            admin_wallet: config.admin_wallet,
+        });
+        ctx.accounts.audit(AuditAction::RenounceOwnership, &confirm)
+    }
+
+    /// Sorts and deduplicates the whitelist in place. Configs created before the whitelist
+    /// was kept sorted must run this once, since lookups rely on binary search.
+    /// Callable by the owner or the whitelist manager. Configs still in the original
+    /// layout cannot be loaded and need `migrate_config` instead.
+    pub fn sort_whitelist(ctx: Context<ManageWhitelist>) -> Result<()> {
+        ctx.accounts.config.sort_whitelist();
+        ctx.accounts.audit(AuditAction::SortWhitelist, &())
+    }
+
+    /// Upgrades a config in the original layout (owner, admin wallet and a list of mints)
+    /// to the current one. The account grows to `Config::LEN`, with the owner paying the
+    /// extra rent. The mints are sorted and deduplicated and get no deposit limits; every
+    /// other setting starts as in `initialize_config`. Callable by the stored owner only.
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config_info = ctx.accounts.config.to_account_info();
+        let config = LegacyConfig::from_account_data(&config_info.try_borrow_data()?)?.migrate()?;
+        require_keys_eq!(config.owner, ctx.accounts.owner.key(), CustomError::Unauthorized);
+
+        let space = 8 + Config::LEN;
+        let shortfall = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(config_info.lamports());
+        if shortfall > 0 {
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.owner.to_account_info(),
+                to: config_info.clone(),
+            };
+            let system_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new(system_program, cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_ctx, shortfall)?;
+        }
+        config_info.realloc(space, true)?;
+        {
+            let mut data = config_info.try_borrow_mut_data()?;
+            data.fill(0);
+            config.try_serialize(&mut &mut data[..])?;
+        }
+
+        emit!(WhitelistReplacedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config_info.key(),
+            mints: config.whitelist.iter().map(|entry| entry.mint).collect(),
+        });
         Ok(())
     }
 
+    /// Sets the per-deposit floor and ceiling for a whitelisted mint in one step,
+    /// so the pair can never be observed in an inconsistent state.
+    /// Callable by the owner or the whitelist manager.
+    pub fn set_mint_limits(
+        ctx: Context<ManageWhitelist>,
+        token_mint: Pubkey,
+        min_amount: u64,
+        max_amount: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.set_mint_limits(&token_mint, min_amount, max_amount)?;
+
+        emit!(MintLimitsUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            token_mint,
+            min_amount,
+            max_amount,
+        });
+        ctx.accounts.audit(AuditAction::SetMintLimits, &(token_mint, min_amount, max_amount))
+    }
+
+    /// Sets whether deposits of a whitelisted mint are verified by the admin token account's
+    /// balance growth, which must reach the mint's minimum and never be zero, instead of
+    /// the requested amount. Meant for rebasing mints; the observed growth is reported as
+    /// `received` in `DepositEvent`. Callable by the owner or the whitelist manager.
+    pub fn set_balance_delta_mode(
+        ctx: Context<ManageWhitelist>,
+        token_mint: Pubkey,
+        enabled: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.set_balance_delta_mode(&token_mint, enabled)?;
+
+        emit!(BalanceDeltaModeUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            token_mint,
+            enabled,
+        });
+        ctx.accounts.audit(AuditAction::SetBalanceDeltaMode, &(token_mint, enabled))
+    }
+
+    /// Sets how many seconds each user must wait between two deposits of a whitelisted
+    /// mint; 0 disables the cooldown. Callable by the owner or the whitelist manager.
+    pub fn set_deposit_cooldown(
+        ctx: Context<ManageWhitelist>,
+        token_mint: Pubkey,
+        cooldown_secs: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.set_deposit_cooldown(&token_mint, cooldown_secs)?;
+
+        emit!(DepositCooldownUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            token_mint,
+            cooldown_secs,
+        });
+        ctx.accounts.audit(AuditAction::SetDepositCooldown, &(token_mint, cooldown_secs))
+    }
+
+    /// Sets the loyalty points a whitelisted mint earns per raw unit deposited, times 10^6;
+    /// 0 disables points for the mint. Only callable by the owner.
+    pub fn set_points_rate(
+        ctx: Context<UpdateConfig>,
+        token_mint: Pubkey,
+        points_per_token_e6: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.set_points_rate(&token_mint, points_per_token_e6)?;
+
+        emit!(PointsRateUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            token_mint,
+            points_per_token_e6,
+        });
+        ctx.accounts.audit(AuditAction::SetPointsRate, &(token_mint, points_per_token_e6))
+    }
+
+    /// Clears a user's loyalty points once they were redeemed off-chain. The event reports
+    /// the points cleared. Only callable by the owner.
+    pub fn reset_points(ctx: Context<ResetPoints>, user: Pubkey) -> Result<()> {
+        let points = std::mem::take(&mut ctx.accounts.user_stats.points);
+        emit!(PointsResetEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: ctx.accounts.config.key(),
+            user,
+            points,
+        });
+        ctx.accounts.audit(AuditAction::ResetPoints, &user)
+    }
+
+    /// Caps how much of a whitelisted mint may be deposited per `epoch_len_secs`, across
+    /// all users; 0 seconds disables the cap. An epoch starts with the first deposit after
+    /// the previous one ended. Callable by the owner or the whitelist manager.
+    pub fn set_epoch_cap(
+        ctx: Context<ManageWhitelist>,
+        token_mint: Pubkey,
+        epoch_len_secs: u32,
+        epoch_cap: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.set_epoch_cap(&token_mint, epoch_len_secs, epoch_cap)?;
+
+        emit!(EpochCapUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            token_mint,
+            epoch_len_secs,
+            epoch_cap,
+        });
+        ctx.accounts.audit(AuditAction::SetEpochCap, &(token_mint, epoch_len_secs, epoch_cap))
+    }
+
     /// Deposits tokens from a user into the admin wallet’s associated token account.
-    /// Only tokens that are whitelisted in the config can be deposited.
-    pub fn deposit_token(ctx: Context<DepositToken>, amount: u64) -> Result<()> {
-        // Ensure that the token mint is whitelisted.
+    /// Only tokens that are whitelisted in the config can be deposited, and the amount
+    /// must fall within the mint's deposit limits.
+    ///
+    /// For Token-2022 mints with the transfer-hook extension, the hook's extra accounts
+    /// must be passed as remaining accounts so they can be forwarded to the hook program.
+    ///
+    /// If `beneficiary` is provided, the deposit is credited to it in the event while the
+    /// tokens still come from the signing user.
+    ///
+    /// If the mint has receipts enabled, `amount` receipt tokens are minted to the user's
+    /// `user_receipt_account`, and both receipt accounts must be passed.
+    ///
+    /// A non-empty `memo` of at most `MAX_MEMO_LEN` bytes is written with the SPL Memo
+    /// program before the transfer, for reconciliation in explorers, and repeated in
+    /// `DepositEvent`. `memo_program` must then be passed.
+    ///
+    /// Passing `deposit_receipt` (with `system_program`) opts into a `DepositReceipt`
+    /// account recording the deposit on-chain, paid for by the user and reclaimable with
+    /// `close_receipt`. Deposits without it pay no extra rent.
+    ///
+    /// With a `callback_program` configured, that program is invoked after the transfer
+    /// with `DEPOSIT_CALLBACK_DISCRIMINATOR` and a `DepositCallback`. The callback program
+    /// must follow any transfer-hook accounts in the remaining accounts, and every account
+    /// after it is forwarded to the callback. A failing callback aborts the deposit.
+    ///
+    /// Deposits made through CPI must pass the instructions sysvar, and the program of the
+    /// top-level transaction instruction is reported as `via_program` in `DepositEvent`.
+    /// The runtime does not expose the immediate caller, so with nested CPIs this is the
+    /// outermost program. With `FEATURE_REJECT_CPI_DEPOSITS` set they are refused.
+    ///
+    /// Mints with a deposit cooldown require `deposit_cooldown` and `system_program`. The
+    /// cooldown account is created on the user's first deposit of the mint, at their expense.
+    pub fn deposit_token<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositToken<'info>>,
+        amount: u64,
+        beneficiary: Option<Pubkey>,
+        memo: String,
+    ) -> Result<()> {
+        let accounts = ctx.accounts.deposit_accounts();
+        execute_deposit(accounts, ctx.remaining_accounts, amount, beneficiary, memo, &[])
+    }
+
+    /// Closes one of the user's deposit receipts and returns its rent to them.
+    pub fn close_receipt(_ctx: Context<CloseReceipt>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Commits to a future deposit by storing `commitment`, which must equal
+    /// `commitment_hash(amount, nonce, user)`, so the amount stays hidden until
+    /// `reveal_deposit`. A user has at most one open commitment per config, and it
+    /// expires `COMMITMENT_TTL_SECS` after creation.
+    pub fn commit_deposit(ctx: Context<CommitDeposit>, commitment: [u8; 32]) -> Result<()> {
+        let clock = Clock::get()?;
+        let record = &mut ctx.accounts.commitment;
+        record.user = ctx.accounts.user.key();
+        record.commitment = commitment;
+        record.expires_at = clock
+            .unix_timestamp
+            .checked_add(COMMITMENT_TTL_SECS)
+            .ok_or(CustomError::CounterOverflow)?;
+        Ok(())
+    }
+
+    /// Reveals a committed deposit and performs it exactly like `deposit_token`.
+    /// Fails unless `amount` and `nonce` match the commitment and it has not expired.
+    /// The commitment is closed and its rent returned to the user.
+    pub fn reveal_deposit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RevealDeposit<'info>>,
+        amount: u64,
+        nonce: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.commitment.verify(amount, &nonce, clock.unix_timestamp)?;
+        let accounts = ctx.accounts.deposit.deposit_accounts();
+        execute_deposit(accounts, ctx.remaining_accounts, amount, None, String::new(), &[])?;
+        let user = ctx.accounts.deposit.user.to_account_info();
+        ctx.accounts.commitment.close(user)
+    }
+
+    /// Closes the caller's open commitment, e.g. once it has expired, and returns its rent.
+    pub fn cancel_commitment(_ctx: Context<CancelCommitment>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Performs a deposit the user signed off-chain and a relayer submits, so the user
+    /// needs no SOL. The relayer pays the fees and any rent.
+    ///
+    /// The instruction right before this one must be an Ed25519 program instruction
+    /// verifying the user's signature over `DepositIntent::message`. `nonce` must equal
+    /// the user's `RelayNonce`, which is then bumped, so each intent is used at most once,
+    /// and the intent expires after `deadline`. The tokens move through the config's
+    /// relay authority, which the user must have approved as delegate of their token
+    /// account beforehand.
+    ///
+    /// The deposit is checked and credited to the user exactly like `deposit_token`, with
+    /// the same optional accounts and remaining accounts; the relayer pays any rent. The
+    /// `memo` is part of the signed intent.
+    pub fn relayed_deposit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RelayedDeposit<'info>>,
+        amount: u64,
+        nonce: u64,
+        deadline: i64,
+        memo: String,
+    ) -> Result<()> {
+        let bump = ctx.bumps.relay_authority;
+        ctx.accounts.process(ctx.remaining_accounts, amount, nonce, deadline, memo, bump)
+    }
+    
+    /// Runs the `deposit_token` validation without moving any tokens and returns the
+    /// resulting split via return data. Meant to be run through `simulateTransaction`
+    /// as a preflight; it needs no signer and only reads the config and mint. Passing
+    /// `user` applies the owner bypass and, with `deposit_cooldown`, the user's cooldown.
+    pub fn simulate_deposit(
+        ctx: Context<SimulateDeposit>,
+        amount: u64,
+        beneficiary: Option<Pubkey>,
+    ) -> Result<SimulateDepositResult> {
         require!(
-            ctx.accounts.config.whitelist.contains(&ctx.accounts.token_mint.key()),
-            CustomError::TokenNotWhitelisted
+            beneficiary != Some(Pubkey::default()),
+            CustomError::InvalidBeneficiary
         );
+        let accounts = &ctx.accounts;
+        let token_mint = &accounts.token_mint;
+        let user = accounts.user.as_ref().map_or(Pubkey::default(), |user| user.key());
+        if let Some(deposit_cooldown) = &accounts.deposit_cooldown {
+            let (expected, _) =
+                deposit_cooldown_address(&accounts.config.key(), &user, &token_mint.key());
+            require_keys_eq!(deposit_cooldown.key(), expected, ErrorCode::ConstraintSeeds);
+        }
+        // The user's balance is unknown here; `validate_deposit` checks it.
+        deposit_checks(
+            &accounts.config,
+            &user,
+            &token_mint.key(),
+            token_mint.decimals,
+            amount,
+            u64::MAX,
+            accounts.deposit_cooldown.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        Ok(SimulateDepositResult {
+            net_amount: amount,
+            fee_amount: 0,
+        })
+    }
 
-        // Transfer tokens from the user's token account to the admin's derived associated token account.
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.user_token_account.to_account_info(),
-            to: ctx.accounts.admin_token_account.to_account_info(),
+    /// Runs every `deposit_token` check for `user` without transferring anything and
+    /// returns a `DepositValidation` via return data. Failed checks are reported in the
+    /// result rather than as an error, so wallets can show the precise reason.
+    /// Beyond `simulate_deposit`, this also checks the user's balance and that the admin
+    /// wallet's token account exists. Pass `deposit_cooldown` once the user has one.
+    pub fn validate_deposit(ctx: Context<ValidateDeposit>, amount: u64) -> Result<DepositValidation> {
+        let accounts = &ctx.accounts;
+        let result = deposit_checks(
+            &accounts.config,
+            &accounts.user.key(),
+            &accounts.token_mint.key(),
+            accounts.token_mint.decimals,
+            amount,
+            accounts.user_token_account.amount,
+            accounts.deposit_cooldown.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )
+        .and_then(|_| {
+            let expected = derive_admin_ata(
+                &accounts.config.admin_wallet,
+                &accounts.token_mint.key(),
+                &accounts.token_program.key(),
+            );
+            let admin_token_account = &accounts.admin_token_account;
+            require!(
+                admin_token_account.key() == expected
+                    && admin_token_account.owner == &accounts.token_program.key()
+                    && !admin_token_account.data_is_empty(),
+                CustomError::AdminTokenAccountMissing
+            );
+            Ok(())
+        });
+        Ok(DepositValidation::from_result(result, amount))
+    }
+
+    /// Creates the admin wallet's missing associated token accounts, paid for by the signer,
+    /// so deposits work again right after an admin wallet change.
+    ///
+    /// `remaining_accounts` holds (mint, ata) pairs of whitelisted mints owned by
+    /// `token_program`; whitelists mixing token programs need one call per program.
+    /// Each ATA must be derived from the current admin wallet. Accounts that already exist
+    /// are skipped, so the instruction is safe to repeat. Anyone may call it.
+    /// Each created account emits an `AtaCreatedEvent` recording who paid its rent.
+    pub fn prepare_admin_accounts<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PrepareAdminAccounts<'info>>,
+    ) -> Result<()> {
+        let accounts = &ctx.accounts;
+        require!(
+            ctx.remaining_accounts.len().is_multiple_of(2),
+            CustomError::InvalidAdminAccountPairs
+        );
+        let mut created: u8 = 0;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let (mint, ata) = (&pair[0], &pair[1]);
+            check_admin_account_pair(
+                &accounts.config,
+                mint.key,
+                mint.owner,
+                ata.key,
+                accounts.token_program.key,
+            )?;
+            if !ata.data_is_empty() {
+                continue;
+            }
+            associated_token::create_idempotent(CpiContext::new(
+                accounts.associated_token_program.to_account_info(),
+                associated_token::Create {
+                    payer: accounts.payer.to_account_info(),
+                    associated_token: ata.clone(),
+                    authority: accounts.admin_wallet.to_account_info(),
+                    mint: mint.clone(),
+                    system_program: accounts.system_program.to_account_info(),
+                    token_program: accounts.token_program.to_account_info(),
+                },
+            ))?;
+            emit!(AtaCreatedEvent {
+                version: PROGRAM_VERSION,
+                schema: EVENT_SCHEMA_VERSION,
+                config: accounts.config.key(),
+                payer: accounts.payer.key(),
+                token_mint: mint.key(),
+                admin_token_account: ata.key(),
+                rent_lamports: ata.lamports(),
+            });
+            created += 1;
+        }
+
+        emit!(AdminAccountsPreparedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: accounts.config.key(),
+            admin_wallet: accounts.config.admin_wallet,
+            created,
+            skipped: (ctx.remaining_accounts.len() / 2) as u8 - created,
+        });
+        Ok(())
+    }
+
+    /// Creates the receipt mint for a whitelisted mint and enables receipts on its
+    /// whitelist entry. The receipt mint mirrors the underlying mint's decimals.
+    /// Only callable by the owner.
+    pub fn create_receipt_mint(ctx: Context<CreateReceiptMint>) -> Result<()> {
+        let underlying = ctx.accounts.underlying_mint.key();
+        ctx.accounts.config.enable_receipts(&underlying)?;
+        ctx.accounts.audit(AuditAction::CreateReceiptMint, &underlying)
+    }
+
+    /// Burns `amount` of the caller's receipts for `underlying_mint`. This moves no
+    /// underlying tokens: deposits sit in the admin wallet, outside the program's control,
+    /// so settlement happens off-chain. The backend pays the user out of the admin wallet
+    /// on the emitted `ReceiptBurnedEvent`.
+    pub fn burn_receipt(ctx: Context<BurnReceipt>, amount: u64) -> Result<()> {
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.receipt_mint.to_account_info(),
+            from: ctx.accounts.user_receipt_account.to_account_info(),
             authority: ctx.accounts.user.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
         };
-        let cpi_ctx =
-            CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        transfer_checked(cpi_ctx, amount, ctx.accounts.token_mint.decimals)?;
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        burn(cpi_ctx, amount)?;
 
-        // Emit an event indicating a successful deposit.
         let clock = Clock::get()?;
-        emit!(DepositEvent {
-            config_owner: ctx.accounts.config.owner,
+        emit!(ReceiptBurnedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: ctx.accounts.config.key(),
             user: ctx.accounts.user.key(),
+            underlying_mint: ctx.accounts.underlying_mint.key(),
             amount,
-            token_mint: ctx.accounts.token_mint.key(),
             timestamp: clock.unix_timestamp as u64,
         });
         Ok(())
     }
-    
+
     /// Allows the owner to update his configuration.
-    /// In this example, the owner can update the admin wallet.
+    /// In this example, the owner can update the admin wallet, which may be off-curve.
+    ///
+    /// With a non-zero `admin_change_delay` the new wallet only becomes pending and is
+    /// installed by `apply_admin_wallet` once the delay has elapsed. Calling this again
+    /// replaces the pending wallet and restarts the delay.
     pub fn update_config(ctx: Context<UpdateConfig>, new_admin_wallet: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
         let config = &mut ctx.accounts.config;
-        config.admin_wallet = new_admin_wallet;
-        Ok(())
+        let effective_ts = config.schedule_admin_wallet(new_admin_wallet, clock.unix_timestamp);
+        emit!(AdminWalletChangePendingEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            new_admin_wallet,
+            effective_ts,
+        });
+        if config.admin_change_delay == 0 {
+            apply_pending_admin_wallet(config, clock.unix_timestamp)?;
+        }
+        ctx.accounts.audit(AuditAction::UpdateConfig, &new_admin_wallet)
+    }
+
+    /// Installs the pending admin wallet once its delay has elapsed. Only callable by the owner.
+    pub fn apply_admin_wallet(ctx: Context<UpdateConfig>) -> Result<()> {
+        let clock = Clock::get()?;
+        apply_pending_admin_wallet(&mut ctx.accounts.config, clock.unix_timestamp)?;
+        ctx.accounts.audit(AuditAction::ApplyAdminWallet, &())
     }
+
+    /// Sends the lamports a program-owned account holds above its rent-exempt minimum to
+    /// the signer, e.g. SOL transferred to it by mistake. `target` defaults to the config
+    /// itself. Token accounts and mints are owned by the token program and are refused,
+    /// so token balances are never touched. Callable by the owner or the admin wallet.
+    pub fn collect_excess_lamports(ctx: Context<CollectExcessLamports>) -> Result<()> {
+        let accounts = &ctx.accounts;
+        let authority = accounts.authority.key();
+        let role = accounts
+            .config
+            .privileged_role(&authority)
+            .ok_or(CustomError::Unauthorized)?;
+        let target = match &accounts.target {
+            Some(target) => target.to_account_info(),
+            None => accounts.config.to_account_info(),
+        };
+        let amount = transfer_excess_lamports(
+            &target,
+            &accounts.authority.to_account_info(),
+            &Rent::get()?,
+        )?;
+        emit!(ExcessLamportsCollectedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: accounts.config.key(),
+            target: target.key(),
+            amount,
+            authority,
+            role,
+        });
+        ctx.accounts
+            .audit(AuditAction::CollectExcessLamports, &(target.key(), amount))
+    }
+
+    /// Sets how many seconds a new admin wallet stays pending before it can be applied.
+    /// The delay can only be raised, so a compromised owner key cannot shorten it.
+    /// Only callable by the owner.
+    pub fn set_admin_change_delay(ctx: Context<UpdateConfig>, delay: i64) -> Result<()> {
+        ctx.accounts.config.set_admin_change_delay(delay)?;
+        ctx.accounts.audit(AuditAction::SetAdminChangeDelay, &delay)
+    }
+
+    /// Sets the inclusive range of mint decimals accepted when whitelisting tokens.
+    /// Tokens already on the whitelist are not re-checked.
+    pub fn set_decimals_bounds(
+        ctx: Context<UpdateConfig>,
+        min_decimals: u8,
+        max_decimals: u8,
+    ) -> Result<()> {
+        require!(min_decimals <= max_decimals, CustomError::InvalidDecimalsBounds);
+        let config = &mut ctx.accounts.config;
+        config.min_decimals = min_decimals;
+        config.max_decimals = max_decimals;
+        ctx.accounts.audit(AuditAction::SetDecimalsBounds, &(min_decimals, max_decimals))
+    }
+
+    /// Hands whitelist management to a separate manager. Only callable by the owner.
+    /// Passing the owner's own key collapses the role back onto the owner.
+    pub fn set_whitelist_manager(ctx: Context<UpdateConfig>, new_manager: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.whitelist_manager = new_manager;
+        ctx.accounts.audit(AuditAction::SetWhitelistManager, &new_manager)
+    }
+
+    /// Updates any subset of the admin wallet and whitelist manager in one call, e.g. while
+    /// responding to a key compromise. Only callable by the owner.
+    ///
+    /// A new admin wallet goes through the same `admin_change_delay` as `update_config`:
+    /// with a delay it only becomes pending and is applied by `apply_admin_wallet`.
+    pub fn rotate_roles(
+        ctx: Context<UpdateConfig>,
+        admin_wallet: Option<Pubkey>,
+        whitelist_manager: Option<Pubkey>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let config = &mut ctx.accounts.config;
+        let admin_wallet_effective_ts =
+            config.rotate_roles(admin_wallet, whitelist_manager, clock.unix_timestamp)?;
+        emit!(RolesRotatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            admin_wallet,
+            admin_wallet_effective_ts,
+            whitelist_manager,
+        });
+        ctx.accounts.audit(AuditAction::RotateRoles, &(admin_wallet, whitelist_manager))
+    }
+
+    /// Sets whether a whitelisted mint's `min_amount` is counted in whole tokens, e.g. 1 for
+    /// "at least 1.0 token", or in raw units. Whole-token minimums are scaled by the mint's
+    /// decimals at deposit time; `max_amount` always stays in raw units.
+    /// Callable by the owner or the whitelist manager.
+    pub fn set_min_in_whole_tokens(
+        ctx: Context<ManageWhitelist>,
+        token_mint: Pubkey,
+        whole_tokens: bool,
+    ) -> Result<()> {
+        ctx.accounts.config.set_min_in_whole_tokens(&token_mint, whole_tokens)?;
+        ctx.accounts.audit(AuditAction::SetMinInWholeTokens, &(token_mint, whole_tokens))
+    }
+
+    /// Makes `add_whitelisted_token` reject Token-2022 mints with a permanent delegate,
+    /// which could move deposited funds out of the admin wallet. Mints already on the
+    /// whitelist are not re-checked. Only callable by the owner.
+    pub fn set_reject_permanent_delegate(ctx: Context<UpdateConfig>, enabled: bool) -> Result<()> {
+        ctx.accounts.config.reject_permanent_delegate = enabled;
+        ctx.accounts.audit(AuditAction::SetRejectPermanentDelegate, &enabled)
+    }
+
+    /// Sets or clears the program `deposit_token` calls back after every deposit.
+    /// Only callable by the owner.
+    pub fn set_callback_program(
+        ctx: Context<UpdateConfig>,
+        callback_program: Option<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.callback_program = callback_program;
+        emit!(CallbackProgramUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            callback_program,
+        });
+        ctx.accounts.audit(AuditAction::SetCallbackProgram, &callback_program)
+    }
+
+    /// Replaces the config's feature flags. Bits not in `KNOWN_FEATURE_FLAGS` are refused,
+    /// so a bit cannot be set before the feature it will gate exists.
+    /// Only callable by the owner.
+    pub fn set_feature_flags(ctx: Context<UpdateConfig>, feature_flags: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let old_feature_flags = config.feature_flags;
+        config.set_feature_flags(feature_flags)?;
+        emit!(FeatureFlagsUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            old_feature_flags,
+            feature_flags,
+        });
+        ctx.accounts.audit(AuditAction::SetFeatureFlags, &feature_flags)
+    }
+
+    /// Lets the owner deposit mints that are not whitelisted, for testing and treasury
+    /// rebalancing. Such deposits have no limits and are flagged in `DepositEvent`.
+    /// Only callable by the owner.
+    pub fn set_owner_bypass(ctx: Context<UpdateConfig>, enabled: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.owner_bypass_enabled = enabled;
+        emit!(OwnerBypassUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config.key(),
+            enabled,
+        });
+        ctx.accounts.audit(AuditAction::SetOwnerBypass, &enabled)
+    }
+
+    /// Creates the config's `GlobalStats` account, paid for by the owner, and sets
+    /// `FEATURE_GLOBAL_STATS` so every later deposit is counted in it. Deposits made
+    /// before are not included. Only callable by the owner.
+    pub fn init_global_stats(ctx: Context<InitGlobalStats>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        ctx.accounts.global_stats.config = config.key();
+        config.feature_flags |= FEATURE_GLOBAL_STATS;
+        ctx.accounts.audit(AuditAction::InitGlobalStats, &())
+    }
+
+    /// Returns the config's deposit statistics via return data, for simulation reads.
+    pub fn get_stats(ctx: Context<ReadGlobalStats>) -> Result<GlobalStats> {
+        Ok((*ctx.accounts.global_stats).clone())
+    }
+
+    /// Creates the config's `AuditLog` account, paid for by the owner, and sets
+    /// `FEATURE_AUDIT_LOG` so every later privileged instruction is recorded in it. The
+    /// log's first entry records this instruction. Only callable by the owner.
+    pub fn init_audit_log(ctx: Context<InitAuditLog>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        ctx.accounts.audit_log.config = config.key();
+        config.feature_flags |= FEATURE_AUDIT_LOG;
+        ctx.accounts.audit(AuditAction::InitAuditLog, &())
+    }
+
+    /// Returns up to `AUDIT_LOG_PAGE` audit entries via return data, oldest first, skipping
+    /// the `offset` oldest ones. Call again with a larger offset until fewer come back.
+    pub fn get_audit_log(ctx: Context<ReadAuditLog>, offset: u8) -> Result<Vec<AuditEntry>> {
+        Ok(ctx.accounts.audit_log.page(usize::from(offset)))
+    }
+
+    /// Returns the whitelist entry at `index` via return data. Entries are sorted by mint,
+    /// so an index is only stable until the whitelist changes.
+    pub fn get_whitelist_entry(ctx: Context<ReadConfig>, index: u16) -> Result<WhitelistEntry> {
+        ctx.accounts.config.whitelist_entry_at(index).cloned()
+    }
+
+    /// Returns `PROGRAM_VERSION` via return data so deployments can be probed.
+    pub fn version(_ctx: Context<Version>) -> Result<u16> {
+        Ok(PROGRAM_VERSION)
+    }
+
+    /// Returns the crate version and the config's feature bits via return data, so SDKs
+    /// can detect what a deployment supports and degrade against older ones.
+    pub fn version_info(ctx: Context<ReadConfig>) -> Result<VersionInfo> {
+        Ok(VersionInfo::new(&ctx.accounts.config))
+    }
+}
+
+/// Installs the pending admin wallet and emits `AdminWalletAppliedEvent`.
+fn apply_pending_admin_wallet(config: &mut Account<Config>, now: i64) -> Result<()> {
+    let old_admin_wallet = config.apply_admin_wallet(now)?;
+    emit!(AdminWalletAppliedEvent {
+        version: PROGRAM_VERSION,
+        schema: EVENT_SCHEMA_VERSION,
+        config: config.key(),
+        old_admin_wallet,
+        new_admin_wallet: config.admin_wallet,
+    });
+    Ok(())
+}
+
+/// Appends `action` by `actor` to `audit_log` when `FEATURE_AUDIT_LOG` is set, failing if
+/// the log is then missing. `args` are only stored as a SHA-256 hash.
+pub fn record_audit(
+    config: &Config,
+    audit_log: Option<&mut AuditLog>,
+    action: AuditAction,
+    actor: Pubkey,
+    args: &impl AnchorSerialize,
+    now: i64,
+) -> Result<()> {
+    if !config.feature_enabled(FEATURE_AUDIT_LOG) {
+        return Ok(());
+    }
+    let audit_log = audit_log.ok_or(CustomError::AuditLogMissing)?;
+    audit_log.append(AuditEntry {
+        action: action as u8,
+        actor,
+        data_hash: hashv(&[&args.try_to_vec()?]).to_bytes(),
+        timestamp: now,
+    });
+    Ok(())
+}
+
+/// Deposit checks shared by the deposit instructions and their previews: the mint must be
+/// whitelisted, `amount` within its limits and its epoch cap, `balance` large enough to
+/// cover it and the mint's cooldown since `deposit_cooldown` elapsed at `now`. Nothing is
+/// recorded.
+/// With the owner bypass enabled, the owner may deposit a mint that is not whitelisted.
+/// Returns whether the deposit goes through that bypass.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_checks(
+    config: &Config,
+    user: &Pubkey,
+    mint: &Pubkey,
+    decimals: u8,
+    amount: u64,
+    balance: u64,
+    deposit_cooldown: Option<&DepositCooldown>,
+    now: i64,
+) -> Result<bool> {
+    let owner_bypass = config.whitelist_entry(mint).is_none() && config.owner_bypass(user);
+    if !owner_bypass {
+        config.check_deposit(mint, amount, decimals)?;
+        config.check_epoch_cap(mint, amount, now)?;
+    }
+    require!(balance >= amount, CustomError::InsufficientBalance);
+    check_deposit_cooldown(config, mint, deposit_cooldown, now)?;
+    Ok(owner_bypass)
+}
+
+/// Checks that `mint` may be whitelisted under `config`: its decimals must fall within the
+/// configured bounds and its extensions must be acceptable.
+pub fn check_whitelistable_mint(config: &Config, mint: &InterfaceAccount<Mint>) -> Result<()> {
+    require!(
+        mint.decimals >= config.min_decimals && mint.decimals <= config.max_decimals,
+        CustomError::DecimalsOutOfRange
+    );
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    check_mint_extensions(&mint_data, config.reject_permanent_delegate)
+}
+
+/// A callback program and the accounts forwarded to it.
+pub type CallbackAccounts<'a, 'info> = (&'a AccountInfo<'info>, &'a [AccountInfo<'info>]);
+
+/// Splits `deposit_token`'s remaining accounts into the transfer-hook accounts and, with
+/// a callback program configured, that program and the accounts after it. The callback
+/// program must then be present.
+pub fn split_callback_accounts<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    callback_program: Option<Pubkey>,
+) -> Result<(&'a [AccountInfo<'info>], Option<CallbackAccounts<'a, 'info>>)> {
+    let Some(callback_program) = callback_program else {
+        return Ok((remaining_accounts, None));
+    };
+    let index = remaining_accounts
+        .iter()
+        .position(|account| account.key() == callback_program)
+        .ok_or(CustomError::CallbackProgramMissing)?;
+    let (hook_accounts, rest) = remaining_accounts.split_at(index);
+    Ok((hook_accounts, Some((&rest[0], &rest[1..]))))
+}
+
+/// Rejects a deposit of `mint` made before its cooldown since the user's previous deposit
+/// has elapsed. Without `deposit_cooldown`, the user has never deposited the mint.
+pub fn check_deposit_cooldown(
+    config: &Config,
+    mint: &Pubkey,
+    deposit_cooldown: Option<&DepositCooldown>,
+    now: i64,
+) -> Result<()> {
+    let cooldown_secs = config.whitelist_entry(mint).map_or(0, |entry| entry.cooldown_secs);
+    let remaining = deposit_cooldown.map_or(0, |cooldown| cooldown.remaining(cooldown_secs, now));
+    if remaining > 0 {
+        msg!("Deposit cooldown active, {} seconds remaining", remaining);
+        return err!(CustomError::CooldownActive);
+    }
+    Ok(())
+}
+
+/// Records `now` as the user's last deposit of `mint` in `deposit_cooldown`, after
+/// `deposit_checks` passed. Mints without a cooldown need no cooldown account.
+pub fn record_deposit_cooldown(
+    config: &Config,
+    mint: &Pubkey,
+    deposit_cooldown: Option<&mut DepositCooldown>,
+    now: i64,
+) -> Result<()> {
+    let cooldown_secs = config.whitelist_entry(mint).map_or(0, |entry| entry.cooldown_secs);
+    if cooldown_secs == 0 {
+        return Ok(());
+    }
+    let deposit_cooldown = deposit_cooldown.ok_or(CustomError::DepositCooldownMissing)?;
+    deposit_cooldown.last_deposit_ts = now;
+    Ok(())
+}
+
+/// Points earned by depositing `amount` raw units at `points_per_token_e6`, i.e.
+/// `amount * points_per_token_e6 / 10^6`, saturating at `u64::MAX`.
+pub fn deposit_points(amount: u64, points_per_token_e6: u64) -> u64 {
+    let points = u128::from(amount) * u128::from(points_per_token_e6) / 1_000_000;
+    u64::try_from(points).unwrap_or(u64::MAX)
+}
+
+/// Credits the points a deposit of `amount` earns to `user_stats` and returns them.
+/// Mints without a points rate earn nothing and need no stats account.
+pub fn accrue_deposit_points(
+    config: &Config,
+    mint: &Pubkey,
+    amount: u64,
+    user_stats: Option<&mut UserStats>,
+) -> Result<u64> {
+    let rate = config.whitelist_entry(mint).map_or(0, |entry| entry.points_per_token_e6);
+    if rate == 0 {
+        return Ok(0);
+    }
+    let user_stats = user_stats.ok_or(CustomError::UserStatsMissing)?;
+    let points = deposit_points(amount, rate);
+    user_stats.points = user_stats.points.saturating_add(points);
+    Ok(points)
+}
+
+/// Mints `amount` receipt tokens of `token_mint` to `user_receipt_account`, checking that
+/// both accounts belong to the mint's receipt mint.
+pub fn mint_deposit_receipts<'info>(
+    config: &Pubkey,
+    token_mint: &Pubkey,
+    receipt_mint: Option<&InterfaceAccount<'info, Mint>>,
+    user_receipt_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(receipt_mint), Some(user_receipt_account)) = (receipt_mint, user_receipt_account)
+    else {
+        return err!(CustomError::ReceiptAccountsMissing);
+    };
+    let (expected_mint, bump) = receipt_mint_address(config, token_mint);
+    require_keys_eq!(receipt_mint.key(), expected_mint, CustomError::InvalidReceiptMint);
+    require_keys_eq!(user_receipt_account.mint, expected_mint, CustomError::InvalidReceiptMint);
+
+    let seeds: &[&[u8]] = &[RECEIPT_SEED, config.as_ref(), token_mint.as_ref(), &[bump]];
+    let cpi_accounts = MintTo {
+        mint: receipt_mint.to_account_info(),
+        to: user_receipt_account.to_account_info(),
+        authority: receipt_mint.to_account_info(),
+    };
+    let signer_seeds = &[seeds];
+    let cpi_ctx =
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+    mint_to(cpi_ctx, amount)
+}
+
+/// Invokes `callback_program` with `DEPOSIT_CALLBACK_DISCRIMINATOR` and `payload`,
+/// forwarding `callback_accounts` with their signer and writable flags.
+pub fn invoke_deposit_callback<'info>(
+    callback_program: &AccountInfo<'info>,
+    callback_accounts: &[AccountInfo<'info>],
+    payload: &DepositCallback,
+) -> Result<()> {
+    let mut data = DEPOSIT_CALLBACK_DISCRIMINATOR.to_vec();
+    payload.serialize(&mut data)?;
+    let instruction = Instruction {
+        program_id: callback_program.key(),
+        accounts: callback_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data,
+    };
+    let mut account_infos = callback_accounts.to_vec();
+    account_infos.push(callback_program.clone());
+    invoke(&instruction, &account_infos)?;
+    Ok(())
+}
+
+/// Checks that `instruction` is an Ed25519 program instruction verifying exactly one
+/// signature by `signer` over `message`, with all data inside the instruction itself.
+/// The Ed25519 program has already checked the signature if the transaction landed.
+pub fn check_ed25519_instruction(
+    instruction: &Instruction,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    require_keys_eq!(
+        instruction.program_id,
+        anchor_lang::solana_program::ed25519_program::ID,
+        CustomError::InvalidIntentSignature
+    );
+    let data = &instruction.data;
+    // One signature: a count byte, a padding byte and seven u16 offsets.
+    require!(
+        data.len() >= 16 && data[0] == 1,
+        CustomError::InvalidIntentSignature
+    );
+    let offset = |index: usize| {
+        usize::from(u16::from_le_bytes([data[2 + 2 * index], data[3 + 2 * index]]))
+    };
+    let (public_key_offset, message_offset, message_size) = (offset(2), offset(4), offset(5));
+    // The signature, public key and message must all come from this instruction.
+    require!(
+        [offset(1), offset(3), offset(6)] == [usize::from(u16::MAX); 3],
+        CustomError::InvalidIntentSignature
+    );
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed = data.get(message_offset..message_offset + message_size);
+    require!(
+        public_key == Some(signer.as_ref()) && signed == Some(message),
+        CustomError::InvalidIntentSignature
+    );
+    Ok(())
+}
+
+/// Checks whether a deposit made through `via_program`, or directly when `None`, is
+/// allowed under `config`.
+pub fn check_via_program(config: &Config, via_program: Option<Pubkey>) -> Result<()> {
+    require!(
+        via_program.is_none() || !config.feature_enabled(FEATURE_REJECT_CPI_DEPOSITS),
+        CustomError::CpiDepositsDisabled
+    );
+    Ok(())
+}
+
+/// Returns the commitment for a hidden deposit: `sha256(amount_le || nonce || user)`.
+pub fn commitment_hash(amount: u64, nonce: &[u8; 32], user: &Pubkey) -> [u8; 32] {
+    hashv(&[&amount.to_le_bytes(), nonce, user.as_ref()]).to_bytes()
+}
+
+/// Moves the lamports `target` holds above its rent-exempt minimum to `destination` and
+/// returns the amount moved. `target` must be owned by this program.
+pub fn transfer_excess_lamports(
+    target: &AccountInfo,
+    destination: &AccountInfo,
+    rent: &Rent,
+) -> Result<u64> {
+    require_keys_eq!(*target.owner, crate::ID, CustomError::NotProgramAccount);
+    let surplus = target
+        .lamports()
+        .saturating_sub(rent.minimum_balance(target.data_len()));
+    if surplus > 0 {
+        **target.try_borrow_mut_lamports()? -= surplus;
+        let mut destination_lamports = destination.try_borrow_mut_lamports()?;
+        **destination_lamports = destination_lamports
+            .checked_add(surplus)
+            .ok_or(CustomError::CounterOverflow)?;
+    }
+    Ok(surplus)
+}
+
+/// Checks one (mint, ata) pair passed to `prepare_admin_accounts`: the mint must be
+/// whitelisted and owned by `token_program`, and the ATA must be the admin wallet's.
+pub fn check_admin_account_pair(
+    config: &Config,
+    mint: &Pubkey,
+    mint_owner: &Pubkey,
+    ata: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<()> {
+    require!(config.whitelist_entry(mint).is_some(), CustomError::TokenNotWhitelisted);
+    require_keys_eq!(*mint_owner, *token_program, CustomError::InvalidAdminAccountPairs);
+    let expected = derive_admin_ata(&config.admin_wallet, mint, token_program);
+    require_keys_eq!(*ata, expected, CustomError::InvalidAdminAccountPairs);
+    Ok(())
+}
+
+/// Returns the receipt mint address and bump for `underlying_mint` under `config`.
+pub fn receipt_mint_address(config: &Pubkey, underlying_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RECEIPT_SEED, config.as_ref(), underlying_mint.as_ref()],
+        &crate::ID,
+    )
+}
+
+/// Returns the address and bump of `user`'s cooldown account for `token_mint` under `config`.
+pub fn deposit_cooldown_address(
+    config: &Pubkey,
+    user: &Pubkey,
+    token_mint: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DEPOSIT_COOLDOWN_SEED, config.as_ref(), user.as_ref(), token_mint.as_ref()],
+        &crate::ID,
+    )
+}
+
+/// Returns the address and bump of the receipt for deposit number `deposit_seq` under `config`.
+pub fn deposit_receipt_address(config: &Pubkey, deposit_seq: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DEPOSIT_RECEIPT_SEED, config.as_ref(), &deposit_seq.to_le_bytes()],
+        &crate::ID,
+    )
+}
+
+/// Returns the deposit commitment address and bump of `user` under `config`.
+pub fn commitment_address(config: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COMMITMENT_SEED, config.as_ref(), user.as_ref()], &crate::ID)
+}
+
+/// Returns the associated token account of `admin_wallet` for `mint`, which
+/// `deposit_token` transfers into. Off-curve admin wallets are supported.
+pub fn derive_admin_ata(admin_wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address_with_program_id(
+        admin_wallet,
+        mint,
+        token_program,
+    )
+}
+
+/// Every address a client needs for deposits of one mint by one user, so SDKs do not
+/// reimplement the seed logic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivedAddresses {
+    /// The current admin wallet's token account, the destination of deposits.
+    pub admin_ata: Pubkey,
+    /// The pending admin wallet's token account, to prepare before the change applies.
+    pub pending_admin_ata: Option<Pubkey>,
+    /// The receipt mint, which only exists once `create_receipt_mint` ran for the mint.
+    pub receipt_mint: Pubkey,
+    /// The user's token account for the mint.
+    pub user_ata: Pubkey,
+    /// The user's receipt token account.
+    pub user_receipt_ata: Pubkey,
+    /// The user's deposit commitment.
+    pub commitment: Pubkey,
+}
+
+/// Derives every address of `DerivedAddresses` for the config at `config_key`.
+pub fn derive_addresses(
+    config_key: &Pubkey,
+    config: &Config,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    user: &Pubkey,
+) -> DerivedAddresses {
+    let receipt_mint = receipt_mint_address(config_key, mint).0;
+    let user_ata = |mint: &Pubkey| {
+        associated_token::get_associated_token_address_with_program_id(user, mint, token_program)
+    };
+    DerivedAddresses {
+        admin_ata: derive_admin_ata(&config.admin_wallet, mint, token_program),
+        pending_admin_ata: config
+            .pending_admin_wallet
+            .as_ref()
+            .map(|pending| derive_admin_ata(&pending.wallet, mint, token_program)),
+        receipt_mint,
+        user_ata: user_ata(mint),
+        user_receipt_ata: user_ata(&receipt_mint),
+        commitment: commitment_address(config_key, user).0,
+    }
+}
+
+/// Rejects mints whose Token-2022 extensions make them unfit for the whitelist: a
+/// non-transferable mint can never be moved out of the admin wallet, and with
+/// `reject_permanent_delegate` a permanent delegate could claw deposits back.
+/// Legacy SPL Token mints carry no extensions and always pass.
+pub fn check_mint_extensions(mint_data: &[u8], reject_permanent_delegate: bool) -> Result<()> {
+    let Ok(mint) = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data) else {
+        return Ok(());
+    };
+    require!(
+        mint.get_extension::<NonTransferable>().is_err(),
+        CustomError::MintNotTransferable
+    );
+    require!(
+        !reject_permanent_delegate || mint.get_extension::<PermanentDelegate>().is_err(),
+        CustomError::MintHasPermanentDelegate
+    );
+    Ok(())
+}
+
+/// Returns the UI amount of `amount` raw units, scaled by 10^9, as wallets show it at
+/// `unix_timestamp`. For interest-bearing Token-2022 mints this applies the extension's
+/// accrued rate; other mints only shift by `decimals`. Saturates instead of panicking on
+/// extreme rates or decimals.
+pub fn ui_amount_e9(mint_data: &[u8], amount: u64, decimals: u8, unix_timestamp: i64) -> u64 {
+    let interest = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data)
+        .ok()
+        .and_then(|mint| mint.get_extension::<InterestBearingConfig>().ok().copied());
+    if let Some(ui_amount) = interest
+        .and_then(|config| config.amount_to_ui_amount(amount, decimals, unix_timestamp))
+        .and_then(|ui_amount| ui_amount.parse::<f64>().ok())
+    {
+        // Float-to-int casts saturate, and NaN becomes 0.
+        return (ui_amount * 1e9) as u64;
+    }
+    if decimals <= 9 {
+        amount.saturating_mul(10u64.pow(9 - u32::from(decimals)))
+    } else {
+        // A divisor beyond u64 leaves less than one unit.
+        10u64
+            .checked_pow(u32::from(decimals) - 9)
+            .map_or(0, |divisor| amount / divisor)
+    }
+}
+
+/// Returns the transfer-hook program configured on a mint, if any.
+/// Legacy SPL Token mints and Token-2022 mints without the extension return `None`.
+pub fn transfer_hook_program_id(mint_data: &[u8]) -> Option<Pubkey> {
+    StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data)
+        .ok()
+        .and_then(|mint| transfer_hook::get_program_id(&mint))
 }
 
 #[derive(Accounts)]
@@ -87,12 +1400,40 @@ pub struct InitializeConfig<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct AddWhitelistedToken<'info> {
+    /// The config account; the authority must be its owner or whitelist manager.
+    #[account(
+        mut,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable,
+        constraint = config.can_manage_whitelist(&authority.key()) @ CustomError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub authority: Signer<'info>,
+    /// The mint being whitelisted; its decimals are checked against the config bounds.
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
 #[derive(Accounts)]
 pub struct ManageWhitelist<'info> {
-    /// The config account; the owner must match the one stored in config.
-    #[account(mut, has_one = owner)]
+    /// The config account; the authority must be its owner or whitelist manager.
+    #[account(
+        mut,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable,
+        constraint = config.can_manage_whitelist(&authority.key()) @ CustomError::Unauthorized
+    )]
     pub config: Account<'info, Config>,
-    pub owner: Signer<'info>,
+    pub authority: Signer<'info>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
 }
 
 #[derive(Accounts)]
@@ -102,7 +1443,7 @@ pub struct DepositToken<'info> {
     pub user: Signer<'info>,
 
     /// The config account containing the admin wallet and whitelist.
-    #[account(mut)]
+    #[account(mut, constraint = config.is_valid() @ CustomError::CorruptConfig)]
     pub config: Account<'info, Config>,
 
     /// The token mint for the token being deposited.
@@ -117,51 +1458,3602 @@ pub struct DepositToken<'info> {
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// The admin wallet's associated token account for the given mint.
-    /// This account is derived automatically using the admin_wallet from the config.
+    /// This account is derived automatically using the admin_wallet from the config and
+    /// `token_program`, so Token-2022 mints use the admin's Token-2022 ATA.
+    /// It is only checked, never created here, so an off-curve admin wallet works as long as
+    /// its ATA was created beforehand (with `allowOwnerOffCurve` in the spl-token client).
     #[account(
         mut,
         associated_token::mint = token_mint,
         associated_token::authority = config.admin_wallet,
+        associated_token::token_program = token_program,
     )]
     pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
 
     pub token_program: Interface<'info, TokenInterface>,
+
+    /// The receipt mint for `token_mint`; required only if the mint has receipts enabled.
+    #[account(mut)]
+    pub receipt_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// The account receiving the receipts; required only if the mint has receipts enabled.
+    #[account(mut, constraint = user_receipt_account.owner == user.key())]
+    pub user_receipt_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The SPL Memo program; required only if a memo is given.
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// The receipt of this deposit, created only if passed. Its address is derived from
+    /// the sequence number the deposit is about to receive.
+    #[account(
+        init,
+        payer = user,
+        space = 8 + DepositReceipt::LEN,
+        seeds = [
+            DEPOSIT_RECEIPT_SEED,
+            config.key().as_ref(),
+            &config.deposit_seq.wrapping_add(1).to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub deposit_receipt: Option<Account<'info, DepositReceipt>>,
+
+    /// Required only with `deposit_receipt`.
+    pub system_program: Option<Program<'info, System>>,
+
+    /// The config's statistics; required once `init_global_stats` has run.
+    #[account(mut, seeds = [GLOBAL_STATS_SEED, config.key().as_ref()], bump)]
+    pub global_stats: Option<Account<'info, GlobalStats>>,
+
+    /// CHECK: The instructions sysvar, checked by address; required only for deposits
+    /// made through CPI.
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// The user's last deposit of `token_mint`; required only if the mint has a cooldown.
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + DepositCooldown::LEN,
+        seeds = [
+            DEPOSIT_COOLDOWN_SEED,
+            config.key().as_ref(),
+            user.key().as_ref(),
+            token_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub deposit_cooldown: Option<Account<'info, DepositCooldown>>,
+
+    /// The user's loyalty points; required only if the mint earns points.
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStats::LEN,
+        seeds = [USER_STATS_SEED, config.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateConfig<'info> {
-    /// The config account; the owner must match the one stored in config.
-    #[account(mut, has_one = owner)]
+pub struct ValidateDeposit<'info> {
+    /// CHECK: The would-be depositor; only compared against the token account owner.
+    pub user: UncheckedAccount<'info>,
+
+    #[account(constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        constraint = user_token_account.owner == user.key(),
+        constraint = user_token_account.mint == token_mint.key()
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: May not exist yet; its address and initialization are checked in the handler.
+    pub admin_token_account: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The user's last deposit of `token_mint`; omit it if they never deposited the mint.
+    #[account(
+        seeds = [
+            DEPOSIT_COOLDOWN_SEED,
+            config.key().as_ref(),
+            user.key().as_ref(),
+            token_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub deposit_cooldown: Option<Account<'info, DepositCooldown>>,
+}
+
+#[derive(Accounts)]
+pub struct CreateReceiptMint<'info> {
+    /// The config account; only its owner can enable receipts.
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable
+    )]
     pub config: Account<'info, Config>,
+    #[account(mut)]
     pub owner: Signer<'info>,
+
+    /// The whitelisted mint the receipts stand for.
+    #[account(mint::token_program = token_program)]
+    pub underlying_mint: InterfaceAccount<'info, Mint>,
+
+    /// The receipt mint, created here with the underlying decimals and itself as authority.
+    #[account(
+        init,
+        payer = owner,
+        seeds = [RECEIPT_SEED, config.key().as_ref(), underlying_mint.key().as_ref()],
+        bump,
+        mint::decimals = underlying_mint.decimals,
+        mint::authority = receipt_mint,
+        mint::token_program = token_program,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
 }
 
-#[account]
-pub struct Config {
-    /// The owner who can manage the whitelist.
-    pub owner: Pubkey,
-    /// The wallet that receives deposited tokens.
-    pub admin_wallet: Pubkey,
-    /// List of whitelisted token mints.
-    pub whitelist: Vec<Pubkey>,
+#[derive(Accounts)]
+pub struct BurnReceipt<'info> {
+    pub user: Signer<'info>,
+
+    #[account(constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+
+    /// The mint the receipts stand for.
+    pub underlying_mint: InterfaceAccount<'info, Mint>,
+
+    /// The receipt mint for `underlying_mint` under this config.
+    #[account(
+        mut,
+        seeds = [RECEIPT_SEED, config.key().as_ref(), underlying_mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+
+    /// The user's receipt account the receipts are burned from.
+    #[account(
+        mut,
+        token::mint = receipt_mint,
+        token::authority = user,
+    )]
+    pub user_receipt_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-impl Config {
-    // Space calculation: 32 bytes for owner + 32 bytes for admin_wallet + 4 bytes for vector length + (max 10 * 32 bytes)
-    pub const LEN: usize = 32 + 32 + 4 + 10 * 32;
+impl<'info> DepositToken<'info> {
+    /// Borrows the accounts `execute_deposit` works on; shared by `deposit_token` and
+    /// `reveal_deposit`.
+    fn deposit_accounts(&mut self) -> DepositAccounts<'_, 'info> {
+        DepositAccounts {
+            config: &mut self.config,
+            user: self.user.key(),
+            authority: self.user.to_account_info(),
+            token_mint: &self.token_mint,
+            user_token_account: &self.user_token_account,
+            admin_token_account: &mut self.admin_token_account,
+            token_program: &self.token_program,
+            receipt_mint: self.receipt_mint.as_ref(),
+            user_receipt_account: self.user_receipt_account.as_ref(),
+            memo_program: self.memo_program.as_ref(),
+            deposit_receipt: self.deposit_receipt.as_mut(),
+            global_stats: self.global_stats.as_deref_mut(),
+            instructions: self.instructions.as_deref(),
+            deposit_cooldown: self.deposit_cooldown.as_deref_mut(),
+            user_stats: self.user_stats.as_deref_mut(),
+        }
+    }
 }
 
-#[event]
-pub struct DepositEvent {
-    pub config_owner: Pubkey, // Make sure to keep a check for config in the indexer
+/// The accounts of a deposit, borrowed from `DepositToken` or `RelayedDeposit`.
+pub struct DepositAccounts<'a, 'info> {
+    pub config: &'a mut Account<'info, Config>,
+    /// The user whose tokens are deposited.
     pub user: Pubkey,
-    pub amount: u64,
-    pub token_mint: Pubkey,
-    pub timestamp: u64,
+    /// Signs the transfer: the user, or an authority they delegated to.
+    pub authority: AccountInfo<'info>,
+    pub token_mint: &'a InterfaceAccount<'info, Mint>,
+    pub user_token_account: &'a InterfaceAccount<'info, TokenAccount>,
+    pub admin_token_account: &'a mut InterfaceAccount<'info, TokenAccount>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+    pub receipt_mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    pub user_receipt_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub memo_program: Option<&'a Program<'info, Memo>>,
+    pub deposit_receipt: Option<&'a mut Account<'info, DepositReceipt>>,
+    pub global_stats: Option<&'a mut GlobalStats>,
+    pub instructions: Option<&'a AccountInfo<'info>>,
+    pub deposit_cooldown: Option<&'a mut DepositCooldown>,
+    pub user_stats: Option<&'a mut UserStats>,
 }
 
-#[error_code]
-pub enum CustomError {
-    #[msg("The token provided is not whitelisted for deposit.")]
-    TokenNotWhitelisted,
+/// Validates and performs a deposit of `amount` from `accounts.user`; shared by every
+/// deposit instruction. `signer_seeds` sign for `accounts.authority` if it is a PDA.
+pub fn execute_deposit<'info>(
+    accounts: DepositAccounts<'_, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    beneficiary: Option<Pubkey>,
+    memo: String,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let DepositAccounts {
+        config,
+        user,
+        authority,
+        token_mint,
+        user_token_account,
+        admin_token_account,
+        token_program,
+        receipt_mint,
+        user_receipt_account,
+        memo_program,
+        deposit_receipt,
+        global_stats,
+        instructions,
+        deposit_cooldown,
+        user_stats,
+    } = accounts;
+    require!(
+        beneficiary != Some(Pubkey::default()),
+        CustomError::InvalidBeneficiary
+    );
+    require!(memo.len() <= MAX_MEMO_LEN, CustomError::MemoTooLong);
+
+    // Ensure that the token mint is whitelisted, the amount is within its limits, the
+    // user can cover it and the mint's cooldown since their previous deposit elapsed.
+    let clock = Clock::get()?;
+    let owner_bypass = deposit_checks(
+        config,
+        &user,
+        &token_mint.key(),
+        token_mint.decimals,
+        amount,
+        user_token_account.amount,
+        deposit_cooldown.as_deref(),
+        clock.unix_timestamp,
+    )?;
+
+    if !memo.is_empty() {
+        let memo_program = memo_program.ok_or(CustomError::MemoProgramMissing)?;
+        memo::build_memo(
+            CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+            memo.as_bytes(),
+        )?;
+    }
+
+    let (hook_accounts, callback) =
+        split_callback_accounts(remaining_accounts, config.callback_program)?;
+
+    let is_cpi = get_stack_height() > TRANSACTION_LEVEL_STACK_HEIGHT;
+    let via_program = if is_cpi {
+        // Only the top-level instruction is visible, not the immediate caller of a nested CPI.
+        let instructions = instructions.ok_or(CustomError::InstructionsSysvarMissing)?;
+        let index = load_current_index_checked(instructions)?;
+        Some(load_instruction_at_checked(usize::from(index), instructions)?.program_id)
+    } else {
+        None
+    };
+    check_via_program(config, via_program)?;
+
+    record_deposit_cooldown(
+        config,
+        &token_mint.key(),
+        deposit_cooldown,
+        clock.unix_timestamp,
+    )?;
+    config.record_epoch_deposit(&token_mint.key(), amount, clock.unix_timestamp)?;
+
+    // Transfer tokens from the user's token account to the admin's derived associated token account.
+    let balance_before = admin_token_account.amount;
+    let mint_info = token_mint.to_account_info();
+    let (hook_program, ui_amount_e9) = {
+        let mint_data = mint_info.try_borrow_data()?;
+        (
+            transfer_hook_program_id(&mint_data),
+            ui_amount_e9(&mint_data, amount, token_mint.decimals, clock.unix_timestamp),
+        )
+    };
+    if hook_program.is_some() {
+        // The token program invokes the hook, which needs its extra accounts resolved
+        // from the validation account passed in remaining_accounts.
+        spl_token_2022::onchain::invoke_transfer_checked(
+            &token_program.key(),
+            user_token_account.to_account_info(),
+            mint_info,
+            admin_token_account.to_account_info(),
+            authority,
+            hook_accounts,
+            amount,
+            token_mint.decimals,
+            signer_seeds,
+        )?;
+    } else {
+        let cpi_accounts = TransferChecked {
+            from: user_token_account.to_account_info(),
+            to: admin_token_account.to_account_info(),
+            authority,
+            mint: mint_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, token_mint.decimals)?;
+    }
+
+    // Report what actually arrived, which transfer fees or hooks can make less than `amount`.
+    admin_token_account.reload()?;
+    let received = admin_token_account
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(CustomError::AdminBalanceDecreased)?;
+    config.check_received(&token_mint.key(), received, token_mint.decimals)?;
+
+    // Mint a proof of deposit if the whitelist entry opted into receipts.
+    let config_key = config.key();
+    let token_mint_key = token_mint.key();
+    let receipts = config
+        .whitelist_entry(&token_mint_key)
+        .is_some_and(|entry| entry.receipts);
+    if receipts {
+        mint_deposit_receipts(
+            &config_key,
+            &token_mint_key,
+            receipt_mint,
+            user_receipt_account,
+            token_program,
+            amount,
+        )?;
+    }
+
+    if config.feature_enabled(FEATURE_GLOBAL_STATS) {
+        global_stats
+            .ok_or(CustomError::GlobalStatsMissing)?
+            .record_deposit(amount)?;
+    }
+    let points = accrue_deposit_points(config, &token_mint_key, amount, user_stats)?;
+
+    // Give every deposit a sequence number so consumers can order deposits within a slot.
+    config.next_deposit_seq()?;
+
+    let receipt = match deposit_receipt {
+        Some(deposit_receipt) => {
+            deposit_receipt.set_inner(DepositReceipt {
+                config: config_key,
+                user,
+                token_mint: token_mint_key,
+                amount,
+                deposit_seq: config.deposit_seq,
+                timestamp: clock.unix_timestamp,
+            });
+            Some(deposit_receipt.key())
+        }
+        None => None,
+    };
+
+    // Let the downstream program credit the deposit; its failure aborts everything.
+    if let Some((callback_program, callback_accounts)) = callback {
+        let payload = DepositCallback {
+            config: config_key,
+            user,
+            beneficiary: beneficiary.unwrap_or(user),
+            token_mint: token_mint_key,
+            amount,
+            deposit_seq: config.deposit_seq,
+        };
+        invoke_deposit_callback(callback_program, callback_accounts, &payload)?;
+    }
+
+    // Emit an event indicating a successful deposit.
+    emit!(DepositEvent {
+        version: PROGRAM_VERSION,
+        schema: EVENT_SCHEMA_VERSION,
+        config_owner: config.owner,
+        user,
+        beneficiary: beneficiary.unwrap_or(user),
+        amount,
+        received,
+        ui_amount_e9,
+        token_mint: token_mint_key,
+        timestamp: clock.unix_timestamp,
+        deposit_seq: config.deposit_seq,
+        owner_bypass,
+        memo,
+        via_program,
+        receipt,
+        slot: clock.slot,
+        decimals: token_mint.decimals,
+        token_program: token_program.key(),
+        points,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CommitDeposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + DepositCommitment::LEN,
+        seeds = [COMMITMENT_SEED, config.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub commitment: Account<'info, DepositCommitment>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealDeposit<'info> {
+    /// The same accounts as `deposit_token`.
+    pub deposit: DepositToken<'info>,
+
+    /// The user's open commitment; closed once the deposit succeeds.
+    #[account(
+        mut,
+        seeds = [COMMITMENT_SEED, deposit.config.key().as_ref(), deposit.user.key().as_ref()],
+        bump,
+    )]
+    pub commitment: Account<'info, DepositCommitment>,
+}
+
+#[derive(Accounts)]
+pub struct RelayedDeposit<'info> {
+    /// Submits the intent and pays fees and rent.
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    /// CHECK: Only its key is used; the user authorizes the deposit by signing the intent.
+    pub user: UncheckedAccount<'info>,
+
+    #[account(mut, constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// The user's token account, delegated to `relay_authority` for at least `amount`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key(),
+        constraint = user_token_account.mint == token_mint.key()
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = config.admin_wallet,
+        associated_token::token_program = token_program,
+    )]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA that signs the transfer as the user's delegate; it holds no data.
+    #[account(seeds = [RELAY_AUTHORITY_SEED, config.key().as_ref()], bump)]
+    pub relay_authority: UncheckedAccount<'info>,
+
+    /// The next nonce the user's intents must carry; created on their first relayed deposit.
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + RelayNonce::LEN,
+        seeds = [RELAY_NONCE_SEED, config.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub relay_nonce: Account<'info, RelayNonce>,
+
+    /// CHECK: The instructions sysvar, checked by address, holding the Ed25519 instruction.
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// The receipt mint for `token_mint`; required only if the mint has receipts enabled.
+    #[account(mut)]
+    pub receipt_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// The account receiving the receipts; required only if the mint has receipts enabled.
+    #[account(mut, constraint = user_receipt_account.owner == user.key())]
+    pub user_receipt_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The config's statistics; required once `init_global_stats` has run.
+    #[account(mut, seeds = [GLOBAL_STATS_SEED, config.key().as_ref()], bump)]
+    pub global_stats: Option<Account<'info, GlobalStats>>,
+
+    /// The user's last deposit of `token_mint`; required only if the mint has a cooldown.
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + DepositCooldown::LEN,
+        seeds = [
+            DEPOSIT_COOLDOWN_SEED,
+            config.key().as_ref(),
+            user.key().as_ref(),
+            token_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub deposit_cooldown: Option<Account<'info, DepositCooldown>>,
+
+    /// The user's loyalty points; required only if the mint earns points.
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + UserStats::LEN,
+        seeds = [USER_STATS_SEED, config.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+
+    /// The SPL Memo program; required only if the intent carries a memo.
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// The receipt of this deposit, created only if passed, like in `deposit_token`.
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + DepositReceipt::LEN,
+        seeds = [
+            DEPOSIT_RECEIPT_SEED,
+            config.key().as_ref(),
+            &config.deposit_seq.wrapping_add(1).to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub deposit_receipt: Option<Account<'info, DepositReceipt>>,
+}
+
+impl<'info> RelayedDeposit<'info> {
+    /// Verifies the signed intent and performs the deposit for `relayed_deposit`.
+    fn process(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        amount: u64,
+        nonce: u64,
+        deadline: i64,
+        memo: String,
+        relay_authority_bump: u8,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let config_key = self.config.key();
+        let user = self.user.key();
+
+        // The user's signature is what authorizes the deposit and its attribution.
+        let intent = DepositIntent {
+            config: config_key,
+            user,
+            token_mint: self.token_mint.key(),
+            amount,
+            nonce,
+            deadline,
+            memo,
+        };
+        require!(clock.unix_timestamp <= deadline, CustomError::IntentExpired);
+        let current_index = load_current_index_checked(&self.instructions)?;
+        let previous_index = current_index
+            .checked_sub(1)
+            .ok_or(CustomError::InvalidIntentSignature)?;
+        let ed25519_instruction =
+            load_instruction_at_checked(usize::from(previous_index), &self.instructions)?;
+        check_ed25519_instruction(&ed25519_instruction, &user, &intent.message()?)?;
+        self.relay_nonce.consume(nonce)?;
+
+        // The relay authority moves the tokens as the user's delegate.
+        let seeds: &[&[u8]] = &[RELAY_AUTHORITY_SEED, config_key.as_ref(), &[relay_authority_bump]];
+        let accounts = DepositAccounts {
+            config: &mut self.config,
+            user,
+            authority: self.relay_authority.to_account_info(),
+            token_mint: &self.token_mint,
+            user_token_account: &self.user_token_account,
+            admin_token_account: &mut self.admin_token_account,
+            token_program: &self.token_program,
+            receipt_mint: self.receipt_mint.as_ref(),
+            user_receipt_account: self.user_receipt_account.as_ref(),
+            memo_program: self.memo_program.as_ref(),
+            deposit_receipt: self.deposit_receipt.as_mut(),
+            global_stats: self.global_stats.as_deref_mut(),
+            instructions: Some(&self.instructions),
+            deposit_cooldown: self.deposit_cooldown.as_deref_mut(),
+            user_stats: self.user_stats.as_deref_mut(),
+        };
+        execute_deposit(accounts, remaining_accounts, amount, None, intent.memo, &[seeds])?;
+
+        emit!(RelayedDepositEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: config_key,
+            user,
+            relayer: self.relayer.key(),
+            nonce,
+            deposit_seq: self.config.deposit_seq,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CloseReceipt<'info> {
+    /// The depositor the receipt was written for.
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut, close = user, has_one = user)]
+    pub deposit_receipt: Account<'info, DepositReceipt>,
+}
+
+#[derive(Accounts)]
+pub struct CancelCommitment<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Not validated, so a commitment can always be cancelled.
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [COMMITMENT_SEED, config.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub commitment: Account<'info, DepositCommitment>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateDeposit<'info> {
+    /// The config account containing the whitelist; read-only.
+    #[account(constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+
+    /// The token mint that would be deposited.
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: The would-be depositor; only compared against the owner and used to derive
+    /// `deposit_cooldown`.
+    pub user: Option<UncheckedAccount<'info>>,
+
+    /// The user's last deposit of `token_mint`; omit it if they never deposited the mint.
+    pub deposit_cooldown: Option<Account<'info, DepositCooldown>>,
+}
+
+#[derive(Accounts)]
+pub struct PrepareAdminAccounts<'info> {
+    #[account(constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Only used as the authority of the created ATAs; must be the configured admin wallet.
+    #[account(address = config.admin_wallet)]
+    pub admin_wallet: UncheckedAccount<'info>,
+
+    /// Pays the rent of every created account.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    /// The config account; the owner must match the one stored in config.
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable
+    )]
+    pub config: Account<'info, Config>,
+    pub owner: Signer<'info>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// CHECK: A config in the original layout, which `Account<Config>` cannot load; its
+    /// size and discriminator are checked by `LegacyConfig::from_account_data`.
+    #[account(mut, owner = crate::ID @ CustomError::NotProgramAccount)]
+    pub config: UncheckedAccount<'info>,
+    /// Must be the owner stored in the config; pays for the extra space.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CollectExcessLamports<'info> {
+    #[account(
+        mut,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable,
+        constraint = config.is_privileged(&authority.key()) @ CustomError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    /// The owner or the admin wallet; receives the excess lamports.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Must be owned by this program, which is checked in the handler; only its
+    /// lamports above the rent-exempt minimum are moved. Omit to collect from the config.
+    #[account(mut)]
+    pub target: Option<UncheckedAccount<'info>>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct InitGlobalStats<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Pays the rent of the statistics account.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + GlobalStats::LEN,
+        seeds = [GLOBAL_STATS_SEED, config.key().as_ref()],
+        bump,
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct ResetPoints<'info> {
+    /// The config account; the owner must match the one stored in config.
+    #[account(
+        has_one = owner,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable
+    )]
+    pub config: Account<'info, Config>,
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds = [USER_STATS_SEED, config.key().as_ref(), user.as_ref()], bump)]
+    pub user_stats: Account<'info, UserStats>,
+
+    /// The config's audit log; required once `init_audit_log` has run.
+    #[account(mut, seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct InitAuditLog<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = config.is_valid() @ CustomError::CorruptConfig,
+        constraint = !config.immutable @ CustomError::ConfigImmutable
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Pays the rent of the audit log.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [AUDIT_LOG_SEED, config.key().as_ref()],
+        bump,
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReadAuditLog<'info> {
+    pub config: Account<'info, Config>,
+
+    #[account(seeds = [AUDIT_LOG_SEED, config.key().as_ref()], bump)]
+    pub audit_log: Account<'info, AuditLog>,
+}
+
+/// Contexts of privileged instructions. Every handler taking one of them ends by calling
+/// `audit`, which records the change once `FEATURE_AUDIT_LOG` is set. The
+/// `privileged_instructions_are_audited` test holds every context that checks its signer
+/// against the owner, manager or admin wallet to this.
+pub trait Audited {
+    /// Records `action` with the hash of its Borsh-serialized arguments `args`.
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()>;
+}
+
+impl Audited for AddWhitelistedToken<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.authority.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for ManageWhitelist<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.authority.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for UpdateConfig<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.owner.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for ResetPoints<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.owner.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for CreateReceiptMint<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.owner.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for CollectExcessLamports<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.authority.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for InitGlobalStats<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.owner.key();
+        record_audit(&self.config, self.audit_log.as_deref_mut(), action, actor, args, now)
+    }
+}
+
+impl Audited for InitAuditLog<'_> {
+    fn audit(&mut self, action: AuditAction, args: &impl AnchorSerialize) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let actor = self.owner.key();
+        record_audit(&self.config, Some(&mut *self.audit_log), action, actor, args, now)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ReadGlobalStats<'info> {
+    pub config: Account<'info, Config>,
+
+    #[account(seeds = [GLOBAL_STATS_SEED, config.key().as_ref()], bump)]
+    pub global_stats: Account<'info, GlobalStats>,
+}
+
+#[derive(Accounts)]
+pub struct ReadConfig<'info> {
+    #[account(constraint = config.is_valid() @ CustomError::CorruptConfig)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct Version {}
+
+#[account]
+pub struct Config {
+    /// The owner who controls the config and can manage the whitelist.
+    pub owner: Pubkey,
+    /// The wallet that receives deposited tokens. May be an off-curve PDA.
+    pub admin_wallet: Pubkey,
+    /// List of whitelisted token mints and their deposit settings, sorted by mint.
+    pub whitelist: Vec<WhitelistEntry>,
+    /// Minimum mint decimals accepted when whitelisting a token.
+    pub min_decimals: u8,
+    /// Maximum mint decimals accepted when whitelisting a token.
+    pub max_decimals: u8,
+    /// A secondary role allowed to add and remove whitelisted tokens.
+    pub whitelist_manager: Pubkey,
+    /// Number of deposits processed so far; each `DepositEvent` carries the next value.
+    pub deposit_seq: u64,
+    /// Whether the owner may deposit mints that are not whitelisted.
+    pub owner_bypass_enabled: bool,
+    /// Seconds a new admin wallet stays pending before it can be applied; 0 applies at once.
+    pub admin_change_delay: i64,
+    /// Admin wallet scheduled by `update_config`, waiting for `apply_admin_wallet`.
+    pub pending_admin_wallet: Option<PendingAdminWallet>,
+    /// Whether Token-2022 mints with a permanent delegate are refused at whitelist time.
+    pub reject_permanent_delegate: bool,
+    /// Whether the whitelist is permanently frozen against additions and removals.
+    pub whitelist_frozen: bool,
+    /// Whether ownership was renounced; every owner or manager instruction then fails.
+    pub immutable: bool,
+    /// Program invoked with a `DepositCallback` after every deposit, if set.
+    pub callback_program: Option<Pubkey>,
+    /// Bitfield of optional features, drawn from `KNOWN_FEATURE_FLAGS`.
+    pub feature_flags: u64,
+}
+
+impl Config {
+    // Space calculation: 32 bytes for owner + 32 bytes for admin_wallet
+    // + 4 bytes for vector length + (MAX_WHITELIST * WhitelistEntry::LEN bytes)
+    // + 1 byte for min_decimals + 1 byte for max_decimals
+    // + 32 bytes for whitelist_manager
+    // + 8 bytes for deposit_seq
+    // + 1 byte for owner_bypass_enabled
+    // + 8 bytes for admin_change_delay
+    // + 1 byte for the option tag + PendingAdminWallet::LEN bytes for pending_admin_wallet
+    // + 1 byte for reject_permanent_delegate + 1 byte for whitelist_frozen
+    // + 1 byte for immutable + 1 byte for the option tag + 32 bytes for callback_program
+    // + 8 bytes for feature_flags
+    pub const LEN: usize = 32 + 32 + 4 + Self::MAX_WHITELIST * WhitelistEntry::LEN + 1 + 1 + 32
+        + 8 + 1 + 8 + 1 + PendingAdminWallet::LEN + 1 + 1 + 1 + 1 + 32 + 8;
+
+    /// Maximum number of whitelisted mints the account has space for.
+    pub const MAX_WHITELIST: usize = 10;
+
+    /// Sanity-checks a deserialized config so truncated or malformed account data is
+    /// rejected with `CorruptConfig` instead of being acted upon.
+    pub fn is_valid(&self) -> bool {
+        self.owner != Pubkey::default()
+            && self.whitelist.len() <= Self::MAX_WHITELIST
+            && self.min_decimals <= self.max_decimals
+            && self.admin_change_delay >= 0
+            && self
+                .whitelist
+                .iter()
+                .all(|entry| entry.min_amount <= entry.max_amount)
+    }
+
+    /// Returns true if `user` may deposit mints that are not whitelisted.
+    pub fn owner_bypass(&self, user: &Pubkey) -> bool {
+        self.owner_bypass_enabled && *user == self.owner
+    }
+
+    /// Returns the role `key` holds for payout-style instructions: the owner, or else the
+    /// admin wallet. Config and whitelist changes still require the owner or manager.
+    pub fn privileged_role(&self, key: &Pubkey) -> Option<PrivilegedRole> {
+        if *key == self.owner {
+            Some(PrivilegedRole::Owner)
+        } else if *key == self.admin_wallet {
+            Some(PrivilegedRole::AdminWallet)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if `key` may run payout-style instructions.
+    pub fn is_privileged(&self, key: &Pubkey) -> bool {
+        self.privileged_role(key).is_some()
+    }
+
+    /// Returns true if `key` may add or remove whitelisted tokens.
+    pub fn can_manage_whitelist(&self, key: &Pubkey) -> bool {
+        *key == self.owner || *key == self.whitelist_manager
+    }
+
+    /// Binary-searches the sorted whitelist for `mint`, returning its index if present
+    /// or the index where it would be inserted otherwise.
+    fn whitelist_position(&self, mint: &Pubkey) -> std::result::Result<usize, usize> {
+        self.whitelist.binary_search_by_key(mint, |entry| entry.mint)
+    }
+
+    /// Returns the whitelist entry for `mint`, if it is whitelisted.
+    pub fn whitelist_entry(&self, mint: &Pubkey) -> Option<&WhitelistEntry> {
+        let index = self.whitelist_position(mint).ok()?;
+        Some(&self.whitelist[index])
+    }
+
+    /// Makes the config permanently immutable. A pending admin wallet is dropped and the
+    /// owner bypass is disabled, since neither could be managed afterwards.
+    pub fn renounce_ownership(&mut self, confirm: bool) -> Result<()> {
+        require!(confirm, CustomError::RenounceNotConfirmed);
+        self.immutable = true;
+        self.pending_admin_wallet = None;
+        self.owner_bypass_enabled = false;
+        Ok(())
+    }
+
+    /// Advances `deposit_seq` and returns the new value, failing instead of wrapping.
+    pub fn next_deposit_seq(&mut self) -> Result<u64> {
+        self.deposit_seq = self
+            .deposit_seq
+            .checked_add(1)
+            .ok_or(CustomError::CounterOverflow)?;
+        Ok(self.deposit_seq)
+    }
+
+    /// Makes `new_admin_wallet` the pending admin wallet and returns the time from which
+    /// it can be applied.
+    pub fn schedule_admin_wallet(&mut self, new_admin_wallet: Pubkey, now: i64) -> i64 {
+        let effective_ts = now.saturating_add(self.admin_change_delay);
+        self.pending_admin_wallet = Some(PendingAdminWallet {
+            wallet: new_admin_wallet,
+            effective_ts,
+        });
+        effective_ts
+    }
+
+    /// Installs the pending admin wallet if its delay has elapsed at `now`, returning the
+    /// wallet it replaced.
+    pub fn apply_admin_wallet(&mut self, now: i64) -> Result<Pubkey> {
+        let pending = self
+            .pending_admin_wallet
+            .as_ref()
+            .ok_or(CustomError::NoPendingAdminWallet)?;
+        require!(now >= pending.effective_ts, CustomError::AdminChangeTimelocked);
+        let old_admin_wallet = self.admin_wallet;
+        self.admin_wallet = pending.wallet;
+        self.pending_admin_wallet = None;
+        Ok(old_admin_wallet)
+    }
+
+    /// Applies `rotate_roles` at time `now`. The whitelist manager changes at once, while a
+    /// new admin wallet is scheduled and only applied at once without a delay. Returns the
+    /// admin wallet's effective time, if one was given.
+    pub fn rotate_roles(
+        &mut self,
+        admin_wallet: Option<Pubkey>,
+        whitelist_manager: Option<Pubkey>,
+        now: i64,
+    ) -> Result<Option<i64>> {
+        if let Some(whitelist_manager) = whitelist_manager {
+            self.whitelist_manager = whitelist_manager;
+        }
+        let Some(admin_wallet) = admin_wallet else {
+            return Ok(None);
+        };
+        let effective_ts = self.schedule_admin_wallet(admin_wallet, now);
+        if self.admin_change_delay == 0 {
+            self.apply_admin_wallet(now)?;
+        }
+        Ok(Some(effective_ts))
+    }
+
+    /// Returns true if every bit of `flag` is set in `feature_flags`.
+    pub fn feature_enabled(&self, flag: u64) -> bool {
+        self.feature_flags & flag == flag
+    }
+
+    /// Replaces `feature_flags`, rejecting bits outside `KNOWN_FEATURE_FLAGS` and
+    /// attempts to clear `FEATURE_AUDIT_LOG`.
+    pub fn set_feature_flags(&mut self, feature_flags: u64) -> Result<()> {
+        require!(
+            feature_flags & !KNOWN_FEATURE_FLAGS == 0,
+            CustomError::UnknownFeatureFlags
+        );
+        require!(
+            !self.feature_enabled(FEATURE_AUDIT_LOG) || feature_flags & FEATURE_AUDIT_LOG != 0,
+            CustomError::AuditLogPermanent
+        );
+        self.feature_flags = feature_flags;
+        Ok(())
+    }
+
+    /// Raises the admin change delay to `delay` seconds. A wallet that is already pending
+    /// keeps its original effective time.
+    pub fn set_admin_change_delay(&mut self, delay: i64) -> Result<()> {
+        require!(delay >= self.admin_change_delay, CustomError::AdminChangeDelayDecrease);
+        self.admin_change_delay = delay;
+        Ok(())
+    }
+
+    /// Returns the entry at `index`, failing if it is past the end of the whitelist.
+    pub fn whitelist_entry_at(&self, index: u16) -> Result<&WhitelistEntry> {
+        self.whitelist
+            .get(usize::from(index))
+            .ok_or_else(|| error!(CustomError::WhitelistIndexOutOfBounds))
+    }
+
+    /// Replaces the whitelist with `mints`, sorted and deduplicated, with default limits.
+    pub fn replace_whitelist(&mut self, mints: &[Pubkey]) -> Result<()> {
+        self.whitelist = Vec::with_capacity(mints.len().min(Self::MAX_WHITELIST));
+        for mint in mints {
+            self.add_to_whitelist(*mint)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts `mint` at its sorted position. Already whitelisted mints are left untouched.
+    pub fn add_to_whitelist(&mut self, mint: Pubkey) -> Result<()> {
+        require!(!self.whitelist_frozen, CustomError::WhitelistFrozen);
+        if let Err(index) = self.whitelist_position(&mint) {
+            require!(
+                self.whitelist.len() < Self::MAX_WHITELIST,
+                CustomError::WhitelistFull
+            );
+            self.whitelist.insert(index, WhitelistEntry::new(mint));
+        }
+        Ok(())
+    }
+
+    /// Checks that `mint` is whitelisted and `amount` is within its deposit limits.
+    /// `decimals` are the mint's, used when the minimum is set in whole tokens.
+    pub fn check_deposit(&self, mint: &Pubkey, amount: u64, decimals: u8) -> Result<()> {
+        let entry = self
+            .whitelist_entry(mint)
+            .ok_or(CustomError::TokenNotWhitelisted)?;
+        require!(
+            amount >= entry.raw_min_amount(decimals),
+            CustomError::DepositBelowMinimum
+        );
+        require!(amount <= entry.max_amount, CustomError::DepositAboveMaximum);
+        Ok(())
+    }
+
+    /// Opts the whitelisted `mint` into minting receipts on deposit.
+    pub fn enable_receipts(&mut self, mint: &Pubkey) -> Result<()> {
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        self.whitelist[index].receipts = true;
+        Ok(())
+    }
+
+    /// Removes `mint` from the whitelist, keeping the remaining entries sorted.
+    /// Returns the index the mint was removed from, if it was present.
+    pub fn remove_from_whitelist(&mut self, mint: &Pubkey) -> Result<Option<usize>> {
+        require!(!self.whitelist_frozen, CustomError::WhitelistFrozen);
+        let Ok(index) = self.whitelist_position(mint) else {
+            return Ok(None);
+        };
+        self.whitelist.remove(index);
+        Ok(Some(index))
+    }
+
+    /// Removes every whitelisted mint in `mints`, keeping the rest sorted, and returns the
+    /// removed mints in whitelist order.
+    pub fn remove_many_from_whitelist(&mut self, mints: &[Pubkey]) -> Result<Vec<Pubkey>> {
+        require!(!self.whitelist_frozen, CustomError::WhitelistFrozen);
+        let mut removed = Vec::new();
+        self.whitelist.retain(|entry| {
+            let remove = mints.contains(&entry.mint);
+            if remove {
+                removed.push(entry.mint);
+            }
+            !remove
+        });
+        Ok(removed)
+    }
+
+    /// Removes the whitelisted mints in `remove`, then adds the new mints in `add`, keeping
+    /// the whitelist sorted. Nothing changes unless every check passes. Returns the mints
+    /// actually added and removed, each sorted.
+    pub fn update_whitelist_batch(
+        &mut self,
+        add: &[Pubkey],
+        remove: &[Pubkey],
+    ) -> Result<(Vec<Pubkey>, Vec<Pubkey>)> {
+        require!(!self.whitelist_frozen, CustomError::WhitelistFrozen);
+        require!(
+            add.len() <= MAX_WHITELIST_BATCH && remove.len() <= MAX_WHITELIST_BATCH,
+            CustomError::WhitelistBatchTooLarge
+        );
+        require!(
+            !add.iter().any(|mint| remove.contains(mint)),
+            CustomError::ConflictingWhitelistBatch
+        );
+
+        let mut added: Vec<Pubkey> = add
+            .iter()
+            .filter(|mint| self.whitelist_position(mint).is_err())
+            .copied()
+            .collect();
+        added.sort();
+        added.dedup();
+        let mut removed: Vec<Pubkey> = remove
+            .iter()
+            .filter(|mint| self.whitelist_position(mint).is_ok())
+            .copied()
+            .collect();
+        removed.sort();
+        removed.dedup();
+        require!(
+            self.whitelist.len() - removed.len() + added.len() <= Self::MAX_WHITELIST,
+            CustomError::WhitelistFull
+        );
+
+        self.whitelist.retain(|entry| !removed.contains(&entry.mint));
+        for mint in &added {
+            self.add_to_whitelist(*mint)?;
+        }
+        Ok((added, removed))
+    }
+
+    /// Restores the sorted, duplicate-free whitelist invariant. For duplicated mints the
+    /// first entry is kept.
+    pub fn sort_whitelist(&mut self) {
+        self.whitelist.sort_by_key(|entry| entry.mint);
+        self.whitelist.dedup_by(|a, b| a.mint == b.mint);
+    }
+
+    /// Sets whether a whitelisted mint's `min_amount` counts whole tokens or raw units.
+    pub fn set_min_in_whole_tokens(&mut self, mint: &Pubkey, whole_tokens: bool) -> Result<()> {
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        self.whitelist[index].min_in_whole_tokens = whole_tokens;
+        Ok(())
+    }
+
+    /// Sets whether a whitelisted mint's deposits are verified by balance growth.
+    pub fn set_balance_delta_mode(&mut self, mint: &Pubkey, enabled: bool) -> Result<()> {
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        self.whitelist[index].balance_delta = enabled;
+        Ok(())
+    }
+
+    /// Checks what a deposit of `mint` actually delivered. Mints in balance-delta mode
+    /// must grow the admin token account by at least their minimum, and by at least 1.
+    pub fn check_received(&self, mint: &Pubkey, received: u64, decimals: u8) -> Result<()> {
+        let Some(entry) = self.whitelist_entry(mint).filter(|entry| entry.balance_delta) else {
+            return Ok(());
+        };
+        require!(
+            received >= entry.raw_min_amount(decimals).max(1),
+            CustomError::InsufficientBalanceDelta
+        );
+        Ok(())
+    }
+
+    /// Sets the per-user deposit cooldown of a whitelisted mint.
+    pub fn set_deposit_cooldown(&mut self, mint: &Pubkey, cooldown_secs: u32) -> Result<()> {
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        self.whitelist[index].cooldown_secs = cooldown_secs;
+        Ok(())
+    }
+
+    /// Sets the epoch length and cap of a whitelisted mint. Deposits already counted in the
+    /// current epoch stay counted against the new cap.
+    pub fn set_epoch_cap(
+        &mut self,
+        mint: &Pubkey,
+        epoch_len_secs: u32,
+        epoch_cap: u64,
+    ) -> Result<()> {
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        let entry = &mut self.whitelist[index];
+        entry.epoch_len_secs = epoch_len_secs;
+        entry.epoch_cap = epoch_cap;
+        Ok(())
+    }
+
+    /// Sets the points a whitelisted mint earns per raw unit deposited, times 10^6.
+    pub fn set_points_rate(&mut self, mint: &Pubkey, points_per_token_e6: u64) -> Result<()> {
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        self.whitelist[index].points_per_token_e6 = points_per_token_e6;
+        Ok(())
+    }
+
+    /// Checks a deposit of `mint` against its epoch cap without counting it. Mints deposited
+    /// through the owner bypass are not whitelisted and have no cap.
+    pub fn check_epoch_cap(&self, mint: &Pubkey, amount: u64, now: i64) -> Result<()> {
+        match self.whitelist_entry(mint) {
+            Some(entry) => entry.check_epoch_cap(amount, now).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a deposit of `mint` against its epoch cap. Mints deposited through the owner
+    /// bypass are not whitelisted and have no cap.
+    pub fn record_epoch_deposit(&mut self, mint: &Pubkey, amount: u64, now: i64) -> Result<()> {
+        match self.whitelist_position(mint) {
+            Ok(index) => self.whitelist[index].record_epoch_deposit(amount, now),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Replaces the deposit limits of a whitelisted mint, rejecting `min_amount > max_amount`.
+    pub fn set_mint_limits(&mut self, mint: &Pubkey, min_amount: u64, max_amount: u64) -> Result<()> {
+        require!(min_amount <= max_amount, CustomError::InvalidMintLimits);
+        let index = self
+            .whitelist_position(mint)
+            .map_err(|_| CustomError::TokenNotWhitelisted)?;
+        let entry = &mut self.whitelist[index];
+        entry.min_amount = min_amount;
+        entry.max_amount = max_amount;
+        Ok(())
+    }
+}
+
+/// The original config layout, from before whitelist entries carried deposit settings.
+/// Only read by `migrate_config`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LegacyConfig {
+    pub owner: Pubkey,
+    pub admin_wallet: Pubkey,
+    pub whitelist: Vec<Pubkey>,
+}
+
+impl LegacyConfig {
+    // Space calculation: 32 bytes for owner + 32 bytes for admin_wallet + 4 bytes for vector
+    // length + (max 10 * 32 bytes)
+    pub const LEN: usize = 32 + 32 + 4 + 10 * 32;
+
+    /// Parses config account data in the original layout. Fails with `NotLegacyConfig` for
+    /// any other size or discriminator, which includes already migrated configs.
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() == 8 + Self::LEN
+                && data[..8] == <Config as anchor_lang::Discriminator>::DISCRIMINATOR,
+            CustomError::NotLegacyConfig
+        );
+        Self::deserialize(&mut &data[8..]).map_err(|_| error!(CustomError::CorruptConfig))
+    }
+
+    /// Converts to the current layout, sorting and deduplicating the whitelist and
+    /// defaulting every other setting as `initialize_config` does.
+    pub fn migrate(self) -> Result<Config> {
+        let mut config = Config {
+            owner: self.owner,
+            admin_wallet: self.admin_wallet,
+            whitelist: Vec::new(),
+            min_decimals: 0,
+            max_decimals: u8::MAX,
+            whitelist_manager: self.owner,
+            deposit_seq: 0,
+            owner_bypass_enabled: false,
+            admin_change_delay: 0,
+            pending_admin_wallet: None,
+            reject_permanent_delegate: false,
+            whitelist_frozen: false,
+            immutable: false,
+            callback_program: None,
+            feature_flags: 0,
+        };
+        config.replace_whitelist(&self.whitelist)?;
+        require!(config.is_valid(), CustomError::CorruptConfig);
+        Ok(config)
+    }
+}
+
+/// A whitelisted mint together with its per-deposit limits.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WhitelistEntry {
+    /// The whitelisted token mint.
+    pub mint: Pubkey,
+    /// Smallest amount accepted per deposit, in raw units or, with `min_in_whole_tokens`,
+    /// in whole tokens.
+    pub min_amount: u64,
+    /// Largest amount (in raw units) accepted per deposit.
+    pub max_amount: u64,
+    /// Whether deposits of this mint also mint receipt tokens to the depositor.
+    pub receipts: bool,
+    /// Whether `min_amount` counts whole tokens rather than raw units.
+    pub min_in_whole_tokens: bool,
+    /// Seconds a user must wait between two deposits of this mint; 0 disables the cooldown.
+    pub cooldown_secs: u32,
+    /// Whether the admin token account's balance growth, rather than the requested amount,
+    /// must reach the minimum, for rebasing or elastic-supply mints.
+    pub balance_delta: bool,
+    /// Length in seconds of the epochs `epoch_cap` applies to; 0 disables the cap.
+    pub epoch_len_secs: u32,
+    /// Most raw units that may be deposited per epoch, across all users.
+    pub epoch_cap: u64,
+    /// When the current epoch started; the first deposit after it ends starts the next.
+    pub epoch_start_ts: i64,
+    /// Raw units deposited in the current epoch.
+    pub epoch_used: u64,
+    /// Loyalty points earned per raw unit deposited, times 10^6; 0 disables points.
+    pub points_per_token_e6: u64,
+}
+
+impl WhitelistEntry {
+    // Space calculation: 32 bytes for mint + 8 bytes for min_amount + 8 bytes for max_amount
+    // + 1 byte for receipts + 1 byte for min_in_whole_tokens + 4 bytes for cooldown_secs
+    // + 1 byte for balance_delta + 4 bytes for epoch_len_secs + 8 bytes for epoch_cap
+    // + 8 bytes for epoch_start_ts + 8 bytes for epoch_used + 8 bytes for points_per_token_e6
+    pub const LEN: usize = 32 + 8 + 8 + 1 + 1 + 4 + 1 + 4 + 8 + 8 + 8 + 8;
+
+    /// Returns the minimum deposit in raw units for a mint with `decimals`.
+    /// A whole-token minimum too large for `u64` saturates, rejecting every deposit.
+    pub fn raw_min_amount(&self, decimals: u8) -> u64 {
+        if !self.min_in_whole_tokens {
+            return self.min_amount;
+        }
+        self.min_amount.saturating_mul(10u64.saturating_pow(u32::from(decimals)))
+    }
+
+    /// Creates an entry for `mint` with no deposit limits.
+    pub fn new(mint: Pubkey) -> Self {
+        Self {
+            mint,
+            min_amount: 0,
+            max_amount: u64::MAX,
+            receipts: false,
+            min_in_whole_tokens: false,
+            cooldown_secs: 0,
+            balance_delta: false,
+            epoch_len_secs: 0,
+            epoch_cap: 0,
+            epoch_start_ts: 0,
+            epoch_used: 0,
+            points_per_token_e6: 0,
+        }
+    }
+
+    /// Checks a deposit of `amount` at `now` against the epoch cap, where a new epoch starts
+    /// at `now` if the current one has ended. Returns the epoch's start and usage after the
+    /// deposit, and fails if the cap would be exceeded.
+    pub fn check_epoch_cap(&self, amount: u64, now: i64) -> Result<(i64, u64)> {
+        if self.epoch_len_secs == 0 {
+            return Ok((self.epoch_start_ts, self.epoch_used));
+        }
+        let (epoch_start_ts, epoch_used) =
+            if now >= self.epoch_start_ts.saturating_add(i64::from(self.epoch_len_secs)) {
+                (now, 0)
+            } else {
+                (self.epoch_start_ts, self.epoch_used)
+            };
+        let epoch_used = epoch_used
+            .checked_add(amount)
+            .filter(|used| *used <= self.epoch_cap)
+            .ok_or(CustomError::EpochCapExceeded)?;
+        Ok((epoch_start_ts, epoch_used))
+    }
+
+    /// Counts a deposit of `amount` at `now` against the epoch cap, as checked by
+    /// `check_epoch_cap`.
+    pub fn record_epoch_deposit(&mut self, amount: u64, now: i64) -> Result<()> {
+        (self.epoch_start_ts, self.epoch_used) = self.check_epoch_cap(amount, now)?;
+        Ok(())
+    }
+}
+
+/// The role a signer of a payout-style instruction acted in.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivilegedRole {
+    Owner,
+    AdminWallet,
+}
+
+/// An admin wallet change waiting for its timelock to elapse.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingAdminWallet {
+    /// The wallet that will receive deposits once applied.
+    pub wallet: Pubkey,
+    /// Unix timestamp from which `apply_admin_wallet` succeeds.
+    pub effective_ts: i64,
+}
+
+impl PendingAdminWallet {
+    // Space calculation: 32 bytes for wallet + 8 bytes for effective_ts
+    pub const LEN: usize = 32 + 8;
+}
+
+#[account]
+pub struct DepositCommitment {
+    /// The user who committed and must reveal.
+    pub user: Pubkey,
+    /// `commitment_hash` of the hidden amount and nonce.
+    pub commitment: [u8; 32],
+    /// Unix timestamp after which the commitment can no longer be revealed.
+    pub expires_at: i64,
+}
+
+impl DepositCommitment {
+    // Space calculation: 32 bytes for user + 32 bytes for commitment + 8 bytes for expires_at
+    pub const LEN: usize = 32 + 32 + 8;
+
+    /// Checks that `amount` and `nonce` open this commitment at time `now`.
+    pub fn verify(&self, amount: u64, nonce: &[u8; 32], now: i64) -> Result<()> {
+        require!(now <= self.expires_at, CustomError::CommitmentExpired);
+        require!(
+            commitment_hash(amount, nonce, &self.user) == self.commitment,
+            CustomError::CommitmentMismatch
+        );
+        Ok(())
+    }
+}
+
+/// A deposit the user authorizes off-chain for `relayed_deposit`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositIntent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    /// Must equal the user's `RelayNonce` when the intent is submitted.
+    pub nonce: u64,
+    /// Unix timestamp after which the intent can no longer be submitted.
+    pub deadline: i64,
+    /// Written with the SPL Memo program and repeated in `DepositEvent` if not empty.
+    pub memo: String,
+}
+
+impl DepositIntent {
+    /// Returns the bytes the user signs: `DEPOSIT_INTENT_DOMAIN` followed by the
+    /// Borsh-serialized intent.
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = DEPOSIT_INTENT_DOMAIN.to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+/// The next nonce a user's relayed deposit intents must carry.
+#[account]
+pub struct RelayNonce {
+    pub next_nonce: u64,
+}
+
+impl RelayNonce {
+    // Space calculation: 8 bytes for next_nonce
+    pub const LEN: usize = 8;
+
+    /// Accepts `nonce` if it is the next one and advances past it, so every intent is
+    /// used at most once and in order.
+    pub fn consume(&mut self, nonce: u64) -> Result<()> {
+        require!(nonce == self.next_nonce, CustomError::InvalidNonce);
+        self.next_nonce = nonce.checked_add(1).ok_or(CustomError::CounterOverflow)?;
+        Ok(())
+    }
+}
+
+/// Running deposit totals of one config, created by `init_global_stats`. The counters
+/// only ever grow.
+#[account]
+#[derive(Debug, PartialEq, Eq)]
+pub struct GlobalStats {
+    pub config: Pubkey,
+    /// Deposits made since the account was created.
+    pub total_deposits: u64,
+    /// Sum of those deposits in raw units, across all mints and their differing decimals.
+    pub total_volume: u128,
+}
+
+impl GlobalStats {
+    // Space calculation: 32 bytes for config + 8 bytes for total_deposits
+    // + 16 bytes for total_volume
+    pub const LEN: usize = 32 + 8 + 16;
+
+    /// Counts a deposit of `amount`, failing instead of wrapping.
+    pub fn record_deposit(&mut self, amount: u64) -> Result<()> {
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(1)
+            .ok_or(CustomError::CounterOverflow)?;
+        self.total_volume = self
+            .total_volume
+            .checked_add(u128::from(amount))
+            .ok_or(CustomError::CounterOverflow)?;
+        Ok(())
+    }
+}
+
+/// On-chain proof of a single deposit, created on request by `deposit_token`.
+#[account]
+pub struct DepositReceipt {
+    pub config: Pubkey,
+    /// The depositor, who paid the rent and may close the receipt.
+    pub user: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    /// The deposit's `deposit_seq`, also part of the receipt's address.
+    pub deposit_seq: u64,
+    pub timestamp: i64,
+}
+
+impl DepositReceipt {
+    // Space calculation: 32 bytes for config + 32 bytes for user + 32 bytes for token_mint
+    // + 8 bytes for amount + 8 bytes for deposit_seq + 8 bytes for timestamp
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8;
+}
+
+/// Privileged actions recorded in the `AuditLog`, stored as their `u8` discriminant.
+/// New variants are only ever appended so stored entries keep their meaning.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AuditAction {
+    UpdateConfig,
+    ApplyAdminWallet,
+    SetAdminChangeDelay,
+    SetDecimalsBounds,
+    SetWhitelistManager,
+    RotateRoles,
+    SetRejectPermanentDelegate,
+    SetCallbackProgram,
+    SetFeatureFlags,
+    SetOwnerBypass,
+    FreezeWhitelist,
+    RenounceOwnership,
+    AddWhitelistedToken,
+    RemoveWhitelistedToken,
+    RemoveWhitelistedTokensBatch,
+    UpdateWhitelistBatch,
+    SortWhitelist,
+    SetMintLimits,
+    SetBalanceDeltaMode,
+    SetDepositCooldown,
+    SetMinInWholeTokens,
+    SetEpochCap,
+    SetPointsRate,
+    ResetPoints,
+    CreateReceiptMint,
+    CollectExcessLamports,
+    InitGlobalStats,
+    InitAuditLog,
+}
+
+/// One privileged config change.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The `AuditAction` performed.
+    pub action: u8,
+    /// The owner or whitelist manager that signed it.
+    pub actor: Pubkey,
+    /// SHA-256 of the Borsh-serialized instruction arguments.
+    pub data_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+impl AuditEntry {
+    // Space calculation: 1 byte for action + 32 bytes for actor + 32 bytes for data_hash
+    // + 8 bytes for timestamp
+    pub const LEN: usize = 1 + 32 + 32 + 8;
+}
+
+/// The last `AuditLog::CAPACITY` privileged config changes, created by `init_audit_log`.
+/// Its space is allocated up front; once full, each entry overwrites the oldest one.
+#[account]
+pub struct AuditLog {
+    pub config: Pubkey,
+    /// Slot the next entry is written to; once full, also the slot of the oldest entry.
+    pub next: u8,
+    pub entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Entries kept before the oldest is overwritten.
+    pub const CAPACITY: usize = 32;
+
+    // Space calculation: 32 bytes for config + 1 byte for next
+    // + 4 bytes for the entries length + CAPACITY entries
+    pub const LEN: usize = 32 + 1 + 4 + Self::CAPACITY * AuditEntry::LEN;
+
+    /// Appends `entry`, overwriting the oldest entry once the log is full.
+    pub fn append(&mut self, entry: AuditEntry) {
+        let slot = usize::from(self.next);
+        if slot == self.entries.len() {
+            self.entries.push(entry);
+        } else {
+            self.entries[slot] = entry;
+        }
+        self.next = ((slot + 1) % Self::CAPACITY) as u8;
+    }
+
+    /// Returns the entries oldest first.
+    pub fn chronological(&self) -> impl Iterator<Item = &AuditEntry> {
+        let (newer, older) = self.entries.split_at(usize::from(self.next).min(self.entries.len()));
+        older.iter().chain(newer)
+    }
+
+    /// Returns up to `AUDIT_LOG_PAGE` entries oldest first, after skipping `offset`.
+    pub fn page(&self, offset: usize) -> Vec<AuditEntry> {
+        self.chronological().skip(offset).take(AUDIT_LOG_PAGE).cloned().collect()
+    }
+}
+
+/// Loyalty points of one user, created by their first deposit of a mint that earns points.
+/// Points are redeemed off-chain and cleared with `reset_points`.
+#[account]
+pub struct UserStats {
+    pub points: u64,
+}
+
+impl UserStats {
+    // Space calculation: 8 bytes for points
+    pub const LEN: usize = 8;
+}
+
+/// When a user last deposited a mint with a cooldown.
+#[account]
+pub struct DepositCooldown {
+    pub last_deposit_ts: i64,
+}
+
+impl DepositCooldown {
+    // Space calculation: 8 bytes for last_deposit_ts
+    pub const LEN: usize = 8;
+
+    /// Returns the seconds left at `now` before another deposit is allowed, or 0.
+    /// A freshly created account has never deposited and is never in cooldown.
+    pub fn remaining(&self, cooldown_secs: u32, now: i64) -> i64 {
+        if self.last_deposit_ts == 0 {
+            return 0;
+        }
+        self.last_deposit_ts
+            .saturating_add(i64::from(cooldown_secs))
+            .saturating_sub(now)
+            .max(0)
+    }
+}
+
+/// Deposit details passed to the configured callback program after
+/// `DEPOSIT_CALLBACK_DISCRIMINATOR`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositCallback {
+    pub config: Pubkey,
+    /// The signer the tokens came from.
+    pub user: Pubkey,
+    /// The party the deposit is credited to.
+    pub beneficiary: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub deposit_seq: u64,
+}
+
+/// Return data of `version_info`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// The program crate's semantic version, e.g. "0.1.11".
+    pub crate_version: String,
+    /// `PROGRAM_VERSION`, as stamped into events.
+    pub program_version: u16,
+    /// Feature bits this build knows about, i.e. `KNOWN_FEATURE_FLAGS`.
+    pub known_feature_flags: u64,
+    /// Feature bits currently enabled in the config.
+    pub feature_flags: u64,
+}
+
+impl VersionInfo {
+    /// Describes this build running against `config`.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            program_version: PROGRAM_VERSION,
+            known_feature_flags: KNOWN_FEATURE_FLAGS,
+            feature_flags: config.feature_flags,
+        }
+    }
+}
+
+/// Return data of `simulate_deposit`. Deposits are currently fee-free, so the
+/// whole amount is forwarded.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SimulateDepositResult {
+    pub net_amount: u64,
+    pub fee_amount: u64,
+}
+
+/// Return data of `validate_deposit`. `error_code` is the failing check's error number,
+/// or 0 when `ok`. Deposits are currently fee-free.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositValidation {
+    pub ok: bool,
+    pub error_code: u32,
+    pub fee_amount: u64,
+    pub net_amount: u64,
+}
+
+impl DepositValidation {
+    /// Builds the result of validating a deposit of `amount`.
+    pub fn from_result(result: Result<()>, amount: u64) -> Self {
+        match result {
+            Ok(()) => Self {
+                ok: true,
+                error_code: 0,
+                fee_amount: 0,
+                net_amount: amount,
+            },
+            Err(err) => Self {
+                ok: false,
+                error_code: match err {
+                    Error::AnchorError(err) => err.error_code_number,
+                    Error::ProgramError(err) => u64::from(err.program_error) as u32,
+                },
+                fee_amount: 0,
+                net_amount: 0,
+            },
+        }
+    }
+}
+
+#[event]
+pub struct DepositEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config_owner: Pubkey, // Make sure to keep a check for config in the indexer
+    pub user: Pubkey,
+    pub beneficiary: Pubkey,
+    /// The amount requested from the user.
+    pub amount: u64,
+    /// The amount the admin token account actually gained, measured around the transfer.
+    pub received: u64,
+    /// The amount as wallets display it, times 10^9. Includes accrued interest for
+    /// interest-bearing mints and saturates at `u64::MAX`.
+    pub ui_amount_e9: u64,
+    pub token_mint: Pubkey,
+    /// `Clock::unix_timestamp` of the deposit.
+    pub timestamp: i64,
+    pub deposit_seq: u64,
+    /// True if the owner deposited a mint that is not whitelisted.
+    pub owner_bypass: bool,
+    /// The memo written with the deposit; empty if none was given.
+    pub memo: String,
+    /// For deposits made through CPI, the program of the top-level transaction instruction,
+    /// which with nested CPIs is not necessarily the immediate caller; `None` for direct calls.
+    pub via_program: Option<Pubkey>,
+    /// The `DepositReceipt` account created for the deposit, if one was requested.
+    pub receipt: Option<Pubkey>,
+    /// Slot the deposit landed in.
+    pub slot: u64,
+    /// Decimals of `token_mint`.
+    pub decimals: u8,
+    /// The token program that moved the tokens, telling Token-2022 from legacy SPL Token.
+    pub token_program: Pubkey,
+    /// Loyalty points the user earned with the deposit.
+    pub points: u64,
+}
+
+#[event]
+pub struct OwnerBypassUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct AdminWalletChangePendingEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub new_admin_wallet: Pubkey,
+    /// Unix timestamp from which the change can be applied.
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct AdminWalletAppliedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub old_admin_wallet: Pubkey,
+    pub new_admin_wallet: Pubkey,
+}
+
+#[event]
+pub struct AdminAccountsPreparedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub admin_wallet: Pubkey,
+    /// Number of associated token accounts created.
+    pub created: u8,
+    /// Number of pairs whose account already existed.
+    pub skipped: u8,
+}
+
+#[event]
+pub struct AtaCreatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The signer who paid the rent.
+    pub payer: Pubkey,
+    pub token_mint: Pubkey,
+    pub admin_token_account: Pubkey,
+    /// Rent paid for the new account, in lamports.
+    pub rent_lamports: u64,
+}
+
+#[event]
+pub struct RolesRotatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The new admin wallet, if it was rotated.
+    pub admin_wallet: Option<Pubkey>,
+    /// When the new admin wallet takes effect; later than now if it is pending.
+    pub admin_wallet_effective_ts: Option<i64>,
+    /// The new whitelist manager, if it was rotated.
+    pub whitelist_manager: Option<Pubkey>,
+}
+
+#[event]
+pub struct ReceiptBurnedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub underlying_mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct WhitelistTokenRemovedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub token_mint: Pubkey,
+    /// Position the mint held; later entries have shifted down by one.
+    pub index: u8,
+}
+
+#[event]
+pub struct WhitelistTokensRemovedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// Number of mints removed.
+    pub count: u8,
+    /// The removed mints, in the order they held in the whitelist.
+    pub token_mints: Vec<Pubkey>,
+}
+
+#[event]
+pub struct WhitelistReplacedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The new whitelist, sorted by mint.
+    pub mints: Vec<Pubkey>,
+}
+
+#[event]
+pub struct WhitelistFrozenEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The whitelist as frozen, sorted by mint.
+    pub mints: Vec<Pubkey>,
+}
+
+#[event]
+pub struct OwnershipRenouncedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The owner who renounced.
+    pub owner: Pubkey,
+    /// The admin wallet, fixed from now on.
+    pub admin_wallet: Pubkey,
+}
+
+#[event]
+pub struct ExcessLamportsCollectedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The account the lamports were taken from.
+    pub target: Pubkey,
+    /// Lamports sent to `authority`.
+    pub amount: u64,
+    /// The signer, who received the lamports.
+    pub authority: Pubkey,
+    /// The role `authority` signed in.
+    pub role: PrivilegedRole,
+}
+
+#[event]
+pub struct CallbackProgramUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The new callback program; `None` if callbacks were turned off.
+    pub callback_program: Option<Pubkey>,
+}
+
+#[event]
+pub struct FeatureFlagsUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub old_feature_flags: u64,
+    pub feature_flags: u64,
+}
+
+#[event]
+pub struct MintLimitsUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub token_mint: Pubkey,
+    pub min_amount: u64,
+    pub max_amount: u64,
+}
+
+/// Emitted after the `DepositEvent` of a relayed deposit.
+#[event]
+pub struct RelayedDepositEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The user who signed the intent and is credited with the deposit.
+    pub user: Pubkey,
+    /// The signer who submitted the intent.
+    pub relayer: Pubkey,
+    pub nonce: u64,
+    pub deposit_seq: u64,
+}
+
+#[event]
+pub struct WhitelistBatchUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    /// The mints added, sorted; mints that were already whitelisted are left out.
+    pub added: Vec<Pubkey>,
+    /// The mints removed, sorted; mints that were not whitelisted are left out.
+    pub removed: Vec<Pubkey>,
+}
+
+#[event]
+pub struct BalanceDeltaModeUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub token_mint: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct DepositCooldownUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub token_mint: Pubkey,
+    pub cooldown_secs: u32,
+}
+
+#[event]
+pub struct PointsRateUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub token_mint: Pubkey,
+    pub points_per_token_e6: u64,
+}
+
+#[event]
+pub struct PointsResetEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub user: Pubkey,
+    /// The points cleared, i.e. the amount redeemed off-chain.
+    pub points: u64,
+}
+
+#[event]
+pub struct EpochCapUpdatedEvent {
+    pub version: u16,
+    pub schema: u8,
+    pub config: Pubkey,
+    pub token_mint: Pubkey,
+    pub epoch_len_secs: u32,
+    pub epoch_cap: u64,
+}
+
+#[error_code]
+pub enum CustomError {
+    #[msg("The token provided is not whitelisted for deposit.")]
+    TokenNotWhitelisted,
+    #[msg("The token's decimals are outside the configured bounds.")]
+    DecimalsOutOfRange,
+    #[msg("The minimum decimals must not exceed the maximum decimals.")]
+    InvalidDecimalsBounds,
+    #[msg("The signer is not authorized to perform this action.")]
+    Unauthorized,
+    #[msg("The minimum deposit amount must not exceed the maximum.")]
+    InvalidMintLimits,
+    #[msg("The deposit amount is below the token's minimum.")]
+    DepositBelowMinimum,
+    #[msg("The deposit amount is above the token's maximum.")]
+    DepositAboveMaximum,
+    #[msg("The beneficiary must be a valid, non-default address.")]
+    InvalidBeneficiary,
+    #[msg("The whitelist is full.")]
+    WhitelistFull,
+    #[msg("The config account data is corrupt.")]
+    CorruptConfig,
+    #[msg("This mint mints receipts; the receipt mint and receipt account are required.")]
+    ReceiptAccountsMissing,
+    #[msg("The receipt mint does not belong to this config and token mint.")]
+    InvalidReceiptMint,
+    #[msg("The whitelist index is out of bounds.")]
+    WhitelistIndexOutOfBounds,
+    #[msg("The user's token balance does not cover the deposit.")]
+    InsufficientBalance,
+    #[msg("The admin wallet's associated token account does not exist.")]
+    AdminTokenAccountMissing,
+    #[msg("A cumulative counter would overflow.")]
+    CounterOverflow,
+    #[msg("The revealed amount and nonce do not match the commitment.")]
+    CommitmentMismatch,
+    #[msg("The deposit commitment has expired.")]
+    CommitmentExpired,
+    #[msg("There is no pending admin wallet to apply.")]
+    NoPendingAdminWallet,
+    #[msg("The pending admin wallet's delay has not elapsed yet.")]
+    AdminChangeTimelocked,
+    #[msg("The admin change delay can only be raised.")]
+    AdminChangeDelayDecrease,
+    #[msg("Expected (mint, admin ATA) pairs for mints of the given token program.")]
+    InvalidAdminAccountPairs,
+    #[msg("The mint is non-transferable and cannot be whitelisted.")]
+    MintNotTransferable,
+    #[msg("The mint has a permanent delegate and cannot be whitelisted.")]
+    MintHasPermanentDelegate,
+    #[msg("The memo is longer than MAX_MEMO_LEN bytes.")]
+    MemoTooLong,
+    #[msg("A memo was given but the memo program account is missing.")]
+    MemoProgramMissing,
+    #[msg("The whitelist is frozen and can no longer be changed.")]
+    WhitelistFrozen,
+    #[msg("Ownership was renounced; the config can no longer be changed.")]
+    ConfigImmutable,
+    #[msg("Renouncing ownership must be confirmed.")]
+    RenounceNotConfirmed,
+    #[msg("The account is not owned by this program.")]
+    NotProgramAccount,
+    #[msg("The configured callback program was not passed in the remaining accounts.")]
+    CallbackProgramMissing,
+    #[msg("Feature flags contain bits that are not assigned to any feature.")]
+    UnknownFeatureFlags,
+    #[msg("Deposit statistics are enabled but the global stats account is missing.")]
+    GlobalStatsMissing,
+    #[msg("The admin token account balance dropped during the deposit.")]
+    AdminBalanceDecreased,
+    #[msg("Deposits through CPI are disabled for this config.")]
+    CpiDepositsDisabled,
+    #[msg("Deposits through CPI must pass the instructions sysvar.")]
+    InstructionsSysvarMissing,
+    #[msg("The previous deposit of this token is still in its cooldown.")]
+    CooldownActive,
+    #[msg("Tokens with a deposit cooldown require the deposit_cooldown account.")]
+    DepositCooldownMissing,
+    #[msg("A whitelist batch holds too many mints.")]
+    WhitelistBatchTooLarge,
+    #[msg("A mint cannot be both added and removed in the same batch.")]
+    ConflictingWhitelistBatch,
+    #[msg("Every mint being added must be passed as a remaining account.")]
+    MintAccountMissing,
+    #[msg("The deposit intent is not signed by the user in a preceding Ed25519 instruction.")]
+    InvalidIntentSignature,
+    #[msg("The deposit intent's deadline has passed.")]
+    IntentExpired,
+    #[msg("The deposit intent's nonce is not the user's next nonce.")]
+    InvalidNonce,
+    #[msg("The admin token account grew by less than the token's minimum.")]
+    InsufficientBalanceDelta,
+    #[msg("The audit log is enabled but the audit_log account is missing.")]
+    AuditLogMissing,
+    #[msg("The audit log feature cannot be turned off once enabled.")]
+    AuditLogPermanent,
+    #[msg("The deposit exceeds the token's cap for the current epoch.")]
+    EpochCapExceeded,
+    #[msg("Tokens that earn points require the user_stats account.")]
+    UserStatsMissing,
+    #[msg("The account is not a config in the original layout.")]
+    NotLegacyConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(whitelist: &[Pubkey]) -> Config {
+        let mut whitelist: Vec<WhitelistEntry> =
+            whitelist.iter().copied().map(WhitelistEntry::new).collect();
+        whitelist.sort_by_key(|entry| entry.mint);
+        Config {
+            owner: Pubkey::new_unique(),
+            admin_wallet: Pubkey::new_unique(),
+            whitelist,
+            min_decimals: 0,
+            max_decimals: u8::MAX,
+            whitelist_manager: Pubkey::default(),
+            deposit_seq: 0,
+            owner_bypass_enabled: false,
+            admin_change_delay: 0,
+            pending_admin_wallet: None,
+            reject_permanent_delegate: false,
+            whitelist_frozen: false,
+            immutable: false,
+            callback_program: None,
+            feature_flags: 0,
+        }
+    }
+
+    #[test]
+    fn program_version_matches_crate_version() {
+        let parts: Vec<u16> = env!("CARGO_PKG_VERSION")
+            .split('.')
+            .map(|part| part.parse().unwrap())
+            .collect();
+        assert_eq!(PROGRAM_VERSION, parts[0] * 10_000 + parts[1] * 100 + parts[2]);
+    }
+
+    #[test]
+    fn version_info_reports_crate_version_and_features() {
+        let mut config = test_config(&[]);
+        config.feature_flags = FEATURE_GLOBAL_STATS;
+        let info = VersionInfo::new(&config);
+        assert_eq!(info.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.program_version, PROGRAM_VERSION);
+        assert_eq!(info.known_feature_flags, KNOWN_FEATURE_FLAGS);
+        assert_eq!(info.feature_flags, FEATURE_GLOBAL_STATS);
+    }
+
+    #[test]
+    fn events_carry_schema_version_after_program_version() {
+        let deposit = DepositEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config_owner: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            beneficiary: Pubkey::new_unique(),
+            amount: 1,
+            received: 1,
+            ui_amount_e9: 1,
+            token_mint: Pubkey::new_unique(),
+            timestamp: 0,
+            deposit_seq: 1,
+            owner_bypass: false,
+            memo: "invoice 42".to_string(),
+            via_program: None,
+            receipt: None,
+            slot: 7,
+            decimals: 6,
+            token_program: anchor_spl::token::ID,
+            points: 0,
+        };
+        let limits = MintLimitsUpdatedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            min_amount: 0,
+            max_amount: u64::MAX,
+        };
+
+        // Indexers read the schema byte at a fixed offset, before any event-specific field.
+        for data in [deposit.try_to_vec().unwrap(), limits.try_to_vec().unwrap()] {
+            assert_eq!(data[..2], PROGRAM_VERSION.to_le_bytes());
+            assert_eq!(data[2], EVENT_SCHEMA_VERSION);
+        }
+    }
+
+    #[test]
+    fn removal_preserves_order_and_reports_index() {
+        let mut config = test_config(&(0..4).map(|_| Pubkey::new_unique()).collect::<Vec<_>>());
+        let before: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+
+        let index = config.remove_from_whitelist(&before[1]).unwrap().unwrap();
+        assert_eq!(index, 1);
+        let after: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+        assert_eq!(after, [before[0], before[2], before[3]]);
+
+        let event = WhitelistTokenRemovedEvent {
+            version: PROGRAM_VERSION,
+            schema: EVENT_SCHEMA_VERSION,
+            config: Pubkey::new_unique(),
+            token_mint: before[1],
+            index: index as u8,
+        };
+        let data = event.try_to_vec().unwrap();
+        assert_eq!(data[3 + 32..3 + 64], before[1].to_bytes());
+        assert_eq!(data[3 + 64], 1);
+    }
+
+    #[test]
+    fn batch_removal_keeps_other_entries_sorted() {
+        let mut config = test_config(&(0..5).map(|_| Pubkey::new_unique()).collect::<Vec<_>>());
+        let before: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+
+        let absent = Pubkey::new_unique();
+        let removed = config
+            .remove_many_from_whitelist(&[before[3], absent, before[0], before[3]])
+            .unwrap();
+        assert_eq!(removed, [before[0], before[3]]);
+        let after: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+        assert_eq!(after, [before[1], before[2], before[4]]);
+
+        config.whitelist_frozen = true;
+        assert_eq!(
+            config.remove_many_from_whitelist(&[before[1]]).unwrap_err(),
+            CustomError::WhitelistFrozen.into()
+        );
+    }
+
+    #[test]
+    fn whitelist_batch_applies_additions_and_removals_atomically() {
+        let existing: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut config = test_config(&existing);
+        let before = config.whitelist.clone();
+        let (new_a, new_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let err = config.update_whitelist_batch(&[new_a, existing[0]], &[existing[0]]);
+        assert_eq!(err.unwrap_err(), CustomError::ConflictingWhitelistBatch.into());
+        let too_many = vec![Pubkey::new_unique(); MAX_WHITELIST_BATCH + 1];
+        let err = config.update_whitelist_batch(&too_many, &[]);
+        assert_eq!(err.unwrap_err(), CustomError::WhitelistBatchTooLarge.into());
+        // Three existing mints plus a full batch exceed `MAX_WHITELIST`.
+        let fill: Vec<Pubkey> = (0..MAX_WHITELIST_BATCH).map(|_| Pubkey::new_unique()).collect();
+        let err = config.update_whitelist_batch(&fill, &[]);
+        assert_eq!(err.unwrap_err(), CustomError::WhitelistFull.into());
+        assert_eq!(config.whitelist, before);
+
+        // Duplicates, present additions and absent removals are dropped from the result.
+        let (added, removed) = config
+            .update_whitelist_batch(
+                &[new_b, new_a, new_b, existing[2]],
+                &[existing[1], Pubkey::new_unique(), existing[1]],
+            )
+            .unwrap();
+        let mut expected_added = vec![new_a, new_b];
+        expected_added.sort();
+        assert_eq!(added, expected_added);
+        assert_eq!(removed, [existing[1]]);
+        let mints: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+        let mut expected = vec![existing[0], existing[2], new_a, new_b];
+        expected.sort();
+        assert_eq!(mints, expected);
+
+        config.whitelist_frozen = true;
+        let err = config.update_whitelist_batch(&[], &[existing[0]]);
+        assert_eq!(err.unwrap_err(), CustomError::WhitelistFrozen.into());
+    }
+
+    /// Builds Ed25519 program instruction data for one signature, laid out like the
+    /// Solana SDK does: offsets, then public key, signature and message.
+    fn ed25519_instruction(signer: &Pubkey, message: &[u8]) -> Instruction {
+        let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+        let mut data = vec![1, 0];
+        for value in [
+            signature_offset,
+            u16::MAX,
+            public_key_offset,
+            u16::MAX,
+            message_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[7; 64]);
+        data.extend_from_slice(message);
+        Instruction {
+            program_id: anchor_lang::solana_program::ed25519_program::ID,
+            accounts: vec![],
+            data,
+        }
+    }
+
+    #[test]
+    fn relayed_intent_must_be_signed_by_the_user() {
+        let user = Pubkey::new_unique();
+        let intent = DepositIntent {
+            config: Pubkey::new_unique(),
+            user,
+            token_mint: Pubkey::new_unique(),
+            amount: 1_000,
+            nonce: 0,
+            deadline: 1_700_000_000,
+            memo: String::new(),
+        };
+        let message = intent.message().unwrap();
+        assert!(message.starts_with(DEPOSIT_INTENT_DOMAIN));
+        check_ed25519_instruction(&ed25519_instruction(&user, &message), &user, &message)
+            .unwrap();
+
+        let invalid = CustomError::InvalidIntentSignature.into();
+        let other = Pubkey::new_unique();
+        let other_signer = ed25519_instruction(&other, &message);
+        assert_eq!(check_ed25519_instruction(&other_signer, &user, &message).unwrap_err(), invalid);
+        let other_intent = DepositIntent { amount: 2_000, ..intent }.message().unwrap();
+        let other_message = ed25519_instruction(&user, &other_intent);
+        assert_eq!(
+            check_ed25519_instruction(&other_message, &user, &message).unwrap_err(),
+            invalid
+        );
+        let mut other_program = ed25519_instruction(&user, &message);
+        other_program.program_id = Pubkey::new_unique();
+        assert_eq!(
+            check_ed25519_instruction(&other_program, &user, &message).unwrap_err(),
+            invalid
+        );
+        // Offsets pointing into another instruction could reference data the user never signed.
+        let mut elsewhere = ed25519_instruction(&user, &message);
+        elsewhere.data[8..10].copy_from_slice(&0u16.to_le_bytes());
+        assert_eq!(check_ed25519_instruction(&elsewhere, &user, &message).unwrap_err(), invalid);
+        let mut truncated = ed25519_instruction(&user, &message);
+        truncated.data.truncate(100);
+        assert_eq!(check_ed25519_instruction(&truncated, &user, &message).unwrap_err(), invalid);
+    }
+
+    #[test]
+    fn relay_nonce_rejects_reuse() {
+        let mut relay_nonce = RelayNonce { next_nonce: 0 };
+        relay_nonce.consume(0).unwrap();
+        assert_eq!(relay_nonce.consume(0).unwrap_err(), CustomError::InvalidNonce.into());
+        assert_eq!(relay_nonce.consume(5).unwrap_err(), CustomError::InvalidNonce.into());
+        relay_nonce.consume(1).unwrap();
+        assert_eq!(relay_nonce.next_nonce, 2);
+
+        relay_nonce.next_nonce = u64::MAX;
+        assert_eq!(
+            relay_nonce.consume(u64::MAX).unwrap_err(),
+            CustomError::CounterOverflow.into()
+        );
+    }
+
+    #[test]
+    fn whitelist_entry_at_checks_bounds() {
+        let mut config = test_config(&[Pubkey::new_unique(), Pubkey::new_unique()]);
+        let last = config.whitelist[1].mint;
+        config.set_mint_limits(&last, 5, 10).unwrap();
+
+        let entry = config.whitelist_entry_at(1).unwrap();
+        assert_eq!((entry.mint, entry.min_amount, entry.max_amount), (last, 5, 10));
+        assert_eq!(
+            config.whitelist_entry_at(2).unwrap_err(),
+            CustomError::WhitelistIndexOutOfBounds.into()
+        );
+    }
+
+    #[test]
+    fn admin_wallet_may_be_another_programs_pda() {
+        let (vault, _) = Pubkey::find_program_address(&[b"vault"], &Pubkey::new_unique());
+        assert!(!vault.is_on_curve());
+
+        let mut config = test_config(&[]);
+        config.admin_wallet = vault;
+        assert!(config.is_valid());
+
+        // The DepositToken constraint derives the ATA the same way for off-curve owners.
+        let mint = Pubkey::new_unique();
+        for token_program in [anchor_spl::token::ID, anchor_spl::token_2022::ID] {
+            let (expected, _) = Pubkey::find_program_address(
+                &[vault.as_ref(), token_program.as_ref(), mint.as_ref()],
+                &anchor_spl::associated_token::ID,
+            );
+            assert_eq!(
+                anchor_spl::associated_token::get_associated_token_address_with_program_id(
+                    &config.admin_wallet,
+                    &mint,
+                    &token_program,
+                ),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn admin_account_pairs_are_validated() {
+        let mint = Pubkey::new_unique();
+        let config = test_config(&[mint]);
+        let token_program = anchor_spl::token::ID;
+        let ata = anchor_spl::associated_token::get_associated_token_address_with_program_id(
+            &config.admin_wallet,
+            &mint,
+            &token_program,
+        );
+        check_admin_account_pair(&config, &mint, &token_program, &ata, &token_program).unwrap();
+
+        let unlisted = Pubkey::new_unique();
+        assert_eq!(
+            check_admin_account_pair(&config, &unlisted, &token_program, &ata, &token_program)
+                .unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+        // A Token-2022 mint cannot be prepared in a legacy Token call.
+        let err = check_admin_account_pair(
+            &config,
+            &mint,
+            &anchor_spl::token_2022::ID,
+            &ata,
+            &token_program,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("InvalidAdminAccountPairs"));
+        // The ATA must belong to the admin wallet.
+        let other_ata = anchor_spl::associated_token::get_associated_token_address_with_program_id(
+            &Pubkey::new_unique(),
+            &mint,
+            &token_program,
+        );
+        let err = check_admin_account_pair(&config, &mint, &token_program, &other_ata, &token_program)
+            .unwrap_err();
+        assert!(err.to_string().contains("InvalidAdminAccountPairs"));
+    }
+
+    #[test]
+    fn derived_addresses_match_program_seeds() {
+        let config_key = Pubkey::new_unique();
+        let mut config = test_config(&[]);
+        let (mint, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let token_program = anchor_spl::token_2022::ID;
+        let ata = |owner: &Pubkey, mint: &Pubkey| {
+            Pubkey::find_program_address(
+                &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+                &anchor_spl::associated_token::ID,
+            )
+            .0
+        };
+
+        let derived = derive_addresses(&config_key, &config, &mint, &token_program, &user);
+        let receipt_mint = Pubkey::find_program_address(
+            &[RECEIPT_SEED, config_key.as_ref(), mint.as_ref()],
+            &crate::ID,
+        )
+        .0;
+        assert_eq!(
+            derived,
+            DerivedAddresses {
+                admin_ata: ata(&config.admin_wallet, &mint),
+                pending_admin_ata: None,
+                receipt_mint,
+                user_ata: ata(&user, &mint),
+                user_receipt_ata: ata(&user, &receipt_mint),
+                commitment: Pubkey::find_program_address(
+                    &[COMMITMENT_SEED, config_key.as_ref(), user.as_ref()],
+                    &crate::ID,
+                )
+                .0,
+            }
+        );
+
+        let pending = Pubkey::new_unique();
+        config.set_admin_change_delay(1).unwrap();
+        config.schedule_admin_wallet(pending, 0);
+        let derived = derive_addresses(&config_key, &config, &mint, &token_program, &user);
+        assert_eq!(derived.pending_admin_ata, Some(ata(&pending, &mint)));
+    }
+
+    #[test]
+    fn replace_whitelist_dedupes_and_caps() {
+        let mints: Vec<Pubkey> = (0..Config::MAX_WHITELIST).map(|_| Pubkey::new_unique()).collect();
+        let mut config = test_config(&[Pubkey::new_unique()]);
+
+        let mut seed = mints.clone();
+        seed.push(mints[0]);
+        config.replace_whitelist(&seed).unwrap();
+        assert_eq!(config.whitelist.len(), Config::MAX_WHITELIST);
+        assert!(config.whitelist.windows(2).all(|w| w[0].mint < w[1].mint));
+        assert!(config.is_valid());
+
+        seed.push(Pubkey::new_unique());
+        assert_eq!(
+            config.replace_whitelist(&seed).unwrap_err(),
+            CustomError::WhitelistFull.into()
+        );
+    }
+
+    #[test]
+    fn deposit_validation_reports_failing_check() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.set_mint_limits(&mint, 5, 10).unwrap();
+
+        let user = Pubkey::new_unique();
+        let result = deposit_checks(&config, &user, &mint, 0, 8, 8, None, 0).map(|_| ());
+        let validation = DepositValidation::from_result(result, 8);
+        assert_eq!(
+            validation,
+            DepositValidation { ok: true, error_code: 0, fee_amount: 0, net_amount: 8 }
+        );
+
+        let code = |err: CustomError| u32::from(err);
+        for (amount, balance, expected) in [
+            (4, 100, code(CustomError::DepositBelowMinimum)),
+            (11, 100, code(CustomError::DepositAboveMaximum)),
+            (8, 7, code(CustomError::InsufficientBalance)),
+        ] {
+            let result =
+                deposit_checks(&config, &user, &mint, 0, amount, balance, None, 0).map(|_| ());
+            let validation = DepositValidation::from_result(result, amount);
+            assert!(!validation.ok);
+            assert_eq!(validation.error_code, expected);
+            assert_eq!(validation.net_amount, 0);
+        }
+    }
+
+    #[test]
+    fn empty_whitelist_rejects_all_deposits() {
+        let config = test_config(&[]);
+        let user = Pubkey::new_unique();
+        // Wrapped SOL is this program's only form of native deposit.
+        for mint in [anchor_spl::token::spl_token::native_mint::ID, Pubkey::new_unique()] {
+            assert_eq!(
+                deposit_checks(&config, &user, &mint, 9, 10, 10, None, 0).unwrap_err(),
+                CustomError::TokenNotWhitelisted.into()
+            );
+        }
+        assert_eq!(
+            deposit_checks(&config, &config.owner, &Pubkey::new_unique(), 9, 10, 10, None, 0)
+                .unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+    }
+
+    #[test]
+    fn deposit_seq_overflow_is_an_error() {
+        let mut config = test_config(&[]);
+        config.deposit_seq = u64::MAX - 1;
+
+        assert_eq!(config.next_deposit_seq().unwrap(), u64::MAX);
+        assert_eq!(
+            config.next_deposit_seq().unwrap_err(),
+            CustomError::CounterOverflow.into()
+        );
+        assert_eq!(config.deposit_seq, u64::MAX);
+    }
+
+    #[test]
+    fn feature_flags_only_accept_known_bits() {
+        let mut config = test_config(&[]);
+        assert!(config.feature_enabled(0));
+        assert_eq!(
+            config.set_feature_flags(1 << 63).unwrap_err(),
+            CustomError::UnknownFeatureFlags.into()
+        );
+        config.set_feature_flags(KNOWN_FEATURE_FLAGS).unwrap();
+        assert_eq!(config.feature_flags, KNOWN_FEATURE_FLAGS);
+        assert!(config.feature_enabled(KNOWN_FEATURE_FLAGS));
+    }
+
+    #[test]
+    fn admin_wallet_is_privileged_but_cannot_manage_config() {
+        let config = test_config(&[]);
+        assert_eq!(config.privileged_role(&config.owner), Some(PrivilegedRole::Owner));
+        assert_eq!(
+            config.privileged_role(&config.admin_wallet),
+            Some(PrivilegedRole::AdminWallet)
+        );
+        assert!(!config.is_privileged(&Pubkey::new_unique()));
+
+        // Whitelist changes need the owner or manager, and config changes check
+        // `has_one = owner`, so the admin wallet gains nothing there.
+        assert!(!config.can_manage_whitelist(&config.admin_wallet));
+        assert_ne!(config.admin_wallet, config.owner);
+    }
+
+    #[test]
+    fn global_stats_count_deposits_and_volume() {
+        let mut stats = GlobalStats {
+            config: Pubkey::new_unique(),
+            total_deposits: 0,
+            total_volume: 0,
+        };
+        stats.record_deposit(u64::MAX).unwrap();
+        stats.record_deposit(5).unwrap();
+        assert_eq!(stats.total_deposits, 2);
+        assert_eq!(stats.total_volume, u128::from(u64::MAX) + 5);
+        assert_eq!(stats.try_to_vec().unwrap().len(), GlobalStats::LEN);
+
+        stats.total_deposits = u64::MAX;
+        assert_eq!(stats.record_deposit(1).unwrap_err(), CustomError::CounterOverflow.into());
+    }
+
+    #[test]
+    fn audit_log_wraps_and_reads_oldest_first() {
+        let mut config = test_config(&[]);
+        let mut log = AuditLog {
+            config: Pubkey::new_unique(),
+            next: 0,
+            entries: Vec::new(),
+        };
+        let actor = config.owner;
+        record_audit(&config, None, AuditAction::SetOwnerBypass, actor, &true, 1).unwrap();
+
+        config.feature_flags = FEATURE_AUDIT_LOG;
+        assert_eq!(
+            record_audit(&config, None, AuditAction::SetOwnerBypass, actor, &true, 1).unwrap_err(),
+            CustomError::AuditLogMissing.into()
+        );
+        let total = AuditLog::CAPACITY as i64 + 3;
+        for ts in 0..total {
+            record_audit(&config, Some(&mut log), AuditAction::SetAdminChangeDelay, actor, &ts, ts)
+                .unwrap();
+        }
+        assert_eq!(log.entries.len(), AuditLog::CAPACITY);
+        assert_eq!(log.try_to_vec().unwrap().len(), AuditLog::LEN);
+
+        let timestamps: Vec<i64> = log.chronological().map(|entry| entry.timestamp).collect();
+        assert_eq!(timestamps, (3..total).collect::<Vec<_>>());
+        let newest = log.chronological().last().unwrap();
+        assert_eq!(newest.action, AuditAction::SetAdminChangeDelay as u8);
+        assert_eq!(newest.actor, actor);
+        assert_eq!(newest.data_hash, hashv(&[&(total - 1).to_le_bytes()]).to_bytes());
+
+        let first_page = log.page(0);
+        assert_eq!(first_page.len(), AUDIT_LOG_PAGE);
+        assert_eq!(first_page[0].timestamp, 3);
+        assert_eq!(log.page(AuditLog::CAPACITY - 1).len(), 1);
+        assert!(log.page(AuditLog::CAPACITY).is_empty());
+        assert!(first_page.try_to_vec().unwrap().len() <= 1024);
+    }
+
+    #[test]
+    fn audit_log_feature_cannot_be_cleared() {
+        let mut config = test_config(&[]);
+        config.set_feature_flags(FEATURE_AUDIT_LOG).unwrap();
+        assert_eq!(
+            config.set_feature_flags(FEATURE_GLOBAL_STATS).unwrap_err(),
+            CustomError::AuditLogPermanent.into()
+        );
+        config.set_feature_flags(FEATURE_AUDIT_LOG | FEATURE_GLOBAL_STATS).unwrap();
+    }
+
+    #[test]
+    fn cpi_deposits_can_be_rejected() {
+        let mut config = test_config(&[]);
+        let proxy = Pubkey::new_unique();
+        check_via_program(&config, None).unwrap();
+        check_via_program(&config, Some(proxy)).unwrap();
+
+        config.set_feature_flags(FEATURE_REJECT_CPI_DEPOSITS).unwrap();
+        check_via_program(&config, None).unwrap();
+        assert_eq!(
+            check_via_program(&config, Some(proxy)).unwrap_err(),
+            CustomError::CpiDepositsDisabled.into()
+        );
+    }
+
+    #[test]
+    fn admin_wallet_change_waits_for_delay() {
+        let mut config = test_config(&[]);
+        let old_admin_wallet = config.admin_wallet;
+        let new_admin_wallet = Pubkey::new_unique();
+        config.set_admin_change_delay(100).unwrap();
+        assert_eq!(
+            config.set_admin_change_delay(99).unwrap_err(),
+            CustomError::AdminChangeDelayDecrease.into()
+        );
+        assert_eq!(
+            config.apply_admin_wallet(0).unwrap_err(),
+            CustomError::NoPendingAdminWallet.into()
+        );
+
+        assert_eq!(config.schedule_admin_wallet(new_admin_wallet, 1_000), 1_100);
+        assert_eq!(
+            config.apply_admin_wallet(1_099).unwrap_err(),
+            CustomError::AdminChangeTimelocked.into()
+        );
+        assert_eq!(config.admin_wallet, old_admin_wallet);
+
+        assert_eq!(config.apply_admin_wallet(1_100).unwrap(), old_admin_wallet);
+        assert_eq!(config.admin_wallet, new_admin_wallet);
+        assert_eq!(config.pending_admin_wallet, None);
+    }
+
+    #[test]
+    fn rotate_roles_updates_only_given_roles() {
+        let mut config = test_config(&[]);
+        let (admin_wallet, manager) = (config.admin_wallet, config.whitelist_manager);
+
+        assert_eq!(config.rotate_roles(None, None, 0).unwrap(), None);
+        assert_eq!((config.admin_wallet, config.whitelist_manager), (admin_wallet, manager));
+
+        let new_manager = Pubkey::new_unique();
+        let new_admin_wallet = Pubkey::new_unique();
+        assert_eq!(
+            config.rotate_roles(Some(new_admin_wallet), Some(new_manager), 10).unwrap(),
+            Some(10)
+        );
+        assert_eq!(config.admin_wallet, new_admin_wallet);
+        assert_eq!(config.whitelist_manager, new_manager);
+        assert_eq!(config.pending_admin_wallet, None);
+
+        // With a delay the manager still rotates at once but the admin wallet waits.
+        config.set_admin_change_delay(60).unwrap();
+        let later_admin_wallet = Pubkey::new_unique();
+        assert_eq!(
+            config.rotate_roles(Some(later_admin_wallet), Some(manager), 10).unwrap(),
+            Some(70)
+        );
+        assert_eq!(config.whitelist_manager, manager);
+        assert_eq!(config.admin_wallet, new_admin_wallet);
+        assert_eq!(config.apply_admin_wallet(70).unwrap(), new_admin_wallet);
+        assert_eq!(config.admin_wallet, later_admin_wallet);
+    }
+
+    #[test]
+    fn commitment_opens_only_with_matching_reveal() {
+        let user = Pubkey::new_unique();
+        let nonce = [7u8; 32];
+        let record = DepositCommitment {
+            user,
+            commitment: commitment_hash(500, &nonce, &user),
+            expires_at: 1_000,
+        };
+
+        record.verify(500, &nonce, 1_000).unwrap();
+        for (amount, nonce) in [(501, nonce), (500, [8u8; 32])] {
+            assert_eq!(
+                record.verify(amount, &nonce, 1_000).unwrap_err(),
+                CustomError::CommitmentMismatch.into()
+            );
+        }
+        assert_ne!(commitment_hash(500, &nonce, &Pubkey::new_unique()), record.commitment);
+        assert_eq!(
+            record.verify(500, &nonce, 1_001).unwrap_err(),
+            CustomError::CommitmentExpired.into()
+        );
+    }
+
+    #[test]
+    fn owner_bypass_skips_whitelist_only_for_enabled_owner() {
+        let mint = Pubkey::new_unique();
+        let unlisted = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        let owner = config.owner;
+        let not_whitelisted = CustomError::TokenNotWhitelisted.into();
+
+        assert_eq!(
+            deposit_checks(&config, &owner, &unlisted, 0, 1, 1, None, 0).unwrap_err(),
+            not_whitelisted
+        );
+
+        config.owner_bypass_enabled = true;
+        assert!(deposit_checks(&config, &owner, &unlisted, 0, 1, 1, None, 0).unwrap());
+        assert!(!deposit_checks(&config, &owner, &mint, 0, 1, 1, None, 0).unwrap());
+        assert_eq!(
+            deposit_checks(&config, &Pubkey::new_unique(), &unlisted, 0, 1, 1, None, 0)
+                .unwrap_err(),
+            not_whitelisted
+        );
+        assert_eq!(
+            deposit_checks(&config, &owner, &unlisted, 0, 2, 1, None, 0).unwrap_err(),
+            CustomError::InsufficientBalance.into()
+        );
+    }
+
+    #[test]
+    fn set_mint_limits_rejects_inverted_pair() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+
+        assert_eq!(
+            config.set_mint_limits(&mint, 10, 5).unwrap_err(),
+            CustomError::InvalidMintLimits.into()
+        );
+        let entry = config.whitelist_entry(&mint).unwrap();
+        assert_eq!((entry.min_amount, entry.max_amount), (0, u64::MAX));
+    }
+
+    #[test]
+    fn whole_token_minimum_scales_with_decimals() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.set_mint_limits(&mint, 2, u64::MAX).unwrap();
+        config.check_deposit(&mint, 2, 6).unwrap();
+
+        config.set_min_in_whole_tokens(&mint, true).unwrap();
+        assert_eq!(
+            config.check_deposit(&mint, 1_999_999, 6).unwrap_err(),
+            CustomError::DepositBelowMinimum.into()
+        );
+        config.check_deposit(&mint, 2_000_000, 6).unwrap();
+        config.check_deposit(&mint, 2, 0).unwrap();
+
+        // A threshold beyond u64 saturates instead of overflowing.
+        let entry = config.whitelist_entry(&mint).unwrap();
+        assert_eq!(entry.raw_min_amount(u8::MAX), u64::MAX);
+        assert_eq!(
+            config.set_min_in_whole_tokens(&Pubkey::new_unique(), true).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+    }
+
+    #[test]
+    fn check_deposit_enforces_whitelist_and_limits() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.set_mint_limits(&mint, 5, 10).unwrap();
+
+        config.check_deposit(&mint, 5, 0).unwrap();
+        config.check_deposit(&mint, 10, 0).unwrap();
+        assert_eq!(
+            config.check_deposit(&mint, 4, 0).unwrap_err(),
+            CustomError::DepositBelowMinimum.into()
+        );
+        assert_eq!(
+            config.check_deposit(&mint, 11, 0).unwrap_err(),
+            CustomError::DepositAboveMaximum.into()
+        );
+        assert_eq!(
+            config.check_deposit(&Pubkey::new_unique(), 5, 0).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+    }
+
+    #[test]
+    fn enable_receipts_requires_whitelisted_mint() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        assert!(!config.whitelist_entry(&mint).unwrap().receipts);
+
+        config.enable_receipts(&mint).unwrap();
+        assert!(config.whitelist_entry(&mint).unwrap().receipts);
+        assert_eq!(
+            config.enable_receipts(&Pubkey::new_unique()).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+    }
+
+    #[test]
+    fn deposit_receipt_is_unique_per_deposit_and_fits_its_space() {
+        let config = Pubkey::new_unique();
+        let (first, _) = deposit_receipt_address(&config, 1);
+        assert_ne!(first, deposit_receipt_address(&config, 2).0);
+        assert_ne!(first, deposit_receipt_address(&Pubkey::new_unique(), 1).0);
+
+        let receipt = DepositReceipt {
+            config,
+            user: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            amount: u64::MAX,
+            deposit_seq: 1,
+            timestamp: i64::MAX,
+        };
+        assert_eq!(receipt.try_to_vec().unwrap().len(), DepositReceipt::LEN);
+    }
+
+    #[test]
+    fn receipt_mint_is_unique_per_config_and_mint() {
+        let (config, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (receipt, _) = receipt_mint_address(&config, &mint);
+
+        assert_eq!(receipt_mint_address(&config, &mint).0, receipt);
+        assert_ne!(receipt_mint_address(&Pubkey::new_unique(), &mint).0, receipt);
+        assert_ne!(receipt_mint_address(&config, &Pubkey::new_unique()).0, receipt);
+    }
+
+    #[test]
+    fn set_mint_limits_updates_both_bounds() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+
+        config.set_mint_limits(&mint, 5, 10).unwrap();
+        let entry = config.whitelist_entry(&mint).unwrap();
+        assert_eq!((entry.min_amount, entry.max_amount), (5, 10));
+
+        assert_eq!(
+            config.set_mint_limits(&Pubkey::new_unique(), 5, 10).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+    }
+
+    #[test]
+    fn balance_delta_mode_checks_what_arrived() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.set_mint_limits(&mint, 10, u64::MAX).unwrap();
+
+        // Without the mode the requested amount was already checked; any growth is fine.
+        config.check_received(&mint, 0, 6).unwrap();
+        config.set_balance_delta_mode(&mint, true).unwrap();
+        config.check_received(&mint, 10, 6).unwrap();
+        assert_eq!(
+            config.check_received(&mint, 9, 6).unwrap_err(),
+            CustomError::InsufficientBalanceDelta.into()
+        );
+        config.set_mint_limits(&mint, 0, u64::MAX).unwrap();
+        assert_eq!(
+            config.check_received(&mint, 0, 6).unwrap_err(),
+            CustomError::InsufficientBalanceDelta.into()
+        );
+        // Owner-bypass deposits of mints off the whitelist are not checked.
+        config.check_received(&Pubkey::new_unique(), 0, 6).unwrap();
+    }
+
+    #[test]
+    fn deposit_cooldown_counts_from_last_deposit() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.set_deposit_cooldown(&mint, 60).unwrap();
+        assert_eq!(config.whitelist_entry(&mint).unwrap().cooldown_secs, 60);
+        assert_eq!(
+            config.set_deposit_cooldown(&Pubkey::new_unique(), 60).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+
+        // A new account has no previous deposit to wait for.
+        let mut cooldown = DepositCooldown { last_deposit_ts: 0 };
+        assert_eq!(cooldown.remaining(60, 1_000), 0);
+
+        cooldown.last_deposit_ts = 1_000;
+        assert_eq!(cooldown.remaining(60, 1_045), 15);
+        assert_eq!(cooldown.remaining(60, 1_060), 0);
+        assert_eq!(cooldown.remaining(0, 1_000), 0);
+        assert_eq!(cooldown.remaining(u32::MAX, i64::MAX), 0);
+
+        // Validation sees the cooldown without recording anything.
+        let user = Pubkey::new_unique();
+        assert_eq!(
+            deposit_checks(&config, &user, &mint, 0, 1, 1, Some(&cooldown), 1_045).unwrap_err(),
+            CustomError::CooldownActive.into()
+        );
+        deposit_checks(&config, &user, &mint, 0, 1, 1, Some(&cooldown), 1_060).unwrap();
+        deposit_checks(&config, &user, &mint, 0, 1, 1, None, 1_045).unwrap();
+        assert_eq!(cooldown.last_deposit_ts, 1_000);
+
+        record_deposit_cooldown(&config, &mint, Some(&mut cooldown), 1_060).unwrap();
+        assert_eq!(cooldown.last_deposit_ts, 1_060);
+        assert_eq!(
+            record_deposit_cooldown(&config, &mint, None, 1_060).unwrap_err(),
+            CustomError::DepositCooldownMissing.into()
+        );
+        record_deposit_cooldown(&config, &Pubkey::new_unique(), None, 1_060).unwrap();
+    }
+
+    #[test]
+    fn epoch_cap_resets_when_a_new_epoch_begins() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        assert_eq!(
+            config.set_epoch_cap(&Pubkey::new_unique(), 60, 100).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+        // Without a cap, and for mints outside the whitelist, nothing is counted.
+        config.record_epoch_deposit(&mint, u64::MAX, 1_000).unwrap();
+        config.record_epoch_deposit(&Pubkey::new_unique(), u64::MAX, 1_000).unwrap();
+
+        config.set_epoch_cap(&mint, 60, 100).unwrap();
+        config.record_epoch_deposit(&mint, 70, 1_000).unwrap();
+        // Checking a deposit does not count it.
+        let user = Pubkey::new_unique();
+        deposit_checks(&config, &user, &mint, 0, 30, 30, None, 1_059).unwrap();
+        assert_eq!(
+            deposit_checks(&config, &user, &mint, 0, 31, 31, None, 1_059).unwrap_err(),
+            CustomError::EpochCapExceeded.into()
+        );
+        config.check_epoch_cap(&mint, 100, 1_060).unwrap();
+        assert_eq!(config.whitelist_entry(&mint).unwrap().epoch_used, 70);
+        config.record_epoch_deposit(&mint, 30, 1_059).unwrap();
+        assert_eq!(
+            config.record_epoch_deposit(&mint, 1, 1_059).unwrap_err(),
+            CustomError::EpochCapExceeded.into()
+        );
+        let entry = config.whitelist_entry(&mint).unwrap();
+        assert_eq!((entry.epoch_start_ts, entry.epoch_used), (1_000, 100));
+
+        // The first deposit after the epoch ended starts the next one.
+        config.record_epoch_deposit(&mint, 40, 1_075).unwrap();
+        let entry = config.whitelist_entry(&mint).unwrap();
+        assert_eq!((entry.epoch_start_ts, entry.epoch_used), (1_075, 40));
+        assert_eq!(
+            config.record_epoch_deposit(&mint, u64::MAX, 1_075).unwrap_err(),
+            CustomError::EpochCapExceeded.into()
+        );
+    }
+
+    #[test]
+    fn points_accrue_at_the_mint_rate() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        assert_eq!(
+            config.set_points_rate(&Pubkey::new_unique(), 1).unwrap_err(),
+            CustomError::TokenNotWhitelisted.into()
+        );
+        // Without a rate nothing accrues and no stats account is needed.
+        assert_eq!(accrue_deposit_points(&config, &mint, 1_000, None).unwrap(), 0);
+
+        config.set_points_rate(&mint, 2_500_000).unwrap();
+        assert_eq!(
+            accrue_deposit_points(&config, &mint, 1_000, None).unwrap_err(),
+            CustomError::UserStatsMissing.into()
+        );
+        let mut stats = UserStats { points: 0 };
+        assert_eq!(accrue_deposit_points(&config, &mint, 1_000, Some(&mut stats)).unwrap(), 2_500);
+        assert_eq!(accrue_deposit_points(&config, &mint, 1, Some(&mut stats)).unwrap(), 2);
+        assert_eq!(stats.points, 2_502);
+
+        // Rounds down and saturates instead of overflowing.
+        assert_eq!(deposit_points(999_999, 1), 0);
+        assert_eq!(deposit_points(u64::MAX, 1_000_000), u64::MAX);
+        assert_eq!(deposit_points(u64::MAX, u64::MAX), u64::MAX);
+        stats.points = u64::MAX - 1;
+        accrue_deposit_points(&config, &mint, 1_000, Some(&mut stats)).unwrap();
+        assert_eq!(stats.points, u64::MAX);
+    }
+
+    #[test]
+    fn frozen_whitelist_rejects_additions_and_removals() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.whitelist_frozen = true;
+
+        // Freezing binds the config itself, so the owner is refused like anyone else.
+        assert_eq!(
+            config.add_to_whitelist(Pubkey::new_unique()).unwrap_err(),
+            CustomError::WhitelistFrozen.into()
+        );
+        assert_eq!(
+            config.add_to_whitelist(mint).unwrap_err(),
+            CustomError::WhitelistFrozen.into()
+        );
+        assert_eq!(
+            config.remove_from_whitelist(&mint).unwrap_err(),
+            CustomError::WhitelistFrozen.into()
+        );
+        assert_eq!(config.whitelist.len(), 1);
+
+        // Deposits and limits keep working.
+        config.set_mint_limits(&mint, 5, 10).unwrap();
+        config.check_deposit(&mint, 7, 6).unwrap();
+    }
+
+    #[test]
+    fn renounce_ownership_requires_confirmation() {
+        let mint = Pubkey::new_unique();
+        let mut config = test_config(&[mint]);
+        config.owner_bypass_enabled = true;
+        config.schedule_admin_wallet(Pubkey::new_unique(), 0);
+
+        assert_eq!(
+            config.renounce_ownership(false).unwrap_err(),
+            CustomError::RenounceNotConfirmed.into()
+        );
+        assert!(!config.immutable);
+
+        config.renounce_ownership(true).unwrap();
+        assert!(config.immutable);
+        assert!(config.pending_admin_wallet.is_none());
+        assert!(!config.owner_bypass(&config.owner));
+        assert!(config.is_valid());
+        config.check_deposit(&mint, 1, 6).unwrap();
+    }
+
+    #[test]
+    fn excess_lamports_leave_the_account_rent_exempt() {
+        let rent = Rent::default();
+        let minimum = rent.minimum_balance(8 + DepositReceipt::LEN);
+        let (target_key, owner_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut target_lamports = minimum + 500;
+        let mut target_data = vec![0u8; 8 + DepositReceipt::LEN];
+        let target = AccountInfo::new(
+            &target_key,
+            false,
+            true,
+            &mut target_lamports,
+            &mut target_data,
+            &crate::ID,
+            false,
+            0,
+        );
+        let mut owner_lamports = 1_000;
+        let system_program = anchor_lang::system_program::ID;
+        let owner = AccountInfo::new(
+            &owner_key,
+            true,
+            true,
+            &mut owner_lamports,
+            &mut [],
+            &system_program,
+            false,
+            0,
+        );
+
+        assert_eq!(transfer_excess_lamports(&target, &owner, &rent).unwrap(), 500);
+        assert_eq!(target.lamports(), minimum);
+        assert_eq!(owner.lamports(), 1_500);
+        // Nothing is left to collect, and a second call moves nothing.
+        assert_eq!(transfer_excess_lamports(&target, &owner, &rent).unwrap(), 0);
+
+        let foreign = AccountInfo { owner: &system_program, ..owner.clone() };
+        assert_eq!(
+            transfer_excess_lamports(&foreign, &target, &rent).unwrap_err(),
+            CustomError::NotProgramAccount.into()
+        );
+    }
+
+    #[test]
+    fn deposit_callback_discriminator_follows_anchor_convention() {
+        let hash = hashv(&[b"global:robet_deposit_callback"]).to_bytes();
+        assert_eq!(DEPOSIT_CALLBACK_DISCRIMINATOR, hash[..8]);
+    }
+
+    #[test]
+    fn callback_accounts_follow_the_callback_program() {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = vec![0u64; keys.len()];
+        let mut data = vec![Vec::<u8>::new(); keys.len()];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, false, lamports, data, &crate::ID, false, 0)
+            })
+            .collect();
+
+        let (hook_accounts, callback) = split_callback_accounts(&accounts, None).unwrap();
+        assert_eq!(hook_accounts.len(), 4);
+        assert!(callback.is_none());
+
+        let (hook_accounts, callback) = split_callback_accounts(&accounts, Some(keys[1])).unwrap();
+        let (program, callback_accounts) = callback.unwrap();
+        assert_eq!(hook_accounts.len(), 1);
+        assert_eq!(program.key(), keys[1]);
+        assert_eq!(callback_accounts.len(), 2);
+
+        assert_eq!(
+            split_callback_accounts(&accounts, Some(Pubkey::new_unique())).unwrap_err(),
+            CustomError::CallbackProgramMissing.into()
+        );
+    }
+
+    #[test]
+    fn mint_extension_checks() {
+        use anchor_spl::token_2022::spl_token_2022::extension::{
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        };
+        use anchor_spl::token_2022::spl_token_2022::state::Mint as SplMint;
+        use anchor_spl::token_2022_extensions::spl_pod::optional_keys::OptionalNonZeroPubkey;
+        use anchor_lang::solana_program::program_pack::Pack;
+
+        let base_mint = SplMint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let mint_with = |extension: ExtensionType| {
+            let len = ExtensionType::try_calculate_account_len::<SplMint>(&[extension]).unwrap();
+            let mut data = vec![0u8; len];
+            let mut state =
+                StateWithExtensionsMut::<SplMint>::unpack_uninitialized(&mut data).unwrap();
+            match extension {
+                ExtensionType::NonTransferable => {
+                    state.init_extension::<NonTransferable>(true).unwrap();
+                }
+                ExtensionType::PermanentDelegate => {
+                    state.init_extension::<PermanentDelegate>(true).unwrap().delegate =
+                        OptionalNonZeroPubkey::try_from(Some(Pubkey::new_unique())).unwrap();
+                }
+                _ => {
+                    state.init_extension::<transfer_hook::TransferHook>(true).unwrap();
+                }
+            }
+            state.base = base_mint;
+            state.pack_base();
+            state.init_account_type().unwrap();
+            data
+        };
+
+        let mut plain = vec![0u8; SplMint::LEN];
+        SplMint::pack(base_mint, &mut plain).unwrap();
+        check_mint_extensions(&plain, true).unwrap();
+        check_mint_extensions(&mint_with(ExtensionType::TransferHook), true).unwrap();
+
+        let non_transferable = mint_with(ExtensionType::NonTransferable);
+        for reject_permanent_delegate in [false, true] {
+            assert_eq!(
+                check_mint_extensions(&non_transferable, reject_permanent_delegate).unwrap_err(),
+                CustomError::MintNotTransferable.into()
+            );
+        }
+
+        let permanent_delegate = mint_with(ExtensionType::PermanentDelegate);
+        check_mint_extensions(&permanent_delegate, false).unwrap();
+        assert_eq!(
+            check_mint_extensions(&permanent_delegate, true).unwrap_err(),
+            CustomError::MintHasPermanentDelegate.into()
+        );
+    }
+
+    #[test]
+    fn ui_amount_e9_applies_interest_and_decimals() {
+        use anchor_spl::token_2022::spl_token_2022::extension::{
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        };
+        use anchor_spl::token_2022::spl_token_2022::state::Mint as SplMint;
+        use anchor_lang::solana_program::program_pack::Pack;
+
+        let base_mint = SplMint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let mut plain = vec![0u8; SplMint::LEN];
+        SplMint::pack(base_mint, &mut plain).unwrap();
+        assert_eq!(ui_amount_e9(&plain, 1_500_000, 6, 0), 1_500_000_000);
+        assert_eq!(ui_amount_e9(&plain, 1_500_000, 0, 0), 1_500_000_000_000_000);
+        assert_eq!(ui_amount_e9(&plain, u64::MAX, 0, 0), u64::MAX);
+        assert_eq!(ui_amount_e9(&plain, 1_500_000, 12, 0), 1_500);
+        assert_eq!(ui_amount_e9(&plain, u64::MAX, u8::MAX, 0), 0);
+
+        let interest_mint = |rate: i16| {
+            let len = ExtensionType::try_calculate_account_len::<SplMint>(&[
+                ExtensionType::InterestBearingConfig,
+            ])
+            .unwrap();
+            let mut data = vec![0u8; len];
+            let mut state =
+                StateWithExtensionsMut::<SplMint>::unpack_uninitialized(&mut data).unwrap();
+            let config = state.init_extension::<InterestBearingConfig>(true).unwrap();
+            config.pre_update_average_rate = rate.into();
+            config.current_rate = rate.into();
+            state.base = base_mint;
+            state.pack_base();
+            state.init_account_type().unwrap();
+            data
+        };
+        let year = 31_556_736;
+
+        // Without a rate or elapsed time the UI amount only shifts by decimals.
+        assert_eq!(ui_amount_e9(&interest_mint(0), 1_000_000, 6, year), 1_000_000_000);
+        assert_eq!(ui_amount_e9(&interest_mint(500), 1_000_000, 6, 0), 1_000_000_000);
+        // 5% compounded continuously for a year is e^0.05.
+        let accrued = ui_amount_e9(&interest_mint(500), 1_000_000, 6, year);
+        assert!(accrued.abs_diff(1_051_271_096) < 10, "{accrued}");
+        let lost = ui_amount_e9(&interest_mint(-500), 1_000_000, 6, year);
+        assert!(lost.abs_diff(951_229_424) < 10, "{lost}");
+        // An extreme rate over a long time saturates instead of panicking.
+        assert_eq!(ui_amount_e9(&interest_mint(i16::MAX), u64::MAX, 0, i64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn transfer_hook_program_id_detects_extension() {
+        use anchor_spl::token_2022::spl_token_2022::extension::{
+            transfer_hook::TransferHook, BaseStateWithExtensionsMut, ExtensionType,
+            StateWithExtensionsMut,
+        };
+        use anchor_spl::token_2022::spl_token_2022::state::Mint as SplMint;
+        use anchor_spl::token_2022_extensions::spl_pod::optional_keys::OptionalNonZeroPubkey;
+        use anchor_lang::solana_program::program_pack::Pack;
+
+        let base_mint = SplMint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+
+        // A plain mint (legacy layout) has no hook.
+        let mut plain = vec![0u8; SplMint::LEN];
+        SplMint::pack(base_mint, &mut plain).unwrap();
+        assert_eq!(transfer_hook_program_id(&plain), None);
+
+        // A Token-2022 mint carrying the transfer-hook extension reports its program.
+        let hook_program = Pubkey::new_unique();
+        let len =
+            ExtensionType::try_calculate_account_len::<SplMint>(&[ExtensionType::TransferHook])
+                .unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<SplMint>::unpack_uninitialized(&mut data).unwrap();
+        state.init_extension::<TransferHook>(true).unwrap().program_id =
+            OptionalNonZeroPubkey::try_from(Some(hook_program)).unwrap();
+        state.base = base_mint;
+        state.pack_base();
+        state.init_account_type().unwrap();
+        assert_eq!(transfer_hook_program_id(&data), Some(hook_program));
+    }
+
+    #[test]
+    fn is_valid_rejects_inconsistent_configs() {
+        let mut config = test_config(&[Pubkey::new_unique()]);
+        assert!(config.is_valid());
+
+        config.whitelist = (0..=Config::MAX_WHITELIST)
+            .map(|_| WhitelistEntry::new(Pubkey::new_unique()))
+            .collect();
+        assert!(!config.is_valid());
+
+        let mut config = test_config(&[]);
+        config.owner = Pubkey::default();
+        assert!(!config.is_valid());
+
+        let mut config = test_config(&[]);
+        config.min_decimals = 9;
+        config.max_decimals = 6;
+        assert!(!config.is_valid());
+    }
+
+    #[test]
+    fn truncated_config_fails_to_deserialize() {
+        let config = test_config(&[Pubkey::new_unique(), Pubkey::new_unique()]);
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+
+        for len in 0..data.len() {
+            assert!(Config::try_deserialize(&mut &data[..len]).is_err());
+        }
+        assert!(Config::try_deserialize(&mut &data[..]).unwrap().is_valid());
+    }
+
+    #[test]
+    fn whitelist_stays_sorted_across_inserts_and_removals() {
+        let mut config = test_config(&[]);
+        let mints: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        for mint in mints.iter().rev() {
+            config.add_to_whitelist(*mint).unwrap();
+        }
+        config.add_to_whitelist(mints[2]).unwrap();
+        assert_eq!(config.whitelist.len(), mints.len());
+        assert!(config.whitelist.windows(2).all(|w| w[0].mint < w[1].mint));
+
+        assert!(config.remove_from_whitelist(&mints[3]).unwrap().is_some());
+        assert!(config.remove_from_whitelist(&mints[3]).unwrap().is_none());
+        assert!(config.whitelist.windows(2).all(|w| w[0].mint < w[1].mint));
+        assert!(config.whitelist_entry(&mints[3]).is_none());
+        assert!(mints
+            .iter()
+            .filter(|mint| **mint != mints[3])
+            .all(|mint| config.whitelist_entry(mint).is_some()));
+    }
+
+    #[test]
+    fn sort_whitelist_rebuilds_legacy_order() {
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut config = test_config(&[]);
+        // Legacy configs appended in insertion order and could hold duplicates.
+        config.whitelist = [mints[2], mints[0], mints[3], mints[0], mints[1]]
+            .into_iter()
+            .map(WhitelistEntry::new)
+            .collect();
+
+        config.sort_whitelist();
+        let sorted: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+        let mut expected = mints.clone();
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn privileged_instructions_are_audited() {
+        let source = include_str!("lib.rs").split("#[cfg(test)]").next().unwrap();
+        // Contexts whose signer is checked against the owner, manager or admin wallet.
+        let privileged: Vec<&str> = source
+            .split("#[derive(Accounts)]")
+            .skip(1)
+            .filter_map(|item| {
+                let item = item.split("\n}\n").next()?;
+                let name = item.split("pub struct ").nth(1)?.split('<').next()?;
+                ["has_one = owner", "can_manage_whitelist(", "is_privileged("]
+                    .iter()
+                    .any(|check| item.contains(check))
+                    .then_some(name)
+            })
+            .collect();
+        for context in ["UpdateConfig", "ManageWhitelist", "CollectExcessLamports"] {
+            assert!(privileged.contains(&context));
+        }
+        for context in &privileged {
+            let implemented = format!("impl Audited for {context}<'_>");
+            assert!(source.contains(&implemented), "{context} does not implement Audited");
+        }
+
+        let program = source.split("pub mod robet {").nth(1).unwrap();
+        let program = program.split("\n}\n").next().unwrap();
+        for handler in program.split("\n    pub fn ").skip(1) {
+            let (name, rest) = handler.split_once(['(', '<']).unwrap();
+            let (signature, body) = rest.split_once(" {\n").unwrap();
+            let body = body.split("\n    }\n").next().unwrap();
+            let takes = |context: &&&str| {
+                signature.contains(&format!("Context<{context}>"))
+                    || signature.contains(&format!("{context}<'info>"))
+            };
+            if let Some(context) = privileged.iter().find(takes) {
+                assert!(
+                    body.contains(".audit(AuditAction::"),
+                    "{name} takes {context} but never calls audit"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn migrate_config_reads_original_layout() {
+        use anchor_lang::Discriminator;
+
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let (owner, admin_wallet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // The account as the original `initialize_config` and `add_whitelisted_token` left
+        // it: 8 + 388 bytes, mints in insertion order and possibly duplicated.
+        let legacy_mints = [mints[2], mints[0], mints[3], mints[0], mints[1]];
+        let mut data = Config::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(owner.as_ref());
+        data.extend_from_slice(admin_wallet.as_ref());
+        data.extend_from_slice(&(legacy_mints.len() as u32).to_le_bytes());
+        for mint in &legacy_mints {
+            data.extend_from_slice(mint.as_ref());
+        }
+        data.resize(8 + 388, 0);
+        assert!(Config::try_deserialize(&mut data.as_slice()).is_err());
+
+        let config = LegacyConfig::from_account_data(&data).unwrap().migrate().unwrap();
+        assert_eq!((config.owner, config.admin_wallet), (owner, admin_wallet));
+        assert_eq!(config.whitelist_manager, owner);
+        let whitelist: Vec<Pubkey> = config.whitelist.iter().map(|entry| entry.mint).collect();
+        let mut expected = mints.clone();
+        expected.sort();
+        assert_eq!(whitelist, expected);
+        assert!(config.whitelist.iter().all(|entry| *entry == WhitelistEntry::new(entry.mint)));
+
+        // Written into the reallocated account, it loads as a current config.
+        let mut migrated = vec![0; 8 + Config::LEN];
+        config.try_serialize(&mut migrated.as_mut_slice()).unwrap();
+        let loaded = Config::try_deserialize(&mut migrated.as_slice()).unwrap();
+        assert!(loaded.is_valid());
+        assert_eq!(loaded.whitelist, config.whitelist);
+
+        // A migrated config, or any other account, is refused.
+        let not_legacy = CustomError::NotLegacyConfig.into();
+        assert_eq!(LegacyConfig::from_account_data(&migrated).unwrap_err(), not_legacy);
+        data[0] ^= 1;
+        assert_eq!(LegacyConfig::from_account_data(&data).unwrap_err(), not_legacy);
+    }
+}
+
+#[cfg(test)]
+mod fuzz {
+    use super::*;
+    use anchor_lang::Discriminator;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn config_deserialization_never_panics(data in proptest::collection::vec(any::<u8>(), 0..1024)) {
+            let _ = Config::try_deserialize(&mut data.as_slice());
+        }
+
+        #[test]
+        fn config_with_valid_discriminator_never_panics(
+            data in proptest::collection::vec(any::<u8>(), 0..Config::LEN * 2)
+        ) {
+            let mut buf = Config::DISCRIMINATOR.to_vec();
+            buf.extend_from_slice(&data);
+            if let Ok(config) = Config::try_deserialize(&mut buf.as_slice()) {
+                // Whatever decodes must still be checked before use.
+                let _ = config.is_valid();
+            }
+        }
+    }
 }
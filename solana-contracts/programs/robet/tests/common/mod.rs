@@ -0,0 +1,470 @@
+//! A minimal in-process runtime for program-level tests.
+//!
+//! Programs run natively, like `solana-program-test` without BPF: the syscall stubs of
+//! `solana_program` are replaced so that CPIs dispatch to the registered processors,
+//! `emit!` data is captured, and the clock, rent and stack height are served from here.
+//! Accounts are shared between caller and callee rather than copied, so a processor must
+//! drop its borrows before invoking another program, as the on-chain helpers already do.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, BorrowedAccountMeta, BorrowedInstruction,
+};
+use anchor_lang::{system_program, Discriminator, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::{Account as SplAccount, Mint as SplMint},
+};
+use robet::{Config, WhitelistEntry};
+
+/// The entrypoint of a program registered with the runtime.
+pub type Processor = for<'a> fn(&Pubkey, &'a [AccountInfo<'a>], &[u8]) -> ProgramResult;
+
+/// Balance of the user created by `Runtime::setup_deposit`.
+pub const DEPOSITOR_BALANCE: u64 = 1_000;
+
+/// Timestamp served by the clock sysvar.
+pub const NOW: i64 = 1_700_000_000;
+
+/// Serializes the tests of a binary, since the syscall stubs are process-wide.
+static RUNTIME_LOCK: Mutex<()> = Mutex::new(());
+
+/// State shared with the syscall stubs.
+struct Shared {
+    programs: HashMap<Pubkey, Processor>,
+    /// Programs currently executing, the top-level one first.
+    call_stack: Mutex<Vec<Pubkey>>,
+    /// Every `sol_log_data` field logged so far.
+    logged_data: Mutex<Vec<Vec<u8>>>,
+}
+
+impl Shared {
+    fn execute<'a>(
+        &self,
+        program_id: &Pubkey,
+        accounts: Vec<AccountInfo<'a>>,
+        data: &[u8],
+    ) -> ProgramResult {
+        let processor = self
+            .programs
+            .get(program_id)
+            .ok_or(ProgramError::IncorrectProgramId)?;
+        // Processors need the slice to live as long as the account references; leaking it
+        // is fine for the lifetime of a test.
+        let accounts: &'a [AccountInfo<'a>] = Box::leak(accounts.into_boxed_slice());
+        self.call_stack.lock().unwrap().push(*program_id);
+        let result = processor(program_id, accounts, data);
+        self.call_stack.lock().unwrap().pop();
+        result
+    }
+}
+
+struct Stubs(Arc<Shared>);
+
+impl SyscallStubs for Stubs {
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = *self.0.call_stack.lock().unwrap().last().unwrap();
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut accounts = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+            // The callee may only get privileges the caller holds or signs for.
+            if meta.is_signer && !info.is_signer && !signers.contains(info.key) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            accounts.push(info);
+        }
+        self.0
+            .execute(&instruction.program_id, accounts, &instruction.data)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: 42,
+            unix_timestamp: NOW,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        anchor_lang::solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        anchor_lang::solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.call_stack.lock().unwrap().len() as u64
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let mut logged_data = self.0.logged_data.lock().unwrap();
+        logged_data.extend(fields.iter().map(|field| field.to_vec()));
+    }
+}
+
+/// Accounts and programs of a test, executed one top-level instruction at a time.
+pub struct Runtime {
+    shared: Arc<Shared>,
+    accounts: HashMap<Pubkey, AccountInfo<'static>>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Runtime {
+    /// Installs a runtime running `programs`, each also added as an executable account.
+    pub fn new(programs: &[(Pubkey, Processor)]) -> Self {
+        let lock = RUNTIME_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let shared = Arc::new(Shared {
+            programs: programs.iter().copied().collect(),
+            call_stack: Mutex::new(Vec::new()),
+            logged_data: Mutex::new(Vec::new()),
+        });
+        set_syscall_stubs(Box::new(Stubs(Arc::clone(&shared))));
+
+        let mut runtime = Self {
+            shared,
+            accounts: HashMap::new(),
+            _lock: lock,
+        };
+        for (program_id, _) in programs {
+            runtime.insert(*program_id, Pubkey::default(), 1, Vec::new(), true);
+        }
+        runtime
+    }
+
+    fn insert(
+        &mut self,
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        executable: bool,
+    ) {
+        let info = AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            false,
+            Box::leak(Box::new(lamports)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            executable,
+            0,
+        );
+        self.accounts.insert(key, info);
+    }
+
+    /// Adds a rent-exempt account holding `data`.
+    pub fn add_account(&mut self, key: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        let lamports = Rent::default().minimum_balance(data.len());
+        self.insert(key, owner, lamports, data, false);
+    }
+
+    /// Returns a copy of the data of `key`.
+    pub fn data(&self, key: &Pubkey) -> Vec<u8> {
+        self.accounts[key].data.borrow().to_vec()
+    }
+
+    /// Executes `instruction` as a top-level instruction of its own transaction, with the
+    /// privileges its account metas request.
+    pub fn process(&mut self, instruction: &Instruction) -> ProgramResult {
+        if instruction
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == instructions_sysvar::ID)
+        {
+            self.insert_instructions_sysvar(instruction);
+        }
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                let mut info = self
+                    .accounts
+                    .get(&meta.pubkey)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)?
+                    .clone();
+                info.is_signer = meta.is_signer;
+                info.is_writable = meta.is_writable;
+                Ok(info)
+            })
+            .collect::<std::result::Result<Vec<_>, ProgramError>>()?;
+        self.shared
+            .execute(&instruction.program_id, accounts, &instruction.data)
+    }
+
+    fn insert_instructions_sysvar(&mut self, instruction: &Instruction) {
+        let borrowed = BorrowedInstruction {
+            program_id: &instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &instruction.data,
+        };
+        let mut data = instructions_sysvar::construct_instructions_data(&[borrowed]);
+        instructions_sysvar::store_current_index(&mut data, 0);
+        self.add_account(
+            instructions_sysvar::ID,
+            anchor_lang::solana_program::sysvar::ID,
+            data,
+        );
+    }
+
+    /// Decodes every `T` emitted so far.
+    pub fn events<T: Discriminator + AnchorDeserialize>(&self) -> Vec<T> {
+        self.shared
+            .logged_data
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|data| data.strip_prefix(&T::DISCRIMINATOR))
+            .map(|mut data| T::deserialize(&mut data).unwrap())
+            .collect()
+    }
+
+    /// Creates an initialized Token-2022 mint, with a transfer hook if `hook_program` is set.
+    pub fn create_mint(
+        &mut self,
+        mint: Pubkey,
+        authority: &Pubkey,
+        decimals: u8,
+        hook_program: Option<Pubkey>,
+    ) {
+        let extensions: &[ExtensionType] = match hook_program {
+            Some(_) => &[ExtensionType::TransferHook],
+            None => &[],
+        };
+        let len = ExtensionType::try_calculate_account_len::<SplMint>(extensions).unwrap();
+        self.add_account(mint, spl_token_2022::ID, vec![0; len]);
+        if hook_program.is_some() {
+            let instruction = spl_token_2022::extension::transfer_hook::instruction::initialize(
+                &spl_token_2022::ID,
+                &mint,
+                None,
+                hook_program,
+            )
+            .unwrap();
+            self.process(&instruction).unwrap();
+        }
+        let instruction = spl_token_2022::instruction::initialize_mint2(
+            &spl_token_2022::ID,
+            &mint,
+            authority,
+            None,
+            decimals,
+        )
+        .unwrap();
+        self.process(&instruction).unwrap();
+    }
+
+    /// Creates a token account of `mint` for `owner` holding `amount`, minted by
+    /// `mint_authority`. `key` defaults to the owner's associated token account.
+    pub fn create_token_account(
+        &mut self,
+        key: Option<Pubkey>,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        mint_authority: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let key = key.unwrap_or_else(|| {
+            get_associated_token_address_with_program_id(owner, mint, &spl_token_2022::ID)
+        });
+        let extensions = {
+            let mint_data = self.accounts[mint].data.borrow();
+            let mint_state = StateWithExtensions::<SplMint>::unpack(&mint_data).unwrap();
+            ExtensionType::get_required_init_account_extensions(
+                &mint_state.get_extension_types().unwrap(),
+            )
+        };
+        let len = ExtensionType::try_calculate_account_len::<SplAccount>(&extensions).unwrap();
+        self.add_account(key, spl_token_2022::ID, vec![0; len]);
+        let instruction = spl_token_2022::instruction::initialize_account3(
+            &spl_token_2022::ID,
+            &key,
+            mint,
+            owner,
+        )
+        .unwrap();
+        self.process(&instruction).unwrap();
+        if amount > 0 {
+            let instruction = spl_token_2022::instruction::mint_to(
+                &spl_token_2022::ID,
+                mint,
+                &key,
+                mint_authority,
+                &[],
+                amount,
+            )
+            .unwrap();
+            self.process(&instruction).unwrap();
+        }
+        key
+    }
+
+    /// Returns the balance of the token account `key`.
+    pub fn token_amount(&self, key: &Pubkey) -> u64 {
+        let data = self.data(key);
+        StateWithExtensions::<SplAccount>::unpack(&data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    /// Creates a valid config owned by the program that whitelists `mints` without limits.
+    pub fn create_config(
+        &mut self,
+        key: Pubkey,
+        owner: Pubkey,
+        admin_wallet: Pubkey,
+        mints: &[Pubkey],
+        feature_flags: u64,
+    ) {
+        let mut whitelist: Vec<WhitelistEntry> =
+            mints.iter().copied().map(WhitelistEntry::new).collect();
+        whitelist.sort_by_key(|entry| entry.mint);
+        let config = Config {
+            owner,
+            admin_wallet,
+            whitelist,
+            min_decimals: 0,
+            max_decimals: u8::MAX,
+            whitelist_manager: Pubkey::default(),
+            deposit_seq: 0,
+            owner_bypass_enabled: false,
+            admin_change_delay: 0,
+            pending_admin_wallet: None,
+            reject_permanent_delegate: false,
+            whitelist_frozen: false,
+            immutable: false,
+            callback_program: None,
+            feature_flags,
+        };
+        let mut data = Vec::with_capacity(8 + Config::LEN);
+        config.try_serialize(&mut data).unwrap();
+        data.resize(8 + Config::LEN, 0);
+        self.add_account(key, robet::ID, data);
+    }
+
+    /// Creates a Token-2022 mint, with a transfer hook if `hook_program` is set, a config
+    /// whitelisting it with `feature_flags`, and a user holding `DEPOSITOR_BALANCE` of it.
+    pub fn setup_deposit(&mut self, hook_program: Option<Pubkey>, feature_flags: u64) -> Deposit {
+        let user = Pubkey::new_unique();
+        let admin_wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let config = Pubkey::new_unique();
+        self.add_account(user, system_program::ID, Vec::new());
+        self.create_mint(mint, &user, 6, hook_program);
+        let user_token_account = self.create_token_account(
+            Some(Pubkey::new_unique()),
+            &mint,
+            &user,
+            &user,
+            DEPOSITOR_BALANCE,
+        );
+        let admin_token_account = self.create_token_account(None, &mint, &admin_wallet, &user, 0);
+        self.create_config(
+            config,
+            Pubkey::new_unique(),
+            admin_wallet,
+            &[mint],
+            feature_flags,
+        );
+        Deposit {
+            user,
+            config,
+            mint,
+            user_token_account,
+            admin_token_account,
+        }
+    }
+
+    /// Returns the config stored at `key`.
+    pub fn config(&self, key: &Pubkey) -> Config {
+        Config::try_deserialize(&mut self.data(key).as_slice()).unwrap()
+    }
+}
+
+/// The accounts of a deposit created by `Runtime::setup_deposit`.
+pub struct Deposit {
+    pub user: Pubkey,
+    pub config: Pubkey,
+    pub mint: Pubkey,
+    pub user_token_account: Pubkey,
+    pub admin_token_account: Pubkey,
+}
+
+impl Deposit {
+    /// Builds a `deposit_token` instruction of `amount`, passing the instructions sysvar if
+    /// `instructions` is set, followed by `remaining_accounts`.
+    pub fn instruction(
+        &self,
+        amount: u64,
+        instructions: bool,
+        remaining_accounts: &[AccountMeta],
+    ) -> Instruction {
+        let mut accounts = robet::accounts::DepositToken {
+            user: self.user,
+            config: self.config,
+            token_mint: self.mint,
+            user_token_account: self.user_token_account,
+            admin_token_account: self.admin_token_account,
+            token_program: spl_token_2022::ID,
+            receipt_mint: None,
+            user_receipt_account: None,
+            memo_program: None,
+            deposit_receipt: None,
+            system_program: None,
+            global_stats: None,
+            instructions: instructions.then_some(instructions_sysvar::ID),
+            deposit_cooldown: None,
+            user_stats: None,
+        }
+        .to_account_metas(None);
+        accounts.extend_from_slice(remaining_accounts);
+        Instruction {
+            program_id: robet::ID,
+            accounts,
+            data: robet::instruction::DepositToken {
+                amount,
+                beneficiary: None,
+                memo: String::new(),
+            }
+            .data(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Deposits made through CPI from proxy programs.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_spl::token_2022::spl_token_2022;
+use common::{Processor, Runtime, DEPOSITOR_BALANCE};
+use robet::{CustomError, DepositEvent, FEATURE_REJECT_CPI_DEPOSITS};
+
+const PROXY: Pubkey = Pubkey::new_from_array([7; 32]);
+const OUTER_PROXY: Pubkey = Pubkey::new_from_array([8; 32]);
+
+/// Invokes `program_id` with the instruction data and accounts this program received.
+fn forward(program_id: Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let metas = accounts
+        .iter()
+        .map(|info| match info.is_writable {
+            true => AccountMeta::new(*info.key, info.is_signer),
+            false => AccountMeta::new_readonly(*info.key, info.is_signer),
+        })
+        .collect();
+    let instruction = Instruction {
+        program_id,
+        accounts: metas,
+        data: data.to_vec(),
+    };
+    invoke(&instruction, accounts)
+}
+
+fn proxy(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    forward(robet::ID, accounts, data)
+}
+
+fn outer_proxy(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    forward(PROXY, accounts, data)
+}
+
+fn runtime() -> Runtime {
+    Runtime::new(&[
+        (robet::ID, robet::entry as Processor),
+        (
+            spl_token_2022::ID,
+            spl_token_2022::processor::Processor::process,
+        ),
+        (PROXY, proxy),
+        (OUTER_PROXY, outer_proxy),
+    ])
+}
+
+fn cpi_error(error: CustomError) -> ProgramResult {
+    Err(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+#[test]
+fn deposit_through_proxy_reports_via_program() {
+    let mut runtime = runtime();
+    let deposit = runtime.setup_deposit(None, 0);
+
+    let mut instruction = deposit.instruction(400, true, &[]);
+    instruction.program_id = PROXY;
+    runtime.process(&instruction).unwrap();
+
+    assert_eq!(
+        runtime.token_amount(&deposit.user_token_account),
+        DEPOSITOR_BALANCE - 400
+    );
+    assert_eq!(runtime.token_amount(&deposit.admin_token_account), 400);
+    assert_eq!(runtime.config(&deposit.config).deposit_seq, 1);
+    let events = runtime.events::<DepositEvent>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].user, deposit.user);
+    assert_eq!(events[0].amount, 400);
+    assert_eq!(events[0].via_program, Some(PROXY));
+}
+
+#[test]
+fn nested_proxies_report_the_top_level_program() {
+    let mut runtime = runtime();
+    let deposit = runtime.setup_deposit(None, 0);
+
+    let mut instruction = deposit.instruction(400, true, &[]);
+    instruction.program_id = OUTER_PROXY;
+    runtime.process(&instruction).unwrap();
+
+    // PROXY invoked the program, but only the top-level instruction is visible.
+    let events = runtime.events::<DepositEvent>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].via_program, Some(OUTER_PROXY));
+}
+
+#[test]
+fn deposit_through_proxy_requires_instructions_sysvar() {
+    let mut runtime = runtime();
+    let deposit = runtime.setup_deposit(None, 0);
+
+    let mut instruction = deposit.instruction(400, false, &[]);
+    instruction.program_id = PROXY;
+    assert_eq!(
+        runtime.process(&instruction),
+        cpi_error(CustomError::InstructionsSysvarMissing)
+    );
+    assert!(runtime.events::<DepositEvent>().is_empty());
+}
+
+#[test]
+fn deposit_through_proxy_fails_when_cpi_deposits_are_rejected() {
+    let mut runtime = runtime();
+    let deposit = runtime.setup_deposit(None, FEATURE_REJECT_CPI_DEPOSITS);
+
+    let mut instruction = deposit.instruction(400, true, &[]);
+    instruction.program_id = PROXY;
+    assert_eq!(
+        runtime.process(&instruction),
+        cpi_error(CustomError::CpiDepositsDisabled)
+    );
+    assert_eq!(
+        runtime.token_amount(&deposit.user_token_account),
+        DEPOSITOR_BALANCE
+    );
+
+    // Direct deposits are still accepted.
+    runtime
+        .process(&deposit.instruction(400, false, &[]))
+        .unwrap();
+    let events = runtime.events::<DepositEvent>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].via_program, None);
+}
@@ -0,0 +1,119 @@
+//! Deposits of a Token-2022 mint with a transfer hook, run through the real Token-2022
+//! processor and a stub hook program.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions},
+    state::Account as SplAccount,
+};
+use common::{Processor, Runtime, DEPOSITOR_BALANCE, NOW};
+use robet::DepositEvent;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+use spl_transfer_hook_interface::{error::TransferHookError, get_extra_account_metas_address};
+
+/// A transfer hook that adds every transferred amount to the counter account listed as
+/// its only extra account, and only while Token-2022 is in the middle of the transfer.
+fn counting_hook(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let TransferHookInstruction::Execute { amount } = TransferHookInstruction::unpack(data)? else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    ExtraAccountMetaList::check_account_infos::<ExecuteInstruction>(
+        accounts,
+        data,
+        program_id,
+        &accounts[4].try_borrow_data()?,
+    )?;
+    let source_data = accounts[0].try_borrow_data()?;
+    let source = StateWithExtensions::<SplAccount>::unpack(&source_data)?;
+    if !bool::from(source.get_extension::<TransferHookAccount>()?.transferring) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut counter = accounts[5].try_borrow_mut_data()?;
+    let total = u64::from_le_bytes(counter[..8].try_into().unwrap()) + amount;
+    counter[..8].copy_from_slice(&total.to_le_bytes());
+    Ok(())
+}
+
+fn runtime(hook_program: Pubkey) -> Runtime {
+    Runtime::new(&[
+        (robet::ID, robet::entry as Processor),
+        (
+            spl_token_2022::ID,
+            spl_token_2022::processor::Processor::process,
+        ),
+        (hook_program, counting_hook),
+    ])
+}
+
+#[test]
+fn deposit_invokes_transfer_hook_with_extra_accounts() {
+    let hook_program = Pubkey::new_unique();
+    let mut runtime = runtime(hook_program);
+    let deposit = runtime.setup_deposit(Some(hook_program), 0);
+
+    // The hook's validation account lists the counter as a writable extra account.
+    let counter = Pubkey::new_unique();
+    runtime.add_account(counter, hook_program, vec![0; 8]);
+    let validation = get_extra_account_metas_address(&deposit.mint, &hook_program);
+    let mut validation_data = vec![0; ExtraAccountMetaList::size_of(1).unwrap()];
+    ExtraAccountMetaList::init::<ExecuteInstruction>(
+        &mut validation_data,
+        &[ExtraAccountMeta::new_with_pubkey(&counter, false, true).unwrap()],
+    )
+    .unwrap();
+    runtime.add_account(validation, hook_program, validation_data);
+
+    let hook_accounts = [
+        AccountMeta::new_readonly(validation, false),
+        AccountMeta::new_readonly(hook_program, false),
+        AccountMeta::new(counter, false),
+    ];
+    runtime
+        .process(&deposit.instruction(400, false, &hook_accounts))
+        .unwrap();
+
+    assert_eq!(
+        runtime.token_amount(&deposit.user_token_account),
+        DEPOSITOR_BALANCE - 400
+    );
+    assert_eq!(runtime.token_amount(&deposit.admin_token_account), 400);
+    assert_eq!(runtime.data(&counter), 400u64.to_le_bytes());
+    assert_eq!(runtime.config(&deposit.config).deposit_seq, 1);
+
+    let events = runtime.events::<DepositEvent>();
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.user, deposit.user);
+    assert_eq!(event.token_mint, deposit.mint);
+    assert_eq!(event.amount, 400);
+    assert_eq!(event.received, 400);
+    assert_eq!(event.token_program, spl_token_2022::ID);
+    assert_eq!(event.timestamp, NOW);
+    assert_eq!(event.deposit_seq, 1);
+    assert_eq!(event.via_program, None);
+}
+
+#[test]
+fn deposit_without_hook_accounts_fails_before_transfer() {
+    let hook_program = Pubkey::new_unique();
+    let mut runtime = runtime(hook_program);
+    let deposit = runtime.setup_deposit(Some(hook_program), 0);
+
+    // The validation account cannot be found among the remaining accounts.
+    assert_eq!(
+        runtime.process(&deposit.instruction(400, false, &[])),
+        Err(TransferHookError::IncorrectAccount.into())
+    );
+    assert_eq!(
+        runtime.token_amount(&deposit.user_token_account),
+        DEPOSITOR_BALANCE
+    );
+    assert!(runtime.events::<DepositEvent>().is_empty());
+}